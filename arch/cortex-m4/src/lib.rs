@@ -85,6 +85,40 @@ _ggeneric_isr_no_stacking:
     movt LR, #0xFFFF");
 }
 
+#[cfg(not(target_os = "none"))]
+pub unsafe extern "C" fn hard_fault_handler() {}
+
+#[cfg(target_os = "none")]
+#[no_mangle]
+#[naked]
+/// Faults raised while a process is running are recorded in `APP_FAULT` and
+/// control returns to the kernel, which decides what to do with the process.
+/// Faults in the kernel itself are passed on to `hard_fault_handler_kernel`,
+/// defined by the chip.
+pub unsafe extern "C" fn hard_fault_handler() {
+    asm!("
+    /* Only faults coming from user-space can be attributed to a process */
+    cmp lr, #0xfffffffd
+    bne hard_fault_handler_kernel
+
+    mrs r0, PSP /* PSP into r0 */
+
+    /* Push non-hardware-stacked registers onto Process stack */
+    /* r0 points to user stack (see to_kernel) */
+    stmdb r0, {r4-r11}
+
+    ldr r0, =APP_FAULT
+    mov r1, #1
+    str r1, [r0, #0]
+
+    /* Set thread mode to privileged */
+    mov r0, #0
+    msr CONTROL, r0
+
+    movw LR, #0xFFF9
+    movt LR, #0xFFFF");
+}
+
 #[cfg(not(target_os = "none"))]
 #[allow(non_snake_case)]
 pub unsafe extern "C" fn SVC_Handler() {}
@@ -1,4 +1,5 @@
 use kernel;
+use kernel::mpu::{Permissions, Region};
 use kernel::common::volatile_cell::VolatileCell;
 
 /// Indicates whether the MPU is present and, if so, how many regions it
@@ -84,10 +85,27 @@ impl kernel::MPU for MPU {
         regs.control.set(0b101);
     }
 
-    fn set_mpu(&self, region_num: u32, start_addr: u32, len: u32, execute: bool, ap: u32) {
+    fn set_region(&self, region_num: u32, region: Region) {
         let regs = unsafe { &*self.0 };
-        regs.region_base_address.set(region_num | 1 << 4 | start_addr);
-        let xn = if execute { 0 } else { 1 };
-        regs.region_attributes_and_size.set(1 | len << 1 | ap << 24 | xn << 28);
+        // Access permissions (Table 4.47 of the user guide) are full access,
+        // read-only or no access for unprivileged code, and always read/write
+        // for the kernel.
+        let (ap, xn) = match region.permissions() {
+            Permissions::ReadWriteExecute => (0b011, 0),
+            Permissions::ReadWriteOnly => (0b011, 1),
+            Permissions::ReadExecuteOnly => (0b111, 0),
+            Permissions::ReadOnly => (0b111, 1),
+            Permissions::NoAccess => (0b001, 1),
+        };
+        let size = region.size().trailing_zeros() - 1;
+        let srd = region.subregion_mask() as u32;
+        regs.region_base_address.set(region_num | 1 << 4 | region.base() as u32);
+        regs.region_attributes_and_size.set(1 | size << 1 | srd << 8 | ap << 24 | xn << 28);
+    }
+
+    fn disable_region(&self, region_num: u32) {
+        let regs = unsafe { &*self.0 };
+        regs.region_base_address.set(region_num | 1 << 4);
+        regs.region_attributes_and_size.set(0);
     }
 }
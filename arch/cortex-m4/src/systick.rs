@@ -22,7 +22,10 @@ impl SysTick {
 impl kernel::SysTick for SysTick {
     fn set_timer(&self, us: u32) {
         let tenms = self.calibration.get() & 0xffffff;
-        let reload = tenms * us / 10000;
+        // Whole milliseconds and the rest are scaled separately, so intervals
+        // up to the 24-bit limit don't overflow.
+        let reload = tenms * (us / 1000) / 10 + tenms * (us % 1000) / 10000;
+        let reload = if reload > 0xffffff { 0xffffff } else { reload };
 
         self.value.set(0);
         self.reload.set(reload);
@@ -32,7 +35,8 @@ impl kernel::SysTick for SysTick {
         let tenms = self.calibration.get() & 0xffffff;
         let value = self.value.get() & 0xffffff;
 
-        value * 10000 / tenms
+        // Scaled in two parts, as in `set_timer`, to avoid overflowing.
+        value / tenms * 10000 + value % tenms * 100 / (tenms / 100)
     }
 
     fn overflowed(&self) -> bool {
@@ -73,6 +73,9 @@ unsafe fn load_process() -> &'static mut [Option<kernel::process::Process<'stati
     static mut MEMORY: [u8; 8192] = [0; 8192];
     static mut PROCS: [Option<kernel::process::Process<'static>>; 1] = [None];
 
+    /// Number of callbacks that can be pending for the process.
+    const CALLBACK_DEPTH: usize = 10;
+
     let addr = &_sapps as *const u8;
 
     // The first member of the LoadInfo header contains the total size of
@@ -81,8 +84,14 @@ unsafe fn load_process() -> &'static mut [Option<kernel::process::Process<'stati
     // of processes.
     let total_size = read_volatile(addr as *const usize);
     if total_size != 0 {
-        write_volatile(&mut PROCS[0],
-                       Some(kernel::process::Process::create(addr, total_size, &mut MEMORY)));
+        // The process is not loaded if its image is corrupt or its memory
+        // requirements cannot be met.
+        let process = kernel::process::Process::create(addr,
+                                                       total_size,
+                                                       &mut MEMORY,
+                                                       kernel::FaultResponse::Panic,
+                                                       CALLBACK_DEPTH);
+        write_volatile(&mut PROCS[0], process.ok().map(|(process, _)| process));
     }
     &mut PROCS
 }
@@ -142,7 +151,7 @@ pub unsafe fn reset_handler() {
     let gpio = static_init!(
         capsules::gpio::GPIO<'static, nrf51::gpio::GPIOPin>,
         capsules::gpio::GPIO::new(gpio_pins),
-        20);
+        28);
     for pin in gpio_pins.iter() {
         pin.set_client(gpio);
     }
@@ -152,7 +161,7 @@ pub unsafe fn reset_handler() {
         capsules::console::Console::new(&nrf51::uart::UART0,
                                        &mut capsules::console::WRITE_BUF,
                                        kernel::Container::create()),
-        24);
+        28);
     nrf51::uart::UART0.set_client(console);
 
     let alarm = &nrf51::rtc::RTC;
@@ -205,7 +214,8 @@ pub unsafe fn reset_handler() {
     chip.systick().reset();
     chip.systick().enable(true);
 
-    kernel::main(platform, &mut chip, load_process());
+    let mut scheduler = kernel::RoundRobin::new(10000);
+    kernel::main(platform, &mut chip, load_process(), &mut scheduler);
 
 }
 
@@ -9,8 +9,9 @@ pub struct Writer {
 
 pub static mut WRITER: Writer = Writer { initialized: false };
 
-impl Write for Writer {
-    fn write_str(&mut self, s: &str) -> ::core::fmt::Result {
+impl Writer {
+    /// Returns the UART, configuring it the first time.
+    fn uart(&mut self) -> &'static mut sam4l::usart::USART {
         let uart = unsafe { &mut sam4l::usart::USART3 };
         if !self.initialized {
             self.initialized = true;
@@ -23,6 +24,13 @@ impl Write for Writer {
             uart.enable_tx();
 
         }
+        uart
+    }
+}
+
+impl Write for Writer {
+    fn write_str(&mut self, s: &str) -> ::core::fmt::Result {
+        let uart = self.uart();
         for c in s.bytes() {
             uart.send_byte(c);
         }
@@ -37,9 +45,15 @@ impl Write for Writer {
 pub unsafe extern "C" fn rust_begin_unwind(args: Arguments, file: &'static str, line: u32) -> ! {
 
     let writer = &mut WRITER;
+
+    // Debug output still waiting in the buffer was written before the panic.
+    let uart = writer.uart();
+    ::kernel::debug::flush(|byte| uart.send_byte(byte));
+
     let _ = writer.write_fmt(format_args!("Kernel panic at {}:{}:\r\n\t\"", file, line));
     let _ = write(writer, args);
     let _ = writer.write_str("\"\r\n");
+    ::kernel::debug::panic_dump(writer);
 
     let led = &sam4l::gpio::PC[10];
     led.enable_output();
@@ -59,13 +73,13 @@ pub unsafe extern "C" fn rust_begin_unwind(args: Arguments, file: &'static str,
     }
 }
 
+/// Prints through the kernel's buffered debug output, which shares USART3
+/// with the console. Only the panic handler writes to the UART directly.
 #[macro_export]
 macro_rules! print {
         ($($arg:tt)*) => (
             {
-                use core::fmt::write;
-                let writer = unsafe { &mut $crate::io::WRITER };
-                let _ = write(writer, format_args!($($arg)*));
+                ::kernel::debug::debug_fmt(format_args!($($arg)*));
             }
         );
 }
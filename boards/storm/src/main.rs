@@ -11,10 +11,12 @@ extern crate crypto;
 
 use capsules::console::{self, Console};
 use capsules::nrf51822_serialization::{self, Nrf51822Serialization};
+use capsules::process_console::{self, ProcessConsole};
 use capsules::timer::TimerDriver;
 use capsules::virtual_alarm::{MuxAlarm, VirtualMuxAlarm};
 use capsules::virtual_i2c::{I2CDevice, MuxI2C};
-use kernel::{Chip, MPU, Platform};
+use capsules::virtual_uart::{MuxUart, UartDevice};
+use kernel::{Chip, FaultResponse, LoadError, MPU, Platform};
 use kernel::hil::Controller;
 use kernel::hil::gpio::PinCtl;
 use kernel::hil::spi::SpiMaster;
@@ -47,12 +49,18 @@ unsafe fn load_processes() -> &'static mut [Option<kernel::process::Process<'sta
 
     const NUM_PROCS: usize = 2;
 
+    /// Number of callbacks that can be pending for each process.
+    const CALLBACK_DEPTH: usize = 10;
+
+    /// Memory shared by all apps. Each app is given a block sized from the
+    /// requirements in its header.
     #[link_section = ".app_memory"]
-    static mut MEMORIES: [[u8; 8192]; NUM_PROCS] = [[0; 8192]; NUM_PROCS];
+    static mut APP_MEMORY: [u8; 16384] = [0; 16384];
 
     static mut processes: [Option<kernel::process::Process<'static>>; NUM_PROCS] = [None, None];
 
     let mut addr = &_sapps as *const u8;
+    let mut memory_used = 0;
     for i in 0..NUM_PROCS {
         // The first member of the LoadInfo header contains the total size of each process image. A
         // sentinel value of 0 (invalid because it's smaller than the header itself) is used to
@@ -62,10 +70,25 @@ unsafe fn load_processes() -> &'static mut [Option<kernel::process::Process<'sta
             break;
         }
 
-        let process = &mut processes[i];
-        let memory = &mut MEMORIES[i];
-        *process = Some(kernel::process::Process::create(addr, total_size, memory));
-        // TODO: panic if loading failed?
+        let memory_pool = &mut APP_MEMORY[memory_used..];
+        // A faulting app is restarted a few times, then stopped, so a single
+        // misbehaving app cannot take down the whole board.
+        let process = kernel::process::Process::create(addr,
+                                                       total_size,
+                                                       memory_pool,
+                                                       FaultResponse::Restart(3),
+                                                       CALLBACK_DEPTH);
+        // A corrupt app, or one whose memory requirements cannot be met, is
+        // skipped. If its size is bad the following apps cannot be found, so
+        // loading stops there.
+        match process {
+            Ok((process, memory)) => {
+                processes[i] = Some(process);
+                memory_used += memory;
+            }
+            Err(LoadError::BadTotalSize) => return &mut processes,
+            Err(_) => {}
+        }
 
         addr = addr.offset(total_size as isize);
     }
@@ -78,13 +101,15 @@ unsafe fn load_processes() -> &'static mut [Option<kernel::process::Process<'sta
 }
 
 struct Firestorm {
-    console: &'static Console<'static, usart::USART>,
+    console: &'static Console<'static, UartDevice<'static, usart::USART>>,
     gpio: &'static capsules::gpio::GPIO<'static, sam4l::gpio::GPIOPin>,
     timer: &'static TimerDriver<'static, VirtualMuxAlarm<'static, sam4l::ast::Ast<'static>>>,
     tmp006: &'static capsules::tmp006::TMP006<'static>,
     isl29035: &'static capsules::isl29035::Isl29035<'static>,
     spi: &'static capsules::spi::Spi<'static, sam4l::spi::Spi>,
     nrf51822: &'static Nrf51822Serialization<'static, usart::USART>,
+    process_info: &'static capsules::process_info::ProcessInfo,
+    ipc: &'static kernel::ipc::IPC,
 }
 
 impl Platform for Firestorm {
@@ -104,6 +129,8 @@ impl Platform for Firestorm {
             4 => f(Some(self.spi)),
             5 => f(Some(self.nrf51822)),
             6 => f(Some(self.isl29035)),
+            7 => f(Some(self.process_info)),
+            0xff => f(Some(self.ipc)),
             _ => f(None),
         }
     }
@@ -310,13 +337,53 @@ pub unsafe fn reset_handler() {
 
     set_pin_primary_functions();
 
+    // USART3 is shared by the app console and the process console.
+    let mux_uart = static_init!(
+        MuxUart<'static, usart::USART>,
+        MuxUart::new(&usart::USART3),
+        12);
+    usart::USART3.set_client(mux_uart);
+
+    let console_uart = static_init!(
+        UartDevice<'static, usart::USART>,
+        UartDevice::new(mux_uart),
+        28);
     let console = static_init!(
-        Console<usart::USART>,
-        Console::new(&usart::USART3,
+        Console<UartDevice<usart::USART>>,
+        Console::new(console_uart,
                      &mut console::WRITE_BUF,
                      kernel::Container::create()),
+        28);
+    console_uart.set_client(console);
+
+    // Kernel shell for listing, stopping and restarting processes. Its
+    // commands are lines starting with `!`, which apps reading the console
+    // receive as well. Leave it out to give USART3 to apps alone.
+    let process_console_uart = static_init!(
+        UartDevice<'static, usart::USART>,
+        UartDevice::new(mux_uart),
+        28);
+    let process_console = static_init!(
+        ProcessConsole<'static, UartDevice<'static, usart::USART>>,
+        ProcessConsole::new(process_console_uart,
+                            &mut process_console::WRITE_BUF,
+                            &mut process_console::COMMAND_BUF),
         24);
-    usart::USART3.set_client(console);
+    process_console_uart.set_client(process_console);
+
+    // Kernel debug output, sent in the background between console writes.
+    let debug_uart = static_init!(
+        UartDevice<'static, usart::USART>,
+        UartDevice::new(mux_uart),
+        28);
+    let debug_writer = static_init!(
+        kernel::debug::DebugWriter,
+        kernel::debug::DebugWriter::new(debug_uart,
+                                        &mut kernel::debug::WRITE_BUF,
+                                        &mut kernel::debug::OUTPUT_BUF),
+        36);
+    debug_uart.set_client(debug_writer);
+    kernel::debug::set_debug_writer(debug_writer);
 
     // Create the Nrf51822Serialization driver for passing BLE commands
     // over UART to the nRF51822 radio.
@@ -324,7 +391,7 @@ pub unsafe fn reset_handler() {
         Nrf51822Serialization<usart::USART>,
         Nrf51822Serialization::new(&usart::USART2,
                                    &mut nrf51822_serialization::WRITE_BUF),
-        68);
+        80);
     usart::USART2.set_client(nrf_serialization);
 
     let ast = &sam4l::ast::AST;
@@ -346,7 +413,7 @@ pub unsafe fn reset_handler() {
         capsules::tmp006::TMP006::new(tmp006_i2c,
                                      &sam4l::gpio::PA[9],
                                      &mut capsules::tmp006::BUFFER),
-        52);
+        56);
     tmp006_i2c.set_client(tmp006);
     sam4l::gpio::PA[9].set_client(tmp006);
 
@@ -355,7 +422,7 @@ pub unsafe fn reset_handler() {
     let isl29035 = static_init!(
         capsules::isl29035::Isl29035<'static>,
         capsules::isl29035::Isl29035::new(isl29035_i2c, &mut capsules::isl29035::BUF),
-        36);
+        40);
     isl29035_i2c.set_client(isl29035);
 
     let virtual_alarm1 = static_init!(
@@ -373,7 +440,7 @@ pub unsafe fn reset_handler() {
     let spi = static_init!(
         capsules::spi::Spi<'static, sam4l::spi::Spi>,
         capsules::spi::Spi::new(&mut sam4l::spi::SPI, chip_selects),
-        92);
+        104);
     spi.config_buffers(&mut spi_read_buf, &mut spi_write_buf);
     sam4l::spi::SPI.set_client(spi);
     sam4l::spi::SPI.init();
@@ -398,7 +465,7 @@ pub unsafe fn reset_handler() {
     let gpio = static_init!(
         capsules::gpio::GPIO<'static, sam4l::gpio::GPIOPin>,
         capsules::gpio::GPIO::new(gpio_pins),
-        20);
+        28);
     for pin in gpio_pins.iter() {
         pin.set_client(gpio);
     }
@@ -410,6 +477,12 @@ pub unsafe fn reset_handler() {
     // &sam4l::gpio::PA[14] // No Connection
     //
 
+    let process_info = static_init!(capsules::process_info::ProcessInfo,
+                                    capsules::process_info::ProcessInfo::new(),
+                                    0);
+
+    let ipc = static_init!(kernel::ipc::IPC, kernel::ipc::IPC::new(), 4);
+
     let firestorm = static_init!(
         Firestorm,
         Firestorm {
@@ -420,8 +493,10 @@ pub unsafe fn reset_handler() {
             isl29035: isl29035,
             spi: spi,
             nrf51822: nrf_serialization,
+            process_info: process_info,
+            ipc: ipc,
         },
-        28);
+        36);
 
     usart::USART3.configure(usart::USARTParams {
         // client: &console,
@@ -468,6 +543,7 @@ pub unsafe fn reset_handler() {
     // flash_dummy::set_read_write_test();
 
     firestorm.console.initialize();
+    process_console.initialize();
     firestorm.nrf51822.initialize();
 
     let mut chip = sam4l::chip::Sam4l::new();
@@ -487,5 +563,6 @@ pub unsafe fn reset_handler() {
     //let mut res:i32=crypto::test1();
     //if (res==0) {println!("we have "); }
     
-    kernel::main(firestorm, &mut chip, load_processes());
+    let mut scheduler = kernel::RoundRobin::new(10000);
+    kernel::main(firestorm, &mut chip, load_processes(), &mut scheduler);
 }
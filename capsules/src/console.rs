@@ -1,4 +1,4 @@
-use kernel::{AppId, AppSlice, Container, Callback, Shared, Driver};
+use kernel::{AppId, AppSlice, Container, Callback, Shared, Driver, ReturnCode};
 use kernel::common::take_cell::TakeCell;
 use kernel::hil::uart::{UART, Client};
 
@@ -52,48 +52,90 @@ impl<'a, U: UART> Console<'a, U> {
         self.uart.enable_tx();
         self.uart.enable_rx();
     }
+
+    /// Releases an app's state once the console has nothing left to do for
+    /// it: no callbacks, no buffers and no write waiting or under way.
+    fn release_if_idle(&self, appid: AppId) {
+        let idle = self.apps
+            .enter(appid, |app, _| {
+                app.read_callback.is_none() && app.write_callback.is_none() &&
+                app.read_buffer.is_none() && app.write_buffer.is_none() &&
+                !app.pending_write
+            })
+            .unwrap_or(false);
+        let writing = self.in_progress.map_or(false, |id| *id == appid);
+        if idle && !writing {
+            self.apps.release(appid);
+        }
+    }
 }
 
 impl<'a, U: UART> Driver for Console<'a, U> {
-    fn allow(&self, appid: AppId, allow_num: usize, slice: AppSlice<Shared, u8>) -> isize {
+    fn allow(&self,
+             appid: AppId,
+             allow_num: usize,
+             slice: Option<AppSlice<Shared, u8>>)
+             -> ReturnCode {
         match allow_num {
             0 => {
                 self.apps
                     .enter(appid, |app, _| {
-                        app.read_buffer = Some(slice);
+                        app.read_buffer = slice;
                         app.read_idx = 0;
-                        0
+                        ReturnCode::SUCCESS
                     })
-                    .unwrap_or(-1)
+                    .unwrap_or_else(|err| err.into())
             }
             1 => {
                 self.apps
                     .enter(appid, |app, _| {
-                        app.write_buffer = Some(slice);
-                        0
+                        // A write still waiting for the UART has not copied
+                        // the old buffer yet, so it can't go ahead.
+                        if slice.is_none() {
+                            app.pending_write = false;
+                        }
+                        app.write_buffer = slice;
+                        ReturnCode::SUCCESS
                     })
-                    .unwrap_or(-1)
+                    .unwrap_or_else(|err| err.into())
             }
-            _ => -1,
+            _ => ReturnCode::ENOSUPPORT,
         }
     }
 
-    fn subscribe(&self, subscribe_num: usize, callback: Callback) -> isize {
+    fn subscribe(&self,
+                 subscribe_num: usize,
+                 callback: Option<Callback>,
+                 appid: AppId)
+                 -> ReturnCode {
         match subscribe_num {
             0 /* read line */ => {
-                self.apps.enter(callback.app_id(), |app, _| {
-                    app.read_callback = Some(callback);
-                    0
-                }).unwrap_or(-1)
+                let rc = self.apps.enter(appid, |app, _| {
+                    app.read_callback = callback;
+                    ReturnCode::SUCCESS
+                }).unwrap_or_else(|err| err.into());
+                if callback.is_none() {
+                    self.release_if_idle(appid);
+                }
+                rc
             },
             1 /* putstr/write_done */ => {
-                self.apps.enter(callback.app_id(), |app, _| {
+                let rc = self.apps.enter(appid, |app, _| {
+                    let callback = match callback {
+                        Some(callback) => callback,
+                        None => {
+                            // Unsubscribing leaves a write in progress to
+                            // finish unreported.
+                            app.write_callback = None;
+                            return ReturnCode::SUCCESS;
+                        }
+                    };
                     match app.write_buffer.take() {
                         Some(slice) => {
                             app.write_callback = Some(callback);
                             app.write_len = slice.len();
                             if self.in_progress.is_none() {
-                                self.in_progress.replace(callback.app_id());
+                                self.in_progress.replace(appid);
                                 self.buffer.take().map(|buffer| {
                                     for (i, c) in slice.as_ref().iter().enumerate() {
                                         if buffer.len() <= i {
@@ -107,20 +149,28 @@ impl<'a, U: UART> Driver for Console<'a, U> {
                                 app.pending_write = true;
                                 app.write_buffer = Some(slice);
                             }
-                            0
+                            ReturnCode::SUCCESS
                         },
-                        None => -1
+                        None => ReturnCode::ERESERVE
                     }
-                }).unwrap_or(-1)
+                }).unwrap_or_else(|err| err.into());
+                if callback.is_none() {
+                    self.release_if_idle(appid);
+                }
+                rc
             },
-            _ => -1
+            _ => ReturnCode::ENOSUPPORT
         }
     }
 
-    fn command(&self, cmd_num: usize, arg1: usize, _: AppId) -> isize {
+    fn command(&self, cmd_num: usize, arg1: usize, _: usize, _: AppId) -> ReturnCode {
         match cmd_num {
-            0 /* putc */ => { self.uart.send_byte(arg1 as u8); 1 },
-            _ => -1
+            0 /* putc */ => {
+                self.uart.send_byte(arg1 as u8);
+                // Apps have always been told 1 on success.
+                ReturnCode::SuccessWithValue { value: 1 }
+            },
+            _ => ReturnCode::ENOSUPPORT
         }
     }
 }
@@ -1,10 +1,13 @@
 use core::cell::Cell;
-use kernel::{AppId, Callback, Driver};
+use kernel::{AppId, Callback, Driver, ReturnCode};
 use kernel::hil::gpio::{Pin, PinCtl, InputMode, InterruptMode, Client};
 
 pub struct GPIO<'a, G: Pin + 'a> {
     pins: &'a [&'a G],
     callback: Cell<Option<Callback>>,
+    /// Interrupts that could not be delivered since the last callback that
+    /// was, because the app's callback queue was full.
+    dropped: Cell<usize>,
 }
 
 impl<'a, G: Pin + PinCtl> GPIO<'a, G> {
@@ -12,51 +15,52 @@ impl<'a, G: Pin + PinCtl> GPIO<'a, G> {
         GPIO {
             pins: pins,
             callback: Cell::new(None),
+            dropped: Cell::new(0),
         }
     }
 
-    fn configure_input_pin(&self, pin_num: usize, config: usize) -> isize {
+    fn configure_input_pin(&self, pin_num: usize, config: usize) -> ReturnCode {
         let pin = self.pins[pin_num];
         pin.make_input();
         match config {
             0 => {
                 pin.set_input_mode(InputMode::PullUp);
-                0
+                ReturnCode::SUCCESS
             }
 
             1 => {
                 pin.set_input_mode(InputMode::PullDown);
-                0
+                ReturnCode::SUCCESS
             }
 
             2 => {
                 pin.set_input_mode(InputMode::PullNone);
-                0
+                ReturnCode::SUCCESS
             }
 
-            _ => -1,
+            _ => ReturnCode::EINVAL,
         }
     }
 
-    fn configure_interrupt(&self, pin_num: usize, config: usize) -> isize {
+    fn configure_interrupt(&self, pin_num: usize, config: usize) -> ReturnCode {
         let pins = self.pins.as_ref();
         match config {
             0 => {
                 pins[pin_num].enable_interrupt(pin_num, InterruptMode::EitherEdge);
-                0
+                ReturnCode::SUCCESS
             }
 
             1 => {
                 pins[pin_num].enable_interrupt(pin_num, InterruptMode::RisingEdge);
-                0
+                ReturnCode::SUCCESS
             }
 
             2 => {
                 pins[pin_num].enable_interrupt(pin_num, InterruptMode::FallingEdge);
-                0
+                ReturnCode::SUCCESS
             }
 
-            _ => -1,
+            _ => ReturnCode::EINVAL,
         }
     }
 }
@@ -67,113 +71,107 @@ impl<'a, G: Pin> Client for GPIO<'a, G> {
         let pins = self.pins.as_ref();
         let pin_state = pins[pin_num].read();
 
-        // schedule callback with the pin number and value
-        if self.callback.get().is_some() {
-            self.callback.get().unwrap().schedule(pin_num, pin_state as usize, 0);
-        }
+        // schedule callback with the pin number, value and the number of
+        // interrupts dropped before this one. Interrupts while the app is
+        // not running are not counted, as its queue was not full.
+        self.callback.get().map(|mut callback| {
+            let dropped = self.dropped.get();
+            if callback.schedule(pin_num, pin_state as usize, dropped) {
+                self.dropped.set(0);
+            } else if callback.is_live() {
+                self.dropped.set(dropped + 1);
+            }
+        });
     }
 }
 
 impl<'a, G: Pin + PinCtl> Driver for GPIO<'a, G> {
-    fn subscribe(&self, subscribe_num: usize, callback: Callback) -> isize {
+    fn subscribe(&self, subscribe_num: usize, callback: Option<Callback>, _: AppId) -> ReturnCode {
         match subscribe_num {
             // subscribe to all pin interrupts
             // (no affect or reliance on individual pins being configured as interrupts)
             0 => {
-                self.callback.set(Some(callback));
-                0
+                self.callback.set(callback);
+                ReturnCode::SUCCESS
             }
 
             // default
-            _ => -1,
+            _ => ReturnCode::ENOSUPPORT,
         }
     }
 
-    fn command(&self, command_num: usize, data: usize, _: AppId) -> isize {
+    fn command(&self, command_num: usize, data: usize, arg2: usize, _: AppId) -> ReturnCode {
         let pins = self.pins.as_ref();
         match command_num {
             // enable output
             0 => {
                 if data >= pins.len() {
-                    -1
+                    ReturnCode::EINVAL
                 } else {
                     pins[data].make_output();
-                    0
+                    ReturnCode::SUCCESS
                 }
             }
 
             // set pin
             1 => {
                 if data >= pins.len() {
-                    -1
+                    ReturnCode::EINVAL
                 } else {
                     pins[data].set();
-                    0
+                    ReturnCode::SUCCESS
                 }
             }
 
             // clear pin
             2 => {
                 if data >= pins.len() {
-                    -1
+                    ReturnCode::EINVAL
                 } else {
                     pins[data].clear();
-                    0
+                    ReturnCode::SUCCESS
                 }
             }
 
             // toggle pin
             3 => {
                 if data >= pins.len() {
-                    -1
+                    ReturnCode::EINVAL
                 } else {
                     pins[data].toggle();
-                    0
+                    ReturnCode::SUCCESS
                 }
             }
 
             // enable and configure input
+            // data == pin, arg2 == pin_config
             4 => {
-                // XXX: this is clunky
-                // data == ((pin_config << 8) | pin)
-                // this allows two values to be passed into a command interface
-                let pin_num = data & 0xFF;
-                let pin_config = (data >> 8) & 0xFF;
-                if pin_num >= pins.len() {
-                    -1
+                if data >= pins.len() {
+                    ReturnCode::EINVAL
                 } else {
-                    let err_code = self.configure_input_pin(pin_num, pin_config);
-                    err_code
+                    self.configure_input_pin(data, arg2)
                 }
             }
 
             // read input
             5 => {
                 if data >= pins.len() {
-                    -1
+                    ReturnCode::EINVAL
                 } else {
                     let pin_state = pins[data].read();
-                    pin_state as isize
+                    ReturnCode::SuccessWithValue { value: pin_state as usize }
                 }
             }
 
-            // enable and configure interrupts on pin, also sets pin as input
+            // enable and configure interrupts on pin, which must already be
+            // configured as an input with command 4
             // (no affect or reliance on registered callback)
+            // data == pin, arg2 == irq_config
             6 => {
-                // TODO(brghena): this is clunky
-                // data == ((irq_config << 16) | (pin_config << 8) | pin)
-                // this allows three values to be passed into a command interface
-                let pin_num = data & 0xFF;
-                let pin_config = (data >> 8) & 0xFF;
-                let irq_config = (data >> 16) & 0xFF;
-                if pin_num >= pins.len() {
-                    -1
+                if data >= pins.len() {
+                    ReturnCode::EINVAL
                 } else {
-                    let mut err_code = self.configure_input_pin(pin_num, pin_config);
-                    if err_code == 0 {
-                        err_code = self.configure_interrupt(pin_num, irq_config);
-                    }
-                    err_code
+                    self.configure_interrupt(data, arg2)
                 }
             }
 
@@ -181,26 +179,26 @@ impl<'a, G: Pin + PinCtl> Driver for GPIO<'a, G> {
             // (no affect or reliance on registered callback)
             7 => {
                 if data >= pins.len() {
-                    -1
+                    ReturnCode::EINVAL
                 } else {
                     pins[data].disable_interrupt();
                     pins[data].disable();
-                    0
+                    ReturnCode::SUCCESS
                 }
             }
 
             // disable pin
             8 => {
                 if data >= pins.len() {
-                    -1
+                    ReturnCode::EINVAL
                 } else {
                     pins[data].disable();
-                    0
+                    ReturnCode::SUCCESS
                 }
             }
 
             // default
-            _ => -1,
+            _ => ReturnCode::ENOSUPPORT,
         }
     }
 }
@@ -1,7 +1,7 @@
 //! Driver for the ISL29035 digital light sensor
 
 use core::cell::Cell;
-use kernel::{AppId, Callback, Driver};
+use kernel::{AppId, Callback, Driver, ReturnCode};
 use kernel::common::take_cell::TakeCell;
 use kernel::hil::i2c::{I2CDevice, I2CClient, Error};
 
@@ -57,30 +57,38 @@ impl<'a> Isl29035<'a> {
 }
 
 impl<'a> Driver for Isl29035<'a> {
-    fn subscribe(&self, subscribe_num: usize, callback: Callback) -> isize {
+    fn subscribe(&self, subscribe_num: usize, callback: Option<Callback>, _: AppId) -> ReturnCode {
         match subscribe_num {
             0 => {
-                self.callback.set(Some(callback));
-                0
+                self.callback.set(callback);
+                ReturnCode::SUCCESS
             }
-            _ => -1,
+            _ => ReturnCode::ENOSUPPORT,
         }
     }
 
-    fn command(&self, command_num: usize, _arg1: usize, _: AppId) -> isize {
+    fn command(&self, command_num: usize, _arg1: usize, _: usize, _: AppId) -> ReturnCode {
         match command_num {
             0 => {
                 self.start_read_lux();
-                0
+                ReturnCode::SUCCESS
             }
-            _ => -1,
+            _ => ReturnCode::ENOSUPPORT,
         }
     }
 }
 
 impl<'a> I2CClient for Isl29035<'a> {
-    fn command_complete(&self, buffer: &'static mut [u8], _error: Error) {
-        // TODO(alevy): handle I2C errors
+    fn command_complete(&self, buffer: &'static mut [u8], error: Error) {
+        if error != Error::CommandComplete {
+            // The sensor didn't answer. Give up on this reading, so the next
+            // command starts again from `Disabled`.
+            self.i2c.disable();
+            self.state.set(State::Disabled);
+            self.buffer.replace(buffer);
+            return;
+        }
+
         match self.state.get() {
             State::Enabling => {
                 buffer[0] = 0x02 as u8;
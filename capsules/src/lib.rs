@@ -7,9 +7,12 @@ pub mod console;
 pub mod gpio;
 pub mod isl29035;
 pub mod nrf51822_serialization;
+pub mod process_console;
+pub mod process_info;
 pub mod timer;
 pub mod tmp006;
 pub mod spi;
 pub mod virtual_alarm;
 pub mod virtual_i2c;
 pub mod virtual_spi;
+pub mod virtual_uart;
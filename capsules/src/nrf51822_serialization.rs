@@ -1,4 +1,4 @@
-use kernel::{AppId, Callback, AppSlice, Driver, Shared};
+use kernel::{AppId, Callback, AppSlice, Driver, ReturnCode, Shared};
 use kernel::common::take_cell::TakeCell;
 use kernel::hil::uart::{UART, Client};
 
@@ -48,12 +48,18 @@ impl<'a, U: UART> Driver for Nrf51822Serialization<'a, U> {
     /// allow_type: 0 - Provide an RX buffer
     /// allow_type: 1 - Provide an TX buffer
     ///
-    fn allow(&self, _appid: AppId, allow_type: usize, slice: AppSlice<Shared, u8>) -> isize {
+    /// Passing no buffer takes back the previous one.
+    ///
+    fn allow(&self,
+             _appid: AppId,
+             allow_type: usize,
+             slice: Option<AppSlice<Shared, u8>>)
+             -> ReturnCode {
         match allow_type {
             0 => {
                 let resapp = match self.app.take() {
                     Some(mut app) => {
-                        app.rx_buffer = Some(slice);
+                        app.rx_buffer = slice;
                         app.rx_recv_so_far = 0;
                         app.rx_recv_total = 0;
                         app
@@ -62,25 +68,25 @@ impl<'a, U: UART> Driver for Nrf51822Serialization<'a, U> {
                         App {
                             callback: None,
                             tx_buffer: None,
-                            rx_buffer: Some(slice),
+                            rx_buffer: slice,
                             rx_recv_so_far: 0,
                             rx_recv_total: 0,
                         }
                     }
                 };
                 self.app.replace(resapp);
-                0
+                ReturnCode::SUCCESS
             }
             1 => {
                 let resapp = match self.app.take() {
                     Some(mut app) => {
-                        app.tx_buffer = Some(slice);
+                        app.tx_buffer = slice;
                         app
                     }
                     None => {
                         App {
                             callback: None,
-                            tx_buffer: Some(slice),
+                            tx_buffer: slice,
                             rx_buffer: None,
                             rx_recv_so_far: 0,
                             rx_recv_total: 0,
@@ -88,9 +94,9 @@ impl<'a, U: UART> Driver for Nrf51822Serialization<'a, U> {
                     }
                 };
                 self.app.replace(resapp);
-                0
+                ReturnCode::SUCCESS
             }
-            _ => -1,
+            _ => ReturnCode::ENOSUPPORT,
         }
     }
 
@@ -99,20 +105,24 @@ impl<'a, U: UART> Driver for Nrf51822Serialization<'a, U> {
     /// The callback will be called when a TX finishes and when
     /// RX data is available.
     ///
-    /// subscribe_type: 0 - add the callback
+    /// subscribe_type: 0 - add the callback, or remove it if there is none
     ///
     #[inline(never)]
-    fn subscribe(&self, subscribe_type: usize, callback: Callback) -> isize {
+    fn subscribe(&self,
+                 subscribe_type: usize,
+                 callback: Option<Callback>,
+                 _: AppId)
+                 -> ReturnCode {
         match subscribe_type {
             0 => {
                 let resapp = match self.app.take() {
                     Some(mut app) => {
-                        app.callback = Some(callback);
+                        app.callback = callback;
                         app
                     }
                     None => {
                         App {
-                            callback: Some(callback),
+                            callback: callback,
                             tx_buffer: None,
                             rx_buffer: None,
                             rx_recv_so_far: 0,
@@ -121,9 +131,9 @@ impl<'a, U: UART> Driver for Nrf51822Serialization<'a, U> {
                     }
                 };
                 self.app.replace(resapp);
-                0
+                ReturnCode::SUCCESS
             }
-            _ => -1,
+            _ => ReturnCode::ENOSUPPORT,
         }
     }
 
@@ -131,7 +141,7 @@ impl<'a, U: UART> Driver for Nrf51822Serialization<'a, U> {
     ///
     /// command_type: 0 - Write a byte to the UART.
     ///
-    fn command(&self, command_type: usize, _: usize, _: AppId) -> isize {
+    fn command(&self, command_type: usize, _: usize, _: usize, _: AppId) -> ReturnCode {
 
         match command_type {
             0 => {
@@ -149,14 +159,14 @@ impl<'a, U: UART> Driver for Nrf51822Serialization<'a, U> {
                                 }
                                 self.uart.send_bytes(buffer, write_len);
                             });
-                            0
+                            ReturnCode::SUCCESS
                         }
-                        None => -2,
+                        None => ReturnCode::ERESERVE,
                     }
                 });
-                result.unwrap_or(-1)
+                result.unwrap_or(ReturnCode::FAIL)
             }
-            _ => -1,
+            _ => ReturnCode::ENOSUPPORT,
         }
     }
 }
@@ -0,0 +1,160 @@
+//! A kernel shell for inspecting and controlling processes over a UART.
+//!
+//! Commands are read a line at a time, and start with `!`:
+//!
+//! - `!list`: shows each process with its state, memory use and the number of
+//!   callbacks queued for it.
+//! - `!stop N`: stops scheduling process N.
+//! - `!start N`: resumes process N after `!stop`.
+//! - `!restart N`: reloads process N from its image and starts it again.
+//!
+//! The shell is meant to share a UART with `console::Console` through a
+//! `virtual_uart::UartDevice`, so apps reading the console see the commands
+//! too. Lines without the `!` are left to the apps. A command entered while
+//! the output of the previous one is still being sent is ignored.
+
+use core::str;
+use core::cell::Cell;
+use core::fmt::Write;
+use kernel::common::SliceWriter;
+use kernel::common::take_cell::TakeCell;
+use kernel::hil::uart::{UART, Client};
+use kernel::process::{self, Process, State};
+
+pub static mut WRITE_BUF: [u8; 256] = [0; 256];
+pub static mut COMMAND_BUF: [u8; 32] = [0; 32];
+
+/// Starts every command, so that lines meant for apps are not run.
+const COMMAND_PREFIX: u8 = b'!';
+
+pub struct ProcessConsole<'a, U: UART + 'a> {
+    uart: &'a U,
+    tx_buffer: TakeCell<&'static mut [u8]>,
+    command: TakeCell<&'static mut [u8]>,
+    command_len: Cell<usize>,
+}
+
+impl<'a, U: UART> ProcessConsole<'a, U> {
+    pub fn new(uart: &'a U,
+               tx_buffer: &'static mut [u8],
+               command_buffer: &'static mut [u8])
+               -> ProcessConsole<'a, U> {
+        ProcessConsole {
+            uart: uart,
+            tx_buffer: TakeCell::new(tx_buffer),
+            command: TakeCell::new(command_buffer),
+            command_len: Cell::new(0),
+        }
+    }
+
+    pub fn initialize(&self) {
+        self.uart.enable_tx();
+        self.uart.enable_rx();
+    }
+
+    fn execute(&self, line: &[u8]) {
+        let line = match line.split_first() {
+            Some((&COMMAND_PREFIX, command)) => command,
+            _ => return,
+        };
+        self.tx_buffer.take().map(|buffer| {
+            let len = {
+                let mut out = SliceWriter::new(&mut buffer[..]);
+                let mut words = str::from_utf8(line).unwrap_or("").split_whitespace();
+                let command = words.next();
+                let index = words.next().and_then(|word| word.parse::<usize>().ok());
+                match (command, index) {
+                    (Some("list"), _) => list(&mut out),
+                    (Some("stop"), Some(idx)) => with_process(&mut out, idx, |p| p.stop()),
+                    (Some("start"), Some(idx)) => with_process(&mut out, idx, |p| p.resume()),
+                    (Some("restart"), Some(idx)) => {
+                        with_process(&mut out, idx, |p| unsafe { p.restart() })
+                    }
+                    _ => {
+                        let _ = out.write_str("Commands: !list, !stop N, !start N, !restart N\r\n");
+                    }
+                }
+                out.len()
+            };
+            if len > 0 {
+                self.uart.send_bytes(buffer, len);
+            } else {
+                self.tx_buffer.replace(buffer);
+            }
+        });
+    }
+}
+
+impl<'a, U: UART> Client for ProcessConsole<'a, U> {
+    fn write_done(&self, buffer: &'static mut [u8]) {
+        self.tx_buffer.replace(buffer);
+    }
+
+    fn read_done(&self, c: u8) {
+        self.command.take().map(|command| {
+            let len = self.command_len.get();
+            match c as char {
+                '\r' | '\n' => {
+                    if len > 0 {
+                        self.execute(&command[..len]);
+                    }
+                    self.command_len.set(0);
+                }
+                _ => {
+                    // Characters past the end of the buffer are dropped, which
+                    // turns the line into an unknown command.
+                    if len < command.len() {
+                        command[len] = c;
+                        self.command_len.set(len + 1);
+                    }
+                }
+            }
+            self.command.replace(command);
+        });
+    }
+}
+
+/// Writes one line per process.
+fn list(out: &mut SliceWriter) {
+    let _ = out.write_str("PID State      App mem  Kernel mem  Total  Callbacks\r\n");
+    for idx in 0..process::num_procs() {
+        if let Some(p) = unsafe { process::process_at(idx) } {
+            let state = if p.is_stopped() {
+                "Stopped"
+            } else {
+                match p.state {
+                    State::Running => "Running",
+                    State::Yielded => "Yielded",
+                    State::Faulted => "Faulted",
+                    State::Terminated => "Terminated",
+                }
+            };
+            let start = p.mem_start() as usize;
+            let end = p.mem_end() as usize;
+            let _ = write!(out,
+                           "{:<3} {:<10} {:>7}  {:>10}  {:>5}  {}/{}\r\n",
+                           idx,
+                           state,
+                           p.app_memory_break() as usize - start,
+                           end - p.kernel_memory_break() as usize,
+                           end - start,
+                           p.pending_callbacks(),
+                           p.callback_depth());
+        }
+    }
+}
+
+/// Runs `fun` on the process in slot `idx` and reports the result.
+fn with_process<F>(out: &mut SliceWriter, idx: usize, fun: F)
+    where F: FnOnce(&mut Process<'static>)
+{
+    match unsafe { process::process_at(idx) } {
+        Some(p) => {
+            fun(p);
+            let _ = out.write_str("OK\r\n");
+        }
+        None => {
+            let _ = write!(out, "No process {}\r\n", idx);
+        }
+    }
+}
@@ -0,0 +1,71 @@
+//! Exposes the runtime statistics the kernel keeps for each process.
+//!
+//! All commands except 0 and 6 take a process index in `data` and return
+//! `EINVAL` if there is no process in that slot. Command 1 takes the system
+//! call number in `arg2`. Counts too large for the return value come back as
+//! the largest positive one.
+
+use core::cmp;
+use kernel::{AppId, Driver, ReturnCode};
+use kernel::process;
+
+pub struct ProcessInfo;
+
+impl ProcessInfo {
+    pub const fn new() -> ProcessInfo {
+        ProcessInfo
+    }
+}
+
+/// Wraps a statistic in a successful return code.
+fn value(value: usize) -> ReturnCode {
+    ReturnCode::SuccessWithValue { value: value }
+}
+
+impl Driver for ProcessInfo {
+    fn command(&self, command_num: usize, data: usize, arg2: usize, appid: AppId) -> ReturnCode {
+        match command_num {
+            // number of process slots
+            0 => value(process::num_procs()),
+
+            // number of system calls of one type made by a process
+            // data == process, arg2 == syscall
+            1 => {
+                process::statistics(data).map_or(ReturnCode::EINVAL, |stats| {
+                    stats.syscalls.get(arg2).map_or(ReturnCode::EINVAL, |count| value(*count))
+                })
+            }
+
+            // number of callbacks delivered to a process
+            2 => {
+                process::statistics(data)
+                    .map_or(ReturnCode::EINVAL, |stats| value(stats.callbacks_delivered))
+            }
+
+            // number of callbacks dropped because the process' queue was full
+            3 => {
+                process::statistics(data)
+                    .map_or(ReturnCode::EINVAL, |stats| value(stats.callbacks_dropped))
+            }
+
+            // number of timeslice expirations
+            4 => {
+                process::statistics(data)
+                    .map_or(ReturnCode::EINVAL, |stats| value(stats.timeslice_expirations))
+            }
+
+            // cpu time, in milliseconds
+            5 => {
+                process::statistics(data).map_or(ReturnCode::EINVAL, |stats| {
+                    value(cmp::min(stats.cpu_time_us / 1000, usize::max_value() as u64) as usize)
+                })
+            }
+
+            // index of the calling process
+            6 => value(appid.idx()),
+
+            // default
+            _ => ReturnCode::ENOSUPPORT,
+        }
+    }
+}
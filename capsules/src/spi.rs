@@ -1,6 +1,6 @@
 use core::cell::Cell;
 use core::cmp;
-use kernel::{AppId, Driver, Callback, AppSlice, Shared};
+use kernel::{AppId, Driver, Callback, AppSlice, Shared, ReturnCode};
 use kernel::common::take_cell::TakeCell;
 use kernel::hil::spi::{SpiMaster, SpiMasterClient};
 use kernel::hil::spi::ClockPhase;
@@ -77,26 +77,35 @@ impl<'a, S: SpiMaster> Spi<'a, S> {
 }
 
 impl<'a, S: SpiMaster> Driver for Spi<'a, S> {
-    fn allow(&self, _appid: AppId, allow_num: usize, slice: AppSlice<Shared, u8>) -> isize {
+    fn allow(&self,
+             _appid: AppId,
+             allow_num: usize,
+             slice: Option<AppSlice<Shared, u8>>)
+             -> ReturnCode {
+        // A transfer in progress copies to and from the buffers between
+        // chunks, so they can't be swapped or taken back until it is done.
+        if allow_num <= 1 && self.busy.get() {
+            return ReturnCode::EBUSY;
+        }
         match allow_num {
             0 => {
                 let appc = match self.app.take() {
                     None => {
                         App {
                             callback: None,
-                            app_read: Some(slice),
+                            app_read: slice,
                             app_write: None,
                             len: 0,
                             index: 0,
                         }
                     }
                     Some(mut appc) => {
-                        appc.app_read = Some(slice);
+                        appc.app_read = slice;
                         appc
                     }
                 };
                 self.app.replace(appc);
-                0
+                ReturnCode::SUCCESS
             }
             1 => {
                 let appc = match self.app.take() {
@@ -104,44 +113,48 @@ impl<'a, S: SpiMaster> Driver for Spi<'a, S> {
                         App {
                             callback: None,
                             app_read: None,
-                            app_write: Some(slice),
+                            app_write: slice,
                             len: 0,
                             index: 0,
                         }
                     }
                     Some(mut appc) => {
-                        appc.app_write = Some(slice);
+                        appc.app_write = slice;
                         appc
                     }
                 };
                 self.app.replace(appc);
-                0
+                ReturnCode::SUCCESS
             }
-            _ => -1,
+            _ => ReturnCode::ENOSUPPORT,
         }
     }
 
     #[inline(never)]
-    fn subscribe(&self, subscribe_num: usize, callback: Callback) -> isize {
+    fn subscribe(&self,
+                 subscribe_num: usize,
+                 callback: Option<Callback>,
+                 _: AppId)
+                 -> ReturnCode {
         match subscribe_num {
             0 /* read_write */ => {
                 let appc = match self.app.take() {
                     None => App {
-                        callback: Some(callback),
+                        callback: callback,
                         app_read: None,
                         app_write: None,
                         len: 0,
                         index: 0,
                     },
                     Some(mut appc) => {
-                        appc.callback = Some(callback);
+                        appc.callback = callback;
                         appc
                     }
                 };
                 self.app.replace(appc);
-                0
+                ReturnCode::SUCCESS
             },
-            _ => -1
+            _ => ReturnCode::ENOSUPPORT
         }
     }
     // 0: read/write a single byte (blocking)
@@ -176,6 +189,12 @@ impl<'a, S: SpiMaster> Driver for Spi<'a, S> {
     //   - set CSAAT bit of control register
     // 11: release CS line (high) between transfers
     //   - clear CSAAT bit of control register
+    // 12: configure current peripheral
+    //   - first parameter is the rate in bps
+    //   - second parameter is the SPI mode (0-3): bit 1 is the
+    //     clock polarity and bit 0 the clock phase, as in
+    //     commands 6 and 8
+    //   - returns the rate actually set, as command 4
     //
     // x: lock spi
     //   - if you perform an operation without the lock,
@@ -187,16 +206,17 @@ impl<'a, S: SpiMaster> Driver for Spi<'a, S> {
     //   - does nothing if lock not held
     //
 
-    fn command(&self, cmd_num: usize, arg1: usize, _: AppId) -> isize {
+    fn command(&self, cmd_num: usize, arg1: usize, arg2: usize, _: AppId) -> ReturnCode {
         match cmd_num {
             0 /* read_write_byte */ => {
-                self.spi_master.read_write_byte(arg1 as u8) as isize
+                let value = self.spi_master.read_write_byte(arg1 as u8) as usize;
+                ReturnCode::SuccessWithValue { value: value }
             },
             1 /* read_write_bytes */ => {
                 if self.busy.get() {
-                    return -1;
+                    return ReturnCode::EBUSY;
                 }
-                let mut result = -1;
+                let mut result = ReturnCode::EINVAL;
                 self.app.map(|app| {
                     let mut mlen = 0;
                     // If write buffer too small, return
@@ -211,56 +231,75 @@ impl<'a, S: SpiMaster> Driver for Spi<'a, S> {
                         app.index = 0;
                         self.busy.set(true);
                         self.do_next_read_write(app);
-                        result = 0;
+                        result = ReturnCode::SUCCESS;
                     }
                 });
                 return result;
             }
             2 /* set chip select */ => {
                 let cs = arg1;
-                self.chip_selects.get(cs).map_or(-1, |cs_line| {
+                self.chip_selects.get(cs).map_or(ReturnCode::EINVAL, |cs_line| {
                     self.spi_master.specify_chip_select(*cs_line);
-                    0
+                    ReturnCode::SUCCESS
                 })
             }
             3 /* get chip select */ => {
-                0
+                ReturnCode::SuccessWithValue { value: 0 }
             }
             4 /* set baud rate */ => {
-                self.spi_master.set_rate(arg1 as u32) as isize
+                let rate = self.spi_master.set_rate(arg1 as u32) as usize;
+                ReturnCode::SuccessWithValue { value: rate }
             }
             5 /* get baud rate */ => {
-                self.spi_master.get_rate() as isize
+                let rate = self.spi_master.get_rate() as usize;
+                ReturnCode::SuccessWithValue { value: rate }
             }
             6 /* set phase */ => {
                 match arg1 {
                     0 => self.spi_master.set_phase(ClockPhase::SampleLeading),
                     _ => self.spi_master.set_phase(ClockPhase::SampleTrailing),
                 };
-                0
+                ReturnCode::SUCCESS
             }
             7 /* get phase */ => {
-                self.spi_master.get_phase() as isize
+                let phase = self.spi_master.get_phase() as usize;
+                ReturnCode::SuccessWithValue { value: phase }
             }
             8 /* set polarity */ => {
                 match arg1 {
                     0 => self.spi_master.set_clock(ClockPolarity::IdleLow),
                     _ => self.spi_master.set_clock(ClockPolarity::IdleHigh),
                 };
-                0
+                ReturnCode::SUCCESS
             }
             9 /* get polarity */ => {
-                self.spi_master.get_clock() as isize
+                let polarity = self.spi_master.get_clock() as usize;
+                ReturnCode::SuccessWithValue { value: polarity }
             }
             10 /* hold low */ => {
                 self.spi_master.hold_low();
-                0
+                ReturnCode::SUCCESS
             }
             11 /* release low */ => {
                 self.spi_master.release_low();
-                0
+                ReturnCode::SUCCESS
+            }
+            12 /* configure */ => {
+                if arg2 > 3 {
+                    return ReturnCode::EINVAL;
+                }
+                match arg2 & 0b10 {
+                    0 => self.spi_master.set_clock(ClockPolarity::IdleLow),
+                    _ => self.spi_master.set_clock(ClockPolarity::IdleHigh),
+                };
+                match arg2 & 0b01 {
+                    0 => self.spi_master.set_phase(ClockPhase::SampleLeading),
+                    _ => self.spi_master.set_phase(ClockPhase::SampleTrailing),
+                };
+                let rate = self.spi_master.set_rate(arg1 as u32) as usize;
+                ReturnCode::SuccessWithValue { value: rate }
             }
-            _ => -1
+            _ => ReturnCode::ENOSUPPORT
         }
     }
 }
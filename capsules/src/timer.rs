@@ -1,5 +1,5 @@
 use core::cell::Cell;
-use kernel::{AppId, Container, Callback, Driver};
+use kernel::{AppId, Container, Callback, Driver, ReturnCode};
 use kernel::hil::time::{self, Alarm, Frequency};
 
 #[derive(Copy, Clone)]
@@ -56,51 +56,69 @@ impl<'a, A: Alarm> TimerDriver<'a, A> {
             self.alarm.set_alarm(next_alarm);
         }
     }
+
+    /// Disarms an app's timer. Returns the return code for the app and
+    /// whether the next active alarm needs to be reset.
+    fn stop(&self, td: &mut TimerData) -> (ReturnCode, bool) {
+        if td.interval > 0 {
+            td.interval = 0;
+            td.t0 = 0;
+            let num_armed = self.num_armed.get();
+            self.num_armed.set(num_armed - 1);
+            if num_armed == 1 {
+                self.alarm.disable();
+                (ReturnCode::SUCCESS, false)
+            } else {
+                (ReturnCode::SUCCESS, true)
+            }
+        } else {
+            (ReturnCode::EALREADY, false)
+        }
+    }
 }
 
 impl<'a, A: Alarm> Driver for TimerDriver<'a, A> {
-    fn subscribe(&self, _: usize, callback: Callback) -> isize {
-        self.app_timer
-            .enter(callback.app_id(), |td, _allocator| {
-                td.callback = Some(callback);
-                0
-            })
-            .unwrap_or(-1)
+    fn subscribe(&self, _: usize, callback: Option<Callback>, app_id: AppId) -> ReturnCode {
+        let reset = self.app_timer
+            .enter(app_id, |td, _allocator| {
+                td.callback = callback;
+                // A timer without a callback has no one left to tell, so
+                // unsubscribing also stops it.
+                callback.is_none() && self.stop(td).1
+            });
+        match reset {
+            Ok(reset) => {
+                if callback.is_none() {
+                    // The timer is stopped, so nothing in its state is needed
+                    // any more.
+                    self.app_timer.release(app_id);
+                }
+                if reset {
+                    self.reset_active_timer();
+                }
+                ReturnCode::SUCCESS
+            }
+            Err(err) => err.into(),
+        }
     }
 
-    fn command(&self, cmd_type: usize, interval: usize, caller_id: AppId) -> isize {
+    fn command(&self, cmd_type: usize, interval: usize, _: usize, caller_id: AppId) -> ReturnCode {
         // First, convert from milliseconds to native clock frequency
         let interval = (interval as u32) * <A::Frequency>::frequency() / 1000;
 
-        // Returns the error code to return to the user (0 for success, negative
-        // otherwise) and whether we need to reset which is the next active
-        // alarm. We only _don't_ reset if we're disabling the underlying alarm
-        // anyway, if the underlying alarm is currently disabled and we're
-        // enabling the first alarm, or on an error (i.e. no change to the
-        // alarms).
-        let (err_code, reset) = self.app_timer
+        // Returns the return code for the user and whether we need to reset
+        // which is the next active alarm. We only _don't_ reset if we're
+        // disabling the underlying alarm anyway, if the underlying alarm is
+        // currently disabled and we're enabling the first alarm, or on an
+        // error (i.e. no change to the alarms).
+        let (return_code, reset) = self.app_timer
             .enter(caller_id, |td, _alloc| {
                 match cmd_type {
-                2 /* Stop */ => {
-                    if td.interval > 0 {
-                        td.interval = 0;
-                        td.t0 = 0;
-                        let num_armed = self.num_armed.get();
-                        self.num_armed.set(num_armed - 1);
-                        if num_armed == 1 {
-                            self.alarm.disable();
-                            (0, false)
-                        } else {
-                            (0, true)
-                        }
-                    } else {
-                        (-2, false)
-                    }
-                },
+                2 /* Stop */ => self.stop(td),
                 /* 0 for Oneshot, 1 for Repeat */
                 cmd_type if cmd_type <= 1 => {
                     if interval == 0 {
-                        return (-2, false);
+                        return (ReturnCode::EINVAL, false);
                     }
 
                     // if previously unarmed, but now will become armed
@@ -114,20 +132,20 @@ impl<'a, A: Alarm> Driver for TimerDriver<'a, A> {
                     // Repeat if cmd_type was 1
                     td.repeating = cmd_type == 1;
                     if self.alarm.is_armed() {
-                        (0, true)
+                        (ReturnCode::SUCCESS, true)
                     } else {
                         self.alarm.set_alarm(td.t0.wrapping_add(td.interval));
-                        (0, false)
+                        (ReturnCode::SUCCESS, false)
                     }
                 },
-                _ => (-1, false)
+                _ => (ReturnCode::ENOSUPPORT, false)
             }
             })
-            .unwrap_or((-3, false));
+            .unwrap_or_else(|err| (err.into(), false));
         if reset {
             self.reset_active_timer();
         }
-        err_code
+        return_code
     }
 }
 
@@ -1,5 +1,5 @@
 use core::cell::Cell;
-use kernel::{AppId, Callback, Driver};
+use kernel::{AppId, Callback, Driver, ReturnCode};
 use kernel::common::math::{sqrtf32, get_errno};
 use kernel::common::take_cell::TakeCell;
 use kernel::hil::gpio::{Pin, InterruptMode, Client};
@@ -7,9 +7,6 @@ use kernel::hil::i2c;
 
 pub static mut BUFFER: [u8; 3] = [0; 3];
 
-// error codes for this driver
-const ERR_BAD_VALUE: isize = -2;
-
 const MAX_SAMPLING_RATE: u8 = 0x0;
 const DEFAULT_SAMPLING_RATE: u8 = 0x02;
 
@@ -143,6 +140,14 @@ impl<'a> TMP006<'a> {
         });
     }
 
+    fn unsubscribe(&self) {
+        // clear callback function
+        self.callback.set(None);
+
+        // disable temperature sensor
+        self.disable_sensor(None);
+    }
+
     fn enable_interrupts(&self) {
         // setup interrupts from the sensor
         self.interrupt_pin.make_input();
@@ -177,8 +182,16 @@ fn calculate_temperature(sensor_voltage: i16, die_temperature: i16) -> f32 {
 }
 
 impl<'a> i2c::I2CClient for TMP006<'a> {
-    fn command_complete(&self, buffer: &'static mut [u8], _error: i2c::Error) {
-        // TODO(alevy): handle protocol errors
+    fn command_complete(&self, buffer: &'static mut [u8], error: i2c::Error) {
+        if error != i2c::Error::CommandComplete {
+            // The sensor didn't answer. Abandon the sequence so the next
+            // subscribe or interrupt starts from scratch.
+            self.buffer.replace(buffer);
+            self.i2c.disable();
+            self.protocol_state.set(ProtocolState::Idle);
+            return;
+        }
+
         match self.protocol_state.get() {
             ProtocolState::Configure => {
                 self.buffer.replace(buffer);
@@ -257,7 +270,21 @@ impl<'a> Client for TMP006<'a> {
 }
 
 impl<'a> Driver for TMP006<'a> {
-    fn subscribe(&self, subscribe_num: usize, callback: Callback) -> isize {
+    fn subscribe(&self, subscribe_num: usize, callback: Option<Callback>, _: AppId) -> ReturnCode {
+        let callback = match callback {
+            Some(callback) => callback,
+            None => {
+                // unsubscribing from either kind of reading stops the sensor
+                return match subscribe_num {
+                    0 | 1 => {
+                        self.unsubscribe();
+                        ReturnCode::SUCCESS
+                    }
+                    _ => ReturnCode::ENOSUPPORT,
+                };
+            }
+        };
+
         match subscribe_num {
             // single temperature reading with callback
             0 => {
@@ -271,7 +298,7 @@ impl<'a> Driver for TMP006<'a> {
                 //  turn up the sampling rate so we get the sample faster
                 self.enable_sensor(MAX_SAMPLING_RATE);
 
-                0
+                ReturnCode::SUCCESS
             }
 
             // periodic temperature reading subscription
@@ -285,42 +312,37 @@ impl<'a> Driver for TMP006<'a> {
                 // enable temperature sensor
                 self.enable_sensor(self.sampling_period.get());
 
-                0
+                ReturnCode::SUCCESS
             }
 
             // default
-            _ => -1,
+            _ => ReturnCode::ENOSUPPORT,
         }
     }
 
-    fn command(&self, command_num: usize, data: usize, _: AppId) -> isize {
+    fn command(&self, command_num: usize, data: usize, _: usize, _: AppId) -> ReturnCode {
         match command_num {
             // set period for sensing
             0 => {
                 // bounds check on the period
                 if (data & 0xFFFFFFF8) != 0 {
-                    return ERR_BAD_VALUE;
+                    return ReturnCode::EINVAL;
                 }
 
                 // set period value
                 self.sampling_period.set((data & 0x7) as u8);
 
-                0
+                ReturnCode::SUCCESS
             }
 
             // unsubscribe callback
             1 => {
-                // clear callback function
-                self.callback.set(None);
-
-                // disable temperature sensor
-                self.disable_sensor(None);
-
-                0
+                self.unsubscribe();
+                ReturnCode::SUCCESS
             }
 
             // default
-            _ => -1,
+            _ => ReturnCode::ENOSUPPORT,
         }
     }
 }
@@ -36,20 +36,24 @@ impl<'a, SPI: hil::spi::SpiMaster> MuxSPIMaster<'a, SPI> {
         if self.inflight.is_none() {
             let mnode = self.devices.iter().find(|node| node.operation.get() != Op::Idle);
             mnode.map(|node| {
+                // The `chip_select` type will be correct based on what
+                // implemented `SpiMaster`. Select it for every operation, as
+                // the last one may have been for another device.
+                self.spi.specify_chip_select(node.chip_select.get());
 
-                match node.operation.get() {
+                let operation = node.operation.get();
+                node.operation.set(Op::Idle);
+                match operation {
                     Op::Configure(cpol, cpal, rate) => {
-
-                        // The `chip_select` type will be correct based on
-                        // what implemented `SpiMaster`.
-                        self.spi.specify_chip_select(node.chip_select.get());
-
                         self.spi.set_clock(cpol);
                         self.spi.set_phase(cpal);
                         self.spi.set_rate(rate);
+
+                        // Configuring is synchronous, so nothing will call
+                        // back to start the next operation.
+                        self.do_next_op();
                     }
                     Op::ReadWriteBytes(len) => {
-
                         node.txbuffer.take().map(|txbuffer| {
                             node.rxbuffer.take().map(move |rxbuffer| {
                                 self.spi.read_write_bytes(txbuffer, rxbuffer, len);
@@ -62,7 +66,6 @@ impl<'a, SPI: hil::spi::SpiMaster> MuxSPIMaster<'a, SPI> {
                     }
                     Op::Idle => {} // Can't get here...
                 }
-                node.operation.set(Op::Idle);
             });
         }
     }
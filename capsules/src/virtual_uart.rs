@@ -0,0 +1,128 @@
+//! Shares one UART between several users.
+//!
+//! Each user gets a `UartDevice`, which implements `hil::uart::UART`. Buffers
+//! written to devices are queued and sent one at a time. Every byte received
+//! is passed to the clients of all devices.
+
+use core::cell::Cell;
+use kernel::common::{List, ListLink, ListNode};
+use kernel::common::take_cell::TakeCell;
+use kernel::hil::uart::{UART, UARTParams, Client};
+
+pub struct MuxUart<'a, U: UART + 'a> {
+    uart: &'a U,
+    devices: List<'a, UartDevice<'a, U>>,
+    inflight: Cell<Option<&'a UartDevice<'a, U>>>,
+}
+
+impl<'a, U: UART> Client for MuxUart<'a, U> {
+    fn read_done(&self, byte: u8) {
+        for device in self.devices.iter() {
+            device.client.get().map(|client| client.read_done(byte));
+        }
+    }
+
+    fn write_done(&self, buffer: &'static mut [u8]) {
+        self.inflight.get().map(move |device| {
+            self.inflight.set(None);
+            device.client.get().map(move |client| client.write_done(buffer));
+        });
+        self.do_next_op();
+    }
+}
+
+impl<'a, U: UART> MuxUart<'a, U> {
+    pub const fn new(uart: &'a U) -> MuxUart<'a, U> {
+        MuxUart {
+            uart: uart,
+            devices: List::new(),
+            inflight: Cell::new(None),
+        }
+    }
+
+    fn do_next_op(&self) {
+        if self.inflight.get().is_none() {
+            let mnode = self.devices.iter().find(|node| node.tx_buffer.is_some());
+            mnode.map(|node| {
+                node.tx_buffer.take().map(|buf| {
+                    self.uart.send_bytes(buf, node.tx_len.get());
+                });
+                self.inflight.set(Some(node));
+            });
+        }
+    }
+}
+
+pub struct UartDevice<'a, U: UART + 'a> {
+    mux: &'a MuxUart<'a, U>,
+    tx_buffer: TakeCell<&'static mut [u8]>,
+    tx_len: Cell<usize>,
+    next: ListLink<'a, UartDevice<'a, U>>,
+    client: Cell<Option<&'a Client>>,
+}
+
+impl<'a, U: UART> UartDevice<'a, U> {
+    pub const fn new(mux: &'a MuxUart<'a, U>) -> UartDevice<'a, U> {
+        UartDevice {
+            mux: mux,
+            tx_buffer: TakeCell::empty(),
+            tx_len: Cell::new(0),
+            next: ListLink::empty(),
+            client: Cell::new(None),
+        }
+    }
+
+    pub fn set_client(&'a self, client: &'a Client) {
+        self.mux.devices.push_head(self);
+        self.client.set(Some(client));
+    }
+}
+
+impl<'a, U: UART> ListNode<'a, UartDevice<'a, U>> for UartDevice<'a, U> {
+    fn next(&'a self) -> &'a ListLink<'a, UartDevice<'a, U>> {
+        &self.next
+    }
+}
+
+impl<'a, U: UART> UART for UartDevice<'a, U> {
+    /// The underlying UART is configured by the board, not by its users.
+    fn init(&mut self, _params: UARTParams) {}
+
+    fn send_byte(&self, byte: u8) {
+        self.mux.uart.send_byte(byte);
+    }
+
+    fn send_bytes(&self, bytes: &'static mut [u8], len: usize) {
+        self.tx_buffer.replace(bytes);
+        self.tx_len.set(len);
+        self.mux.do_next_op();
+    }
+
+    fn read_byte(&self) -> u8 {
+        self.mux.uart.read_byte()
+    }
+
+    fn rx_ready(&self) -> bool {
+        self.mux.uart.rx_ready()
+    }
+
+    fn tx_ready(&self) -> bool {
+        self.mux.uart.tx_ready()
+    }
+
+    fn enable_rx(&self) {
+        self.mux.uart.enable_rx();
+    }
+
+    /// Other devices may still be receiving, so the underlying UART is left
+    /// enabled.
+    fn disable_rx(&mut self) {}
+
+    fn enable_tx(&self) {
+        self.mux.uart.enable_tx();
+    }
+
+    /// Other devices may still be sending, so the underlying UART is left
+    /// enabled.
+    fn disable_tx(&mut self) {}
+}
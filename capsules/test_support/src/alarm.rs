@@ -0,0 +1,113 @@
+//! A fake alarm with a clock the test moves by hand.
+
+use CallLog;
+use kernel::hil::time::{self, Alarm, Frequency, Time};
+use std::cell::Cell;
+use std::marker::PhantomData;
+
+#[derive(Copy,Clone,Debug,PartialEq,Eq)]
+pub enum AlarmCall {
+    Now,
+    SetAlarm(u32),
+    GetAlarm,
+    Disable,
+    IsArmed,
+}
+
+pub struct MockAlarm<'a, F: Frequency> {
+    client: Cell<Option<&'a time::Client>>,
+    now: Cell<u32>,
+    alarm: Cell<u32>,
+    armed: Cell<bool>,
+    log: CallLog<AlarmCall>,
+    frequency: PhantomData<F>,
+}
+
+impl<'a, F: Frequency> MockAlarm<'a, F> {
+    pub fn new() -> MockAlarm<'a, F> {
+        MockAlarm {
+            client: Cell::new(None),
+            now: Cell::new(0),
+            alarm: Cell::new(0),
+            armed: Cell::new(false),
+            log: CallLog::new(),
+            frequency: PhantomData,
+        }
+    }
+
+    pub fn set_client(&self, client: &'a time::Client) {
+        self.client.set(Some(client));
+    }
+
+    pub fn calls(&self) -> Vec<AlarmCall> {
+        self.log.calls()
+    }
+
+    pub fn take_calls(&self) -> Vec<AlarmCall> {
+        self.log.take()
+    }
+
+    /// Sets the clock to `now` without firing the alarm.
+    pub fn set_now(&self, now: u32) {
+        self.now.set(now);
+    }
+
+    /// Moves the clock forward by `tics` without firing the alarm.
+    pub fn advance(&self, tics: u32) {
+        self.now.set(self.now.get().wrapping_add(tics));
+    }
+
+    /// Whether the alarm is armed for a time the clock has reached, within
+    /// half the range of the clock.
+    pub fn is_due(&self) -> bool {
+        self.armed.get() && self.now.get().wrapping_sub(self.alarm.get()) < (1 << 31)
+    }
+
+    /// Disarms the alarm and calls the client's `fired`, whether or not the
+    /// alarm is due.
+    pub fn fire(&self) {
+        self.armed.set(false);
+        self.client.get().map(|client| client.fired());
+    }
+
+    /// Fires the alarm if it is due. Returns whether it fired.
+    pub fn fire_if_due(&self) -> bool {
+        let due = self.is_due();
+        if due {
+            self.fire();
+        }
+        due
+    }
+}
+
+impl<'a, F: Frequency> Time for MockAlarm<'a, F> {
+    fn disable(&self) {
+        self.log.record(AlarmCall::Disable);
+        self.armed.set(false);
+    }
+
+    fn is_armed(&self) -> bool {
+        self.log.record(AlarmCall::IsArmed);
+        self.armed.get()
+    }
+}
+
+impl<'a, F: Frequency> Alarm for MockAlarm<'a, F> {
+    type Frequency = F;
+
+    fn now(&self) -> u32 {
+        self.log.record(AlarmCall::Now);
+        self.now.get()
+    }
+
+    fn set_alarm(&self, tics: u32) {
+        self.log.record(AlarmCall::SetAlarm(tics));
+        self.alarm.set(tics);
+        self.armed.set(true);
+    }
+
+    fn get_alarm(&self) -> u32 {
+        self.log.record(AlarmCall::GetAlarm);
+        self.alarm.get()
+    }
+}
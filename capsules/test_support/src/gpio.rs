@@ -0,0 +1,122 @@
+//! A fake GPIO pin.
+
+use CallLog;
+use kernel::hil::gpio::{self, InputMode, InterruptMode, PinCtl};
+use std::cell::Cell;
+
+#[derive(Copy,Clone,Debug,PartialEq,Eq)]
+pub enum PinCall {
+    MakeOutput,
+    MakeInput,
+    Disable,
+    Set,
+    Clear,
+    Toggle,
+    Read,
+    EnableInterrupt(usize, InterruptMode),
+    DisableInterrupt,
+    SetInputMode(InputMode),
+}
+
+pub struct MockPin<'a> {
+    client: Cell<Option<&'a gpio::Client>>,
+    level: Cell<bool>,
+    interrupt: Cell<Option<usize>>,
+    log: CallLog<PinCall>,
+}
+
+impl<'a> MockPin<'a> {
+    pub fn new() -> MockPin<'a> {
+        MockPin {
+            client: Cell::new(None),
+            level: Cell::new(false),
+            interrupt: Cell::new(None),
+            log: CallLog::new(),
+        }
+    }
+
+    pub fn set_client(&self, client: &'a gpio::Client) {
+        self.client.set(Some(client));
+    }
+
+    pub fn calls(&self) -> Vec<PinCall> {
+        self.log.calls()
+    }
+
+    pub fn take_calls(&self) -> Vec<PinCall> {
+        self.log.take()
+    }
+
+    /// The level the pin was last set to or driven to.
+    pub fn level(&self) -> bool {
+        self.level.get()
+    }
+
+    /// Sets the level `read` returns, without interrupting.
+    pub fn set_level(&self, level: bool) {
+        self.level.set(level);
+    }
+
+    /// Calls the client's `fired` with the identifier passed to
+    /// `enable_interrupt`. Returns whether the interrupt was enabled.
+    pub fn fire(&self) -> bool {
+        match self.interrupt.get() {
+            Some(identifier) => {
+                self.client.get().map(|client| client.fired(identifier));
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+impl<'a> PinCtl for MockPin<'a> {
+    fn set_input_mode(&self, mode: InputMode) {
+        self.log.record(PinCall::SetInputMode(mode));
+    }
+}
+
+impl<'a> gpio::Pin for MockPin<'a> {
+    fn make_output(&self) {
+        self.log.record(PinCall::MakeOutput);
+    }
+
+    fn make_input(&self) {
+        self.log.record(PinCall::MakeInput);
+    }
+
+    fn disable(&self) {
+        self.log.record(PinCall::Disable);
+        self.interrupt.set(None);
+    }
+
+    fn set(&self) {
+        self.log.record(PinCall::Set);
+        self.level.set(true);
+    }
+
+    fn clear(&self) {
+        self.log.record(PinCall::Clear);
+        self.level.set(false);
+    }
+
+    fn toggle(&self) {
+        self.log.record(PinCall::Toggle);
+        self.level.set(!self.level.get());
+    }
+
+    fn read(&self) -> bool {
+        self.log.record(PinCall::Read);
+        self.level.get()
+    }
+
+    fn enable_interrupt(&self, identifier: usize, mode: InterruptMode) {
+        self.log.record(PinCall::EnableInterrupt(identifier, mode));
+        self.interrupt.set(Some(identifier));
+    }
+
+    fn disable_interrupt(&self) {
+        self.log.record(PinCall::DisableInterrupt);
+        self.interrupt.set(None);
+    }
+}
@@ -0,0 +1,131 @@
+//! A fake I2C controller with scripted responses.
+
+use CallLog;
+use kernel::common::take_cell::TakeCell;
+use kernel::hil::i2c::{Error, I2CClient, I2CController};
+use std::cell::{Cell, RefCell};
+use std::cmp;
+use std::collections::VecDeque;
+
+#[derive(Clone,Debug,PartialEq,Eq)]
+pub enum I2CCall {
+    Enable,
+    Disable,
+    WriteRead { addr: u8, data: Vec<u8>, read_len: u8 },
+    Write { addr: u8, data: Vec<u8> },
+    Read { addr: u8, len: u8 },
+}
+
+pub struct MockI2C<'a> {
+    client: Cell<Option<&'a I2CClient>>,
+    log: CallLog<I2CCall>,
+
+    /// Results for the next transfers: the bytes read, and the error the
+    /// transfer completes with.
+    responses: RefCell<VecDeque<(Vec<u8>, Error)>>,
+
+    /// The buffer of the transfer in progress and the number of bytes it
+    /// reads.
+    buffer: TakeCell<&'static mut [u8]>,
+    read_len: Cell<usize>,
+}
+
+impl<'a> MockI2C<'a> {
+    pub fn new() -> MockI2C<'a> {
+        MockI2C {
+            client: Cell::new(None),
+            log: CallLog::new(),
+            responses: RefCell::new(VecDeque::new()),
+            buffer: TakeCell::empty(),
+            read_len: Cell::new(0),
+        }
+    }
+
+    pub fn set_client(&self, client: &'a I2CClient) {
+        self.client.set(Some(client));
+    }
+
+    pub fn calls(&self) -> Vec<I2CCall> {
+        self.log.calls()
+    }
+
+    pub fn take_calls(&self) -> Vec<I2CCall> {
+        self.log.take()
+    }
+
+    /// Queues a successful transfer that reads `data`.
+    pub fn push_response(&self, data: &[u8]) {
+        self.responses.borrow_mut().push_back((data.to_vec(), Error::CommandComplete));
+    }
+
+    /// Queues a transfer that fails with `error`, such as `AddressNak`.
+    pub fn push_error(&self, error: Error) {
+        self.responses.borrow_mut().push_back((Vec::new(), error));
+    }
+
+    /// Whether a transfer is waiting to be completed.
+    pub fn is_busy(&self) -> bool {
+        self.buffer.is_some()
+    }
+
+    /// Completes the transfer in progress with the next queued response, or
+    /// successfully without changing the buffer if none is queued. Returns
+    /// whether a transfer was in progress.
+    pub fn complete(&self) -> bool {
+        match self.buffer.take() {
+            Some(buffer) => {
+                let (data, error) = self.responses
+                    .borrow_mut()
+                    .pop_front()
+                    .unwrap_or((Vec::new(), Error::CommandComplete));
+                let len = cmp::min(cmp::min(data.len(), self.read_len.get()), buffer.len());
+                buffer[..len].clone_from_slice(&data[..len]);
+                self.client.get().map(move |client| client.command_complete(buffer, error));
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn start(&self, buffer: &'static mut [u8], read_len: u8) {
+        self.read_len.set(read_len as usize);
+        self.buffer.replace(buffer);
+    }
+}
+
+impl<'a> I2CController for MockI2C<'a> {
+    fn enable(&self) {
+        self.log.record(I2CCall::Enable);
+    }
+
+    fn disable(&self) {
+        self.log.record(I2CCall::Disable);
+    }
+
+    fn write_read(&self, addr: u8, data: &'static mut [u8], write_len: u8, read_len: u8) {
+        let written = data[..cmp::min(write_len as usize, data.len())].to_vec();
+        self.log.record(I2CCall::WriteRead {
+            addr: addr,
+            data: written,
+            read_len: read_len,
+        });
+        self.start(data, read_len);
+    }
+
+    fn write(&self, addr: u8, data: &'static mut [u8], len: u8) {
+        let written = data[..cmp::min(len as usize, data.len())].to_vec();
+        self.log.record(I2CCall::Write {
+            addr: addr,
+            data: written,
+        });
+        self.start(data, 0);
+    }
+
+    fn read(&self, addr: u8, buffer: &'static mut [u8], len: u8) {
+        self.log.record(I2CCall::Read {
+            addr: addr,
+            len: len,
+        });
+        self.start(buffer, len);
+    }
+}
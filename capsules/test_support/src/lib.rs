@@ -0,0 +1,77 @@
+//! Fake HIL implementations for testing capsules on a host.
+//!
+//! Each fake implements one of the `kernel::hil` traits, records every call
+//! made to it and only calls its client when the test says so. A test drives
+//! a capsule, checks the calls it made with `calls` or `take_calls`, then
+//! completes the operation with the fake's `fire` or `complete` method and
+//! checks how the capsule reacted.
+//!
+//! ```ignore
+//! let alarm = MockAlarm::<Freq1KHz>::new();
+//! let mux = MuxAlarm::new(&alarm);
+//! alarm.set_client(&mux);
+//! let virtual_alarm = VirtualMuxAlarm::new(&mux);
+//! virtual_alarm.set_client(&client);
+//!
+//! virtual_alarm.set_alarm(100);
+//! assert!(alarm.calls().contains(&AlarmCall::SetAlarm(100)));
+//! alarm.advance(100);
+//! assert!(alarm.fire_if_due());
+//! ```
+
+extern crate kernel;
+
+pub mod alarm;
+pub mod gpio;
+pub mod i2c;
+pub mod spi;
+pub mod uart;
+
+pub use alarm::{AlarmCall, MockAlarm};
+pub use gpio::{MockPin, PinCall};
+pub use i2c::{I2CCall, MockI2C};
+pub use spi::{MockSpi, SpiCall};
+pub use uart::{MockUart, UartCall};
+
+use std::cell::RefCell;
+use std::mem;
+
+/// Moves `value` to the heap and never frees it, giving the `'static`
+/// reference capsules expect. The host equivalent of `static_init!`.
+pub fn leak<T>(value: T) -> &'static mut T {
+    unsafe { &mut *Box::into_raw(Box::new(value)) }
+}
+
+/// The errno newlib provides on the boards. `kernel::common::math` reads it
+/// through `__errno`, which the host's C library doesn't define.
+static mut ERRNO: i32 = 0;
+
+#[no_mangle]
+pub unsafe extern "C" fn __errno() -> &'static mut i32 {
+    &mut ERRNO
+}
+
+/// The calls made to a fake, in order.
+pub struct CallLog<T: Clone> {
+    calls: RefCell<Vec<T>>,
+}
+
+impl<T: Clone> CallLog<T> {
+    pub fn new() -> CallLog<T> {
+        CallLog { calls: RefCell::new(Vec::new()) }
+    }
+
+    pub fn record(&self, call: T) {
+        self.calls.borrow_mut().push(call);
+    }
+
+    /// Every call recorded so far.
+    pub fn calls(&self) -> Vec<T> {
+        self.calls.borrow().clone()
+    }
+
+    /// Every call recorded since the last `take`.
+    pub fn take(&self) -> Vec<T> {
+        mem::replace(&mut *self.calls.borrow_mut(), Vec::new())
+    }
+}
@@ -0,0 +1,187 @@
+//! A fake SPI master with scripted responses.
+
+use CallLog;
+use kernel::common::take_cell::TakeCell;
+use kernel::hil::spi::{ClockPhase, ClockPolarity, SpiMaster, SpiMasterClient};
+use std::cell::{Cell, RefCell};
+use std::cmp;
+use std::collections::VecDeque;
+
+#[derive(Clone,Debug,PartialEq)]
+pub enum SpiCall {
+    Init,
+    ReadWriteBytes { write: Vec<u8>, read: bool, len: usize },
+    WriteByte(u8),
+    ReadByte,
+    ReadWriteByte(u8),
+    SpecifyChipSelect(u8),
+    SetRate(u32),
+    SetClock(ClockPolarity),
+    SetPhase(ClockPhase),
+    HoldLow,
+    ReleaseLow,
+}
+
+pub struct MockSpi<'a> {
+    client: Cell<Option<&'a SpiMasterClient>>,
+    log: CallLog<SpiCall>,
+    rate: Cell<u32>,
+    polarity: Cell<ClockPolarity>,
+    phase: Cell<ClockPhase>,
+
+    /// Bytes clocked back by the next transfers and single-byte reads. Bytes
+    /// not scripted read as zero.
+    responses: RefCell<VecDeque<u8>>,
+
+    /// The buffers and length of the transfer in progress.
+    write_buffer: TakeCell<&'static mut [u8]>,
+    read_buffer: TakeCell<&'static mut [u8]>,
+    len: Cell<usize>,
+}
+
+impl<'a> MockSpi<'a> {
+    pub fn new() -> MockSpi<'a> {
+        MockSpi {
+            client: Cell::new(None),
+            log: CallLog::new(),
+            rate: Cell::new(0),
+            polarity: Cell::new(ClockPolarity::IdleLow),
+            phase: Cell::new(ClockPhase::SampleLeading),
+            responses: RefCell::new(VecDeque::new()),
+            write_buffer: TakeCell::empty(),
+            read_buffer: TakeCell::empty(),
+            len: Cell::new(0),
+        }
+    }
+
+    pub fn calls(&self) -> Vec<SpiCall> {
+        self.log.calls()
+    }
+
+    pub fn take_calls(&self) -> Vec<SpiCall> {
+        self.log.take()
+    }
+
+    /// Queues `bytes` to be clocked back by the next reads.
+    pub fn push_response(&self, bytes: &[u8]) {
+        self.responses.borrow_mut().extend(bytes.iter().cloned());
+    }
+
+    /// Completes the transfer in progress, filling the read buffer from the
+    /// queued responses, and calls the client's `read_write_done`. Returns
+    /// whether a transfer was in progress.
+    pub fn complete(&self) -> bool {
+        match self.write_buffer.take() {
+            Some(write_buffer) => {
+                let len = self.len.get();
+                let mut read_buffer = self.read_buffer.take();
+                read_buffer.as_mut().map(|buffer| {
+                    for byte in buffer[..len].iter_mut() {
+                        *byte = self.next_response();
+                    }
+                });
+                self.client.get().map(move |client| {
+                    client.read_write_done(write_buffer, read_buffer, len)
+                });
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn next_response(&self) -> u8 {
+        self.responses.borrow_mut().pop_front().unwrap_or(0)
+    }
+}
+
+impl<'a> SpiMaster for MockSpi<'a> {
+    type ChipSelect = u8;
+
+    fn set_client(&self, client: &'static SpiMasterClient) {
+        self.client.set(Some(client));
+    }
+
+    fn init(&self) {
+        self.log.record(SpiCall::Init);
+    }
+
+    fn is_busy(&self) -> bool {
+        self.write_buffer.is_some()
+    }
+
+    fn read_write_bytes(&self,
+                        write_buffer: &'static mut [u8],
+                        read_buffer: Option<&'static mut [u8]>,
+                        len: usize)
+                        -> bool {
+        let mut len = cmp::min(len, write_buffer.len());
+        read_buffer.as_ref().map(|buffer| len = cmp::min(len, buffer.len()));
+        self.log.record(SpiCall::ReadWriteBytes {
+            write: write_buffer[..len].to_vec(),
+            read: read_buffer.is_some(),
+            len: len,
+        });
+        if self.is_busy() {
+            return false;
+        }
+        self.write_buffer.replace(write_buffer);
+        read_buffer.map(|buffer| self.read_buffer.replace(buffer));
+        self.len.set(len);
+        true
+    }
+
+    fn write_byte(&self, val: u8) {
+        self.log.record(SpiCall::WriteByte(val));
+        self.next_response();
+    }
+
+    fn read_byte(&self) -> u8 {
+        self.log.record(SpiCall::ReadByte);
+        self.next_response()
+    }
+
+    fn read_write_byte(&self, val: u8) -> u8 {
+        self.log.record(SpiCall::ReadWriteByte(val));
+        self.next_response()
+    }
+
+    fn specify_chip_select(&self, cs: u8) {
+        self.log.record(SpiCall::SpecifyChipSelect(cs));
+    }
+
+    fn set_rate(&self, rate: u32) -> u32 {
+        self.log.record(SpiCall::SetRate(rate));
+        self.rate.set(rate);
+        rate
+    }
+
+    fn get_rate(&self) -> u32 {
+        self.rate.get()
+    }
+
+    fn set_clock(&self, polarity: ClockPolarity) {
+        self.log.record(SpiCall::SetClock(polarity));
+        self.polarity.set(polarity);
+    }
+
+    fn get_clock(&self) -> ClockPolarity {
+        self.polarity.get()
+    }
+
+    fn set_phase(&self, phase: ClockPhase) {
+        self.log.record(SpiCall::SetPhase(phase));
+        self.phase.set(phase);
+    }
+
+    fn get_phase(&self) -> ClockPhase {
+        self.phase.get()
+    }
+
+    fn hold_low(&self) {
+        self.log.record(SpiCall::HoldLow);
+    }
+
+    fn release_low(&self) {
+        self.log.record(SpiCall::ReleaseLow);
+    }
+}
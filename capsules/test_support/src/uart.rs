@@ -0,0 +1,127 @@
+//! A fake UART.
+
+use CallLog;
+use kernel::common::take_cell::TakeCell;
+use kernel::hil::uart::{self, UART, UARTParams};
+use std::cell::{Cell, RefCell};
+use std::cmp;
+use std::collections::VecDeque;
+
+#[derive(Clone,Debug,PartialEq,Eq)]
+pub enum UartCall {
+    Init { baud_rate: u32 },
+    SendByte(u8),
+    SendBytes(Vec<u8>),
+    ReadByte,
+    EnableRx,
+    DisableRx,
+    EnableTx,
+    DisableTx,
+}
+
+pub struct MockUart<'a> {
+    client: Cell<Option<&'a uart::Client>>,
+    log: CallLog<UartCall>,
+
+    /// The buffer of the transmission in progress.
+    tx_buffer: TakeCell<&'static mut [u8]>,
+
+    /// Bytes returned by `read_byte`.
+    input: RefCell<VecDeque<u8>>,
+}
+
+impl<'a> MockUart<'a> {
+    pub fn new() -> MockUart<'a> {
+        MockUart {
+            client: Cell::new(None),
+            log: CallLog::new(),
+            tx_buffer: TakeCell::empty(),
+            input: RefCell::new(VecDeque::new()),
+        }
+    }
+
+    pub fn set_client(&self, client: &'a uart::Client) {
+        self.client.set(Some(client));
+    }
+
+    pub fn calls(&self) -> Vec<UartCall> {
+        self.log.calls()
+    }
+
+    pub fn take_calls(&self) -> Vec<UartCall> {
+        self.log.take()
+    }
+
+    /// Whether a transmission is waiting to be completed.
+    pub fn is_busy(&self) -> bool {
+        self.tx_buffer.is_some()
+    }
+
+    /// Completes the transmission in progress, returning its buffer with the
+    /// client's `write_done`. Returns whether one was in progress.
+    pub fn complete_write(&self) -> bool {
+        match self.tx_buffer.take() {
+            Some(buffer) => {
+                self.client.get().map(move |client| client.write_done(buffer));
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Passes `byte` to the client's `read_done`, as if it had just been
+    /// received.
+    pub fn receive(&self, byte: u8) {
+        self.client.get().map(|client| client.read_done(byte));
+    }
+
+    /// Queues `bytes` for `read_byte` to return.
+    pub fn push_input(&self, bytes: &[u8]) {
+        self.input.borrow_mut().extend(bytes.iter().cloned());
+    }
+}
+
+impl<'a> UART for MockUart<'a> {
+    fn init(&mut self, params: UARTParams) {
+        self.log.record(UartCall::Init { baud_rate: params.baud_rate });
+    }
+
+    fn send_byte(&self, byte: u8) {
+        self.log.record(UartCall::SendByte(byte));
+    }
+
+    fn send_bytes(&self, bytes: &'static mut [u8], len: usize) {
+        let len = cmp::min(len, bytes.len());
+        self.log.record(UartCall::SendBytes(bytes[..len].to_vec()));
+        self.tx_buffer.replace(bytes);
+    }
+
+    fn read_byte(&self) -> u8 {
+        self.log.record(UartCall::ReadByte);
+        self.input.borrow_mut().pop_front().unwrap_or(0)
+    }
+
+    fn rx_ready(&self) -> bool {
+        !self.input.borrow().is_empty()
+    }
+
+    fn tx_ready(&self) -> bool {
+        self.tx_buffer.is_none()
+    }
+
+    fn enable_rx(&self) {
+        self.log.record(UartCall::EnableRx);
+    }
+
+    fn disable_rx(&mut self) {
+        self.log.record(UartCall::DisableRx);
+    }
+
+    fn enable_tx(&self) {
+        self.log.record(UartCall::EnableTx);
+    }
+
+    fn disable_tx(&mut self) {
+        self.log.record(UartCall::DisableTx);
+    }
+}
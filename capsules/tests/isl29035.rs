@@ -0,0 +1,79 @@
+//! The ISL29035's I2C sequence, run against a fake I2C bus.
+
+extern crate capsules;
+extern crate kernel;
+extern crate test_support;
+
+use capsules::isl29035::Isl29035;
+use capsules::virtual_i2c::{I2CDevice, MuxI2C};
+use kernel::{AppId, Driver, ReturnCode};
+use kernel::hil::i2c::Error;
+use test_support::{I2CCall, MockI2C, leak};
+
+const ADDR: u8 = 0x44;
+
+fn sensor() -> (&'static MockI2C<'static>, &'static Isl29035<'static>) {
+    let i2c: &'static MockI2C<'static> = leak(MockI2C::new());
+    let mux: &'static MuxI2C<'static> = leak(MuxI2C::new(i2c));
+    i2c.set_client(mux);
+    let device: &'static I2CDevice<'static> = leak(I2CDevice::new(mux, ADDR));
+    let isl29035: &'static Isl29035<'static> = leak(Isl29035::new(device, leak([0; 3])));
+    device.set_client(isl29035);
+    (i2c, isl29035)
+}
+
+fn start_read(isl29035: &Isl29035) -> ReturnCode {
+    isl29035.command(0, 0, 0, unsafe { AppId::new(0, 0) })
+}
+
+fn write(data: &[u8]) -> I2CCall {
+    I2CCall::Write {
+        addr: ADDR,
+        data: data.to_vec(),
+    }
+}
+
+#[test]
+fn reading_configures_reads_and_powers_down() {
+    let (i2c, isl29035) = sensor();
+
+    assert_eq!(start_read(isl29035), ReturnCode::SUCCESS);
+    assert_eq!(i2c.take_calls(),
+               vec![I2CCall::Enable, write(&[0x00, 0b10100000, 0b00001001])]);
+    assert!(i2c.complete());
+    assert_eq!(i2c.take_calls(),
+               vec![I2CCall::WriteRead {
+                        addr: ADDR,
+                        data: vec![0x02],
+                        read_len: 2,
+                    }]);
+
+    // Another command while a reading is in progress is ignored.
+    assert_eq!(start_read(isl29035), ReturnCode::SUCCESS);
+    assert!(i2c.take_calls().is_empty());
+
+    i2c.push_response(&[0x80, 0x00]);
+    assert!(i2c.complete());
+    assert_eq!(i2c.take_calls(), vec![write(&[0x00, 0x00])]);
+    assert!(i2c.complete());
+    assert_eq!(i2c.take_calls(), vec![I2CCall::Disable]);
+    assert!(!i2c.is_busy());
+}
+
+#[test]
+fn nak_returns_to_disabled() {
+    let (i2c, isl29035) = sensor();
+
+    assert_eq!(start_read(isl29035), ReturnCode::SUCCESS);
+    assert_eq!(i2c.take_calls(),
+               vec![I2CCall::Enable, write(&[0x00, 0b10100000, 0b00001001])]);
+    i2c.push_error(Error::AddressNak);
+    assert!(i2c.complete());
+    assert_eq!(i2c.take_calls(), vec![I2CCall::Disable]);
+    assert!(!i2c.is_busy());
+
+    // The next command starts over.
+    assert_eq!(start_read(isl29035), ReturnCode::SUCCESS);
+    assert_eq!(i2c.take_calls(),
+               vec![I2CCall::Enable, write(&[0x00, 0b10100000, 0b00001001])]);
+}
@@ -0,0 +1,79 @@
+//! Parsing the process console's command lines, against a fake UART. No
+//! processes are loaded.
+
+extern crate capsules;
+extern crate kernel;
+extern crate test_support;
+
+use capsules::process_console::ProcessConsole;
+use test_support::{MockUart, UartCall, leak};
+
+const HELP: &'static [u8] = b"Commands: !list, !stop N, !start N, !restart N\r\n";
+
+fn console() -> &'static MockUart<'static> {
+    let uart: &'static MockUart<'static> = leak(MockUart::new());
+    let console: &'static ProcessConsole<'static, MockUart<'static>> =
+        leak(ProcessConsole::new(uart, leak([0; 256]), leak([0; 32])));
+    uart.set_client(console);
+    uart
+}
+
+/// Types `line` and returns what the console sent back, completing the
+/// write.
+fn enter(uart: &MockUart, line: &[u8]) -> Vec<u8> {
+    for &byte in line {
+        uart.receive(byte);
+    }
+    let sent = uart.take_calls()
+        .into_iter()
+        .flat_map(|call| match call {
+            UartCall::SendBytes(bytes) => bytes,
+            call => panic!("Unexpected {:?}", call),
+        })
+        .collect();
+    uart.complete_write();
+    sent
+}
+
+#[test]
+fn runs_commands_with_prefix() {
+    let uart = console();
+    assert_eq!(enter(uart, b"!stop 0\n"), b"No process 0\r\n".to_vec());
+    assert_eq!(enter(uart, b"!restart 3\r\n"), b"No process 3\r\n".to_vec());
+    assert_eq!(enter(uart, b"!start   1  \n"), b"No process 1\r\n".to_vec());
+    assert!(enter(uart, b"!list\n").starts_with(b"PID State"));
+}
+
+#[test]
+fn leaves_lines_without_prefix_alone() {
+    let uart = console();
+    assert_eq!(enter(uart, b"stop 0\n"), Vec::new());
+    assert_eq!(enter(uart, b"hello !list\n"), Vec::new());
+    assert_eq!(enter(uart, b" !stop 0\n"), Vec::new());
+    assert_eq!(enter(uart, b"\r\n\n"), Vec::new());
+}
+
+#[test]
+fn unknown_or_malformed_commands_print_help() {
+    let uart = console();
+    assert_eq!(enter(uart, b"!\n"), HELP.to_vec());
+    assert_eq!(enter(uart, b"!kill 0\n"), HELP.to_vec());
+    assert_eq!(enter(uart, b"!stop\n"), HELP.to_vec());
+    assert_eq!(enter(uart, b"!stop one\n"), HELP.to_vec());
+    assert_eq!(enter(uart, b"!stop -1\n"), HELP.to_vec());
+
+    // A line longer than the command buffer loses its end.
+    assert_eq!(enter(uart, b"!stop                              0\n"), HELP.to_vec());
+}
+
+#[test]
+fn ignores_command_while_sending() {
+    let uart = console();
+    for &byte in b"!stop 0\n!stop 1\n" {
+        uart.receive(byte);
+    }
+    assert_eq!(uart.take_calls(),
+               vec![UartCall::SendBytes(b"No process 0\r\n".to_vec())]);
+    uart.complete_write();
+    assert_eq!(enter(uart, b"!stop 1\n"), b"No process 1\r\n".to_vec());
+}
@@ -0,0 +1,127 @@
+//! The TMP006's I2C sequences, run against a fake I2C bus and interrupt pin.
+
+extern crate capsules;
+extern crate kernel;
+extern crate test_support;
+
+use capsules::tmp006::TMP006;
+use capsules::virtual_i2c::{I2CDevice, MuxI2C};
+use kernel::{AppId, Callback, Driver, ReturnCode};
+use kernel::hil::gpio::InterruptMode;
+use kernel::hil::i2c::Error;
+use test_support::{I2CCall, MockI2C, MockPin, PinCall, leak};
+
+const ADDR: u8 = 0x40;
+
+struct Sensor {
+    i2c: &'static MockI2C<'static>,
+    pin: &'static MockPin<'static>,
+    tmp006: &'static TMP006<'static>,
+}
+
+fn sensor() -> Sensor {
+    let i2c: &'static MockI2C<'static> = leak(MockI2C::new());
+    let mux: &'static MuxI2C<'static> = leak(MuxI2C::new(i2c));
+    i2c.set_client(mux);
+    let device: &'static I2CDevice<'static> = leak(I2CDevice::new(mux, ADDR));
+    let pin: &'static MockPin<'static> = leak(MockPin::new());
+    let tmp006: &'static TMP006<'static> = leak(TMP006::new(device, pin, leak([0; 3])));
+    device.set_client(tmp006);
+    pin.set_client(tmp006);
+    Sensor {
+        i2c: i2c,
+        pin: pin,
+        tmp006: tmp006,
+    }
+}
+
+fn callback() -> Callback {
+    // No process is loaded, so the callback is never delivered.
+    unsafe { Callback::new(AppId::new(0, 0), 0, 1 as *mut ()) }
+}
+
+fn write(data: &[u8]) -> I2CCall {
+    I2CCall::Write {
+        addr: ADDR,
+        data: data.to_vec(),
+    }
+}
+
+fn read(len: u8) -> I2CCall {
+    I2CCall::Read {
+        addr: ADDR,
+        len: len,
+    }
+}
+
+/// Subscribes for a single reading and completes the configuration write.
+fn configure(sensor: &Sensor) {
+    assert_eq!(sensor.tmp006.subscribe(0, Some(callback()), unsafe { AppId::new(0, 0) }),
+               ReturnCode::SUCCESS);
+    assert_eq!(sensor.i2c.take_calls(),
+               vec![I2CCall::Enable, write(&[0x02, 0x71, 0x00])]);
+    assert!(sensor.i2c.complete());
+    assert_eq!(sensor.i2c.take_calls(), vec![I2CCall::Disable]);
+    assert_eq!(sensor.pin.take_calls(),
+               vec![PinCall::MakeInput, PinCall::EnableInterrupt(0, InterruptMode::FallingEdge)]);
+}
+
+#[test]
+fn single_reading_reads_both_registers_then_disables() {
+    let sensor = sensor();
+    configure(&sensor);
+
+    assert!(sensor.pin.fire());
+    assert_eq!(sensor.i2c.take_calls(), vec![I2CCall::Enable, write(&[0x00])]);
+    assert!(sensor.i2c.complete());
+    assert_eq!(sensor.i2c.take_calls(), vec![read(2)]);
+    sensor.i2c.push_response(&[0x00, 0x10]);
+    assert!(sensor.i2c.complete());
+    assert_eq!(sensor.i2c.take_calls(), vec![write(&[0x01])]);
+    assert!(sensor.i2c.complete());
+    assert_eq!(sensor.i2c.take_calls(), vec![read(2)]);
+    sensor.i2c.push_response(&[0x0C, 0x80]);
+    assert!(sensor.i2c.complete());
+
+    // Single-shot mode turns the sensor off once it has a temperature.
+    assert_eq!(sensor.i2c.take_calls(), vec![write(&[0x02, 0x00, 0x00])]);
+    assert!(sensor.i2c.complete());
+    assert_eq!(sensor.i2c.take_calls(), vec![I2CCall::Disable]);
+    assert_eq!(sensor.pin.take_calls(),
+               vec![PinCall::DisableInterrupt, PinCall::Disable]);
+    assert!(!sensor.i2c.is_busy());
+}
+
+#[test]
+fn nak_abandons_the_reading() {
+    let sensor = sensor();
+    configure(&sensor);
+
+    assert!(sensor.pin.fire());
+    assert_eq!(sensor.i2c.take_calls(), vec![I2CCall::Enable, write(&[0x00])]);
+    sensor.i2c.push_error(Error::AddressNak);
+    assert!(sensor.i2c.complete());
+    assert_eq!(sensor.i2c.take_calls(), vec![I2CCall::Disable]);
+    assert!(!sensor.i2c.is_busy());
+
+    // The buffer came back, so the next interrupt starts a new reading.
+    assert!(sensor.pin.fire());
+    assert_eq!(sensor.i2c.take_calls(), vec![I2CCall::Enable, write(&[0x00])]);
+    assert!(sensor.i2c.complete());
+    assert_eq!(sensor.i2c.take_calls(), vec![read(2)]);
+}
+
+#[test]
+fn unsubscribe_disables_the_sensor() {
+    let sensor = sensor();
+    configure(&sensor);
+
+    assert_eq!(sensor.tmp006.subscribe(0, None, unsafe { AppId::new(0, 0) }),
+               ReturnCode::SUCCESS);
+    assert_eq!(sensor.i2c.take_calls(),
+               vec![I2CCall::Enable, write(&[0x02, 0x00, 0x00])]);
+    assert!(sensor.i2c.complete());
+    assert_eq!(sensor.i2c.take_calls(), vec![I2CCall::Disable]);
+    assert_eq!(sensor.pin.take_calls(),
+               vec![PinCall::DisableInterrupt, PinCall::Disable]);
+}
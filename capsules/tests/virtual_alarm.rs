@@ -0,0 +1,86 @@
+//! Several virtual alarms sharing one hardware alarm through `MuxAlarm`.
+
+extern crate capsules;
+extern crate host;
+extern crate kernel;
+
+use capsules::virtual_alarm::{MuxAlarm, VirtualMuxAlarm};
+use host::alarm::{Alarm, AlarmCall};
+use host::leak;
+use kernel::hil::time::{self, Alarm as AlarmTrait, Time};
+use std::cell::Cell;
+
+struct Client {
+    fired: Cell<usize>,
+}
+
+impl time::Client for Client {
+    fn fired(&self) {
+        self.fired.set(self.fired.get() + 1);
+    }
+}
+
+fn virtual_alarm(mux: &'static MuxAlarm<'static, Alarm>)
+                 -> (&'static VirtualMuxAlarm<'static, Alarm>, &'static Client) {
+    let alarm: &'static VirtualMuxAlarm<'static, Alarm> = leak(VirtualMuxAlarm::new(mux));
+    let client: &'static Client = leak(Client { fired: Cell::new(0) });
+    alarm.set_client(client);
+    (alarm, client)
+}
+
+/// The alarms set on the hardware alarm since the last call.
+fn alarms_set(alarm: &Alarm) -> Vec<u32> {
+    alarm.take_calls()
+        .into_iter()
+        .filter_map(|call| match call {
+            AlarmCall::SetAlarm(tics) => Some(tics),
+            _ => None,
+        })
+        .collect()
+}
+
+#[test]
+fn fires_each_alarm_in_turn() {
+    let alarm: &'static Alarm = leak(Alarm::new());
+    let mux: &'static MuxAlarm<'static, Alarm> = leak(MuxAlarm::new(alarm));
+    alarm.set_client(mux);
+    let (late, late_client) = virtual_alarm(mux);
+    let (early, early_client) = virtual_alarm(mux);
+
+    late.set_alarm(3000);
+    early.set_alarm(1000);
+    assert_eq!(alarms_set(alarm), vec![3000, 1000]);
+
+    alarm.advance(1000);
+    alarm.handle_interrupt();
+    assert_eq!(early_client.fired.get(), 1);
+    assert_eq!(late_client.fired.get(), 0);
+    assert!(!early.is_armed());
+    assert_eq!(alarms_set(alarm), vec![3000]);
+
+    alarm.advance(2000);
+    alarm.handle_interrupt();
+    assert_eq!(early_client.fired.get(), 1);
+    assert_eq!(late_client.fired.get(), 1);
+    assert!(!late.is_armed());
+    assert!(alarms_set(alarm).is_empty());
+}
+
+#[test]
+fn later_alarm_leaves_hardware_alarm_alone() {
+    let alarm: &'static Alarm = leak(Alarm::new());
+    let mux: &'static MuxAlarm<'static, Alarm> = leak(MuxAlarm::new(alarm));
+    alarm.set_client(mux);
+    let (first, _) = virtual_alarm(mux);
+    let (second, _) = virtual_alarm(mux);
+
+    first.set_alarm(1000);
+    second.set_alarm(3000);
+    assert_eq!(alarms_set(alarm), vec![1000]);
+
+    // Disabling one of two armed alarms keeps the hardware alarm running.
+    second.disable();
+    assert!(!alarm.take_calls().contains(&AlarmCall::Disable));
+    first.disable();
+    assert_eq!(alarm.take_calls(), vec![AlarmCall::Disable]);
+}
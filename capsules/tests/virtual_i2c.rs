@@ -0,0 +1,86 @@
+//! Several I2C devices sharing one bus through `MuxI2C`.
+
+extern crate capsules;
+extern crate kernel;
+extern crate test_support;
+
+use capsules::virtual_i2c::{I2CDevice, MuxI2C};
+use kernel::hil::i2c::{Error, I2CClient};
+use kernel::hil::i2c::I2CDevice as I2CDeviceTrait;
+use std::cell::RefCell;
+use test_support::{I2CCall, MockI2C, leak};
+
+struct Client {
+    completed: RefCell<Vec<(Vec<u8>, Error)>>,
+}
+
+impl I2CClient for Client {
+    fn command_complete(&self, buffer: &'static mut [u8], error: Error) {
+        self.completed.borrow_mut().push((buffer.to_vec(), error));
+    }
+}
+
+fn bus() -> (&'static MockI2C<'static>, &'static MuxI2C<'static>) {
+    let i2c: &'static MockI2C<'static> = leak(MockI2C::new());
+    let mux: &'static MuxI2C<'static> = leak(MuxI2C::new(i2c));
+    i2c.set_client(mux);
+    (i2c, mux)
+}
+
+fn device(mux: &'static MuxI2C<'static>, addr: u8)
+          -> (&'static I2CDevice<'static>, &'static Client) {
+    let device: &'static I2CDevice<'static> = leak(I2CDevice::new(mux, addr));
+    let client: &'static Client = leak(Client { completed: RefCell::new(Vec::new()) });
+    device.set_client(client);
+    (device, client)
+}
+
+#[test]
+fn queues_transfers_until_the_bus_is_free() {
+    let (i2c, mux) = bus();
+    let (first, first_client) = device(mux, 0x10);
+    let (second, second_client) = device(mux, 0x20);
+
+    first.write(leak([1, 2]), 2);
+    second.read(leak([0; 2]), 2);
+    assert_eq!(i2c.take_calls(),
+               vec![I2CCall::Write {
+                        addr: 0x10,
+                        data: vec![1, 2],
+                    }]);
+
+    // Completing the first transfer goes to its device and starts the next.
+    assert!(i2c.complete());
+    assert_eq!(*first_client.completed.borrow(),
+               vec![(vec![1, 2], Error::CommandComplete)]);
+    assert!(second_client.completed.borrow().is_empty());
+    assert_eq!(i2c.take_calls(),
+               vec![I2CCall::Read {
+                        addr: 0x20,
+                        len: 2,
+                    }]);
+
+    i2c.push_error(Error::DataNak);
+    assert!(i2c.complete());
+    assert_eq!(*second_client.completed.borrow(),
+               vec![(vec![0, 0], Error::DataNak)]);
+    assert_eq!(first_client.completed.borrow().len(), 1);
+    assert!(!i2c.is_busy());
+}
+
+#[test]
+fn bus_stays_enabled_while_any_device_is() {
+    let (i2c, mux) = bus();
+    let (first, _) = device(mux, 0x10);
+    let (second, _) = device(mux, 0x20);
+
+    first.enable();
+    second.enable();
+    first.enable();
+    assert_eq!(i2c.take_calls(), vec![I2CCall::Enable]);
+
+    first.disable();
+    assert!(i2c.take_calls().is_empty());
+    second.disable();
+    assert_eq!(i2c.take_calls(), vec![I2CCall::Disable]);
+}
@@ -0,0 +1,105 @@
+//! Several SPI devices sharing one SPI master through `MuxSPIMaster`.
+
+extern crate capsules;
+extern crate kernel;
+extern crate test_support;
+
+use capsules::virtual_spi::{MuxSPIMaster, SPIMasterDevice};
+use kernel::hil::spi::{ClockPhase, ClockPolarity, SPIMasterDevice as SPIMasterDeviceTrait,
+                       SpiMaster, SpiMasterClient};
+use std::cell::RefCell;
+use test_support::{MockSpi, SpiCall, leak};
+
+struct Client {
+    completed: RefCell<Vec<Option<Vec<u8>>>>,
+}
+
+impl SpiMasterClient for Client {
+    fn read_write_done(&self,
+                       _write_buffer: &'static mut [u8],
+                       read_buffer: Option<&'static mut [u8]>,
+                       len: usize) {
+        self.completed.borrow_mut().push(read_buffer.map(|buffer| buffer[..len].to_vec()));
+    }
+}
+
+fn bus() -> (&'static MockSpi<'static>, &'static MuxSPIMaster<'static, MockSpi<'static>>) {
+    let spi: &'static MockSpi<'static> = leak(MockSpi::new());
+    let mux: &'static MuxSPIMaster<'static, MockSpi<'static>> = leak(MuxSPIMaster::new(spi));
+    spi.set_client(mux);
+    (spi, mux)
+}
+
+fn device(mux: &'static MuxSPIMaster<'static, MockSpi<'static>>,
+          chip_select: u8)
+          -> (&'static SPIMasterDevice<'static, MockSpi<'static>>, &'static Client) {
+    let device: &'static SPIMasterDevice<'static, MockSpi<'static>> =
+        leak(SPIMasterDevice::new(mux, chip_select));
+    let client: &'static Client = leak(Client { completed: RefCell::new(Vec::new()) });
+    device.set_client(client);
+    (device, client)
+}
+
+#[test]
+fn transfers_run_in_turn_on_their_own_chip_select() {
+    let (spi, mux) = bus();
+    let (first, first_client) = device(mux, 1);
+    let (second, second_client) = device(mux, 2);
+
+    assert!(first.read_write_bytes(leak([0xAA, 0xBB]), Some(leak([0; 2])), 2));
+    assert!(second.read_write_bytes(leak([0xCC]), None, 1));
+    assert_eq!(spi.take_calls(),
+               vec![SpiCall::SpecifyChipSelect(1),
+                    SpiCall::ReadWriteBytes {
+                        write: vec![0xAA, 0xBB],
+                        read: true,
+                        len: 2,
+                    }]);
+
+    spi.push_response(&[0x12, 0x34]);
+    assert!(spi.complete());
+    assert_eq!(*first_client.completed.borrow(), vec![Some(vec![0x12, 0x34])]);
+    assert_eq!(spi.take_calls(),
+               vec![SpiCall::SpecifyChipSelect(2),
+                    SpiCall::ReadWriteBytes {
+                        write: vec![0xCC],
+                        read: false,
+                        len: 1,
+                    }]);
+
+    assert!(spi.complete());
+    assert_eq!(*second_client.completed.borrow(), vec![None]);
+    assert_eq!(first_client.completed.borrow().len(), 1);
+    assert!(!spi.is_busy());
+}
+
+#[test]
+fn configure_waits_for_the_transfer_in_flight() {
+    let (spi, mux) = bus();
+    let (first, _) = device(mux, 1);
+    let (transferring, transferring_client) = device(mux, 2);
+    let (configuring, _) = device(mux, 3);
+
+    assert!(first.read_write_bytes(leak([0x01]), None, 1));
+    spi.take_calls();
+    assert!(transferring.read_write_bytes(leak([0x02]), None, 1));
+    configuring.configure(ClockPolarity::IdleHigh, ClockPhase::SampleTrailing, 1000000);
+    assert!(spi.take_calls().is_empty());
+
+    // The mux picks the configuration first. Configuring finishes straight
+    // away, so the transfer waiting behind it starts too.
+    assert!(spi.complete());
+    assert_eq!(spi.take_calls(),
+               vec![SpiCall::SpecifyChipSelect(3),
+                    SpiCall::SetClock(ClockPolarity::IdleHigh),
+                    SpiCall::SetPhase(ClockPhase::SampleTrailing),
+                    SpiCall::SetRate(1000000),
+                    SpiCall::SpecifyChipSelect(2),
+                    SpiCall::ReadWriteBytes {
+                        write: vec![0x02],
+                        read: false,
+                        len: 1,
+                    }]);
+    assert!(spi.complete());
+    assert_eq!(*transferring_client.completed.borrow(), vec![None]);
+}
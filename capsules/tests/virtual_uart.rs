@@ -0,0 +1,96 @@
+//! Several users sharing one UART through `MuxUart`.
+
+extern crate capsules;
+extern crate kernel;
+extern crate test_support;
+
+use capsules::virtual_uart::{MuxUart, UartDevice};
+use kernel::hil::uart::{self, UART};
+use std::cell::RefCell;
+use test_support::{MockUart, UartCall, leak};
+
+struct Client {
+    written: RefCell<Vec<Vec<u8>>>,
+    read: RefCell<Vec<u8>>,
+}
+
+impl uart::Client for Client {
+    fn write_done(&self, buffer: &'static mut [u8]) {
+        self.written.borrow_mut().push(buffer.to_vec());
+    }
+
+    fn read_done(&self, byte: u8) {
+        self.read.borrow_mut().push(byte);
+    }
+}
+
+fn mux() -> (&'static MockUart<'static>, &'static MuxUart<'static, MockUart<'static>>) {
+    let uart: &'static MockUart<'static> = leak(MockUart::new());
+    let mux: &'static MuxUart<'static, MockUart<'static>> = leak(MuxUart::new(uart));
+    uart.set_client(mux);
+    (uart, mux)
+}
+
+fn device(mux: &'static MuxUart<'static, MockUart<'static>>)
+          -> (&'static UartDevice<'static, MockUart<'static>>, &'static Client) {
+    let device: &'static UartDevice<'static, MockUart<'static>> = leak(UartDevice::new(mux));
+    let client: &'static Client = leak(Client {
+        written: RefCell::new(Vec::new()),
+        read: RefCell::new(Vec::new()),
+    });
+    device.set_client(client);
+    (device, client)
+}
+
+#[test]
+fn writes_are_sent_one_at_a_time() {
+    let (uart, mux) = mux();
+    let (first, first_client) = device(mux);
+    let (second, second_client) = device(mux);
+
+    first.send_bytes(leak([1, 2, 3]), 2);
+    second.send_bytes(leak([4, 5]), 2);
+    assert_eq!(uart.take_calls(), vec![UartCall::SendBytes(vec![1, 2])]);
+
+    // The first buffer goes back to its own client, and only then is the
+    // second one sent.
+    assert!(uart.complete_write());
+    assert_eq!(*first_client.written.borrow(), vec![vec![1, 2, 3]]);
+    assert!(second_client.written.borrow().is_empty());
+    assert_eq!(uart.take_calls(), vec![UartCall::SendBytes(vec![4, 5])]);
+
+    assert!(uart.complete_write());
+    assert_eq!(*second_client.written.borrow(), vec![vec![4, 5]]);
+    assert_eq!(first_client.written.borrow().len(), 1);
+    assert!(!uart.is_busy());
+    assert_eq!(uart.take_calls(), vec![]);
+}
+
+#[test]
+fn later_writes_wait_for_the_one_in_flight() {
+    let (uart, mux) = mux();
+    let (first, _) = device(mux);
+    let (second, _) = device(mux);
+
+    first.send_bytes(leak([1]), 1);
+    assert!(uart.complete_write());
+    assert_eq!(uart.take_calls(), vec![UartCall::SendBytes(vec![1])]);
+
+    second.send_bytes(leak([2]), 1);
+    first.send_bytes(leak([3]), 1);
+    assert_eq!(uart.take_calls(), vec![UartCall::SendBytes(vec![2])]);
+    assert!(uart.complete_write());
+    assert_eq!(uart.take_calls(), vec![UartCall::SendBytes(vec![3])]);
+}
+
+#[test]
+fn every_client_receives_each_byte() {
+    let (uart, mux) = mux();
+    let (_, first_client) = device(mux);
+    let (_, second_client) = device(mux);
+
+    uart.receive(b'a');
+    uart.receive(b'b');
+    assert_eq!(*first_client.read.borrow(), b"ab".to_vec());
+    assert_eq!(*second_client.read.borrow(), b"ab".to_vec());
+}
@@ -0,0 +1,103 @@
+//! Simulated alarm. The clock only moves when the test calls `advance`.
+
+use CallLog;
+use kernel::hil::time::{self, Freq32KHz, Time};
+use std::cell::Cell;
+
+#[derive(Copy,Clone,Debug,PartialEq,Eq)]
+pub enum AlarmCall {
+    Now,
+    SetAlarm(u32),
+    GetAlarm,
+    Disable,
+    IsArmed,
+}
+
+pub struct Alarm {
+    client: Cell<Option<&'static time::Client>>,
+    now: Cell<u32>,
+    alarm: Cell<u32>,
+    armed: Cell<bool>,
+    fired: Cell<bool>,
+    log: CallLog<AlarmCall>,
+}
+
+impl Alarm {
+    pub fn new() -> Alarm {
+        Alarm {
+            client: Cell::new(None),
+            now: Cell::new(0),
+            alarm: Cell::new(0),
+            armed: Cell::new(false),
+            fired: Cell::new(false),
+            log: CallLog::new(),
+        }
+    }
+
+    pub fn set_client(&self, client: &'static time::Client) {
+        self.client.set(Some(client));
+    }
+
+    pub fn calls(&self) -> Vec<AlarmCall> {
+        self.log.calls()
+    }
+
+    pub fn take_calls(&self) -> Vec<AlarmCall> {
+        self.log.take()
+    }
+
+    /// Moves the clock forward by `tics`, firing the alarm if it is reached.
+    pub fn advance(&self, tics: u32) {
+        let now = self.now.get();
+        if self.armed.get() && self.alarm.get().wrapping_sub(now) <= tics {
+            self.fired.set(true);
+        }
+        self.now.set(now.wrapping_add(tics));
+    }
+
+    pub fn is_pending(&self) -> bool {
+        self.fired.get()
+    }
+
+    pub fn handle_interrupt(&self) {
+        if self.fired.get() {
+            self.fired.set(false);
+            self.armed.set(false);
+            self.client.get().map(|client| client.fired());
+        }
+    }
+}
+
+impl Time for Alarm {
+    fn disable(&self) {
+        self.log.record(AlarmCall::Disable);
+        self.armed.set(false);
+        self.fired.set(false);
+    }
+
+    fn is_armed(&self) -> bool {
+        self.log.record(AlarmCall::IsArmed);
+        self.armed.get()
+    }
+}
+
+impl time::Alarm for Alarm {
+    type Frequency = Freq32KHz;
+
+    fn now(&self) -> u32 {
+        self.log.record(AlarmCall::Now);
+        self.now.get()
+    }
+
+    fn set_alarm(&self, tics: u32) {
+        self.log.record(AlarmCall::SetAlarm(tics));
+        self.alarm.set(tics);
+        self.armed.set(true);
+        self.fired.set(false);
+    }
+
+    fn get_alarm(&self) -> u32 {
+        self.log.record(AlarmCall::GetAlarm);
+        self.alarm.get()
+    }
+}
@@ -0,0 +1,240 @@
+//! Simulated apps.
+//!
+//! On hardware the kernel runs a process with `switch_to_user`, which returns
+//! when the process makes a system call or is interrupted. This crate provides
+//! `switch_to_user` for the host instead. It tells the process's `App` what
+//! happened since it last ran, and writes the system call the app returns
+//! into the process's stack as the SVC exception would, so
+//! `kernel::sched::do_process` handles it exactly as on hardware.
+//!
+//! There is no app code to run on the host. The stacked program counter
+//! points into a table of `svc` instructions, which is where the kernel reads
+//! system call numbers from. Callback addresses are never called; they are
+//! passed back to `App::step` to tell callbacks apart.
+
+use kernel::{FaultResponse, LoadError, Process};
+use kernel::loader;
+use kernel::process;
+use kernel::syscall;
+use leak;
+use std::cell::RefCell;
+use std::ptr;
+
+/// Memory set aside for each simulated process. Processes are placed at an
+/// address aligned to their size within it, so it is twice the largest
+/// process.
+pub const MEMORY_POOL_LEN: usize = 128 * 1024;
+
+/// Number of system call numbers that can be simulated.
+const NUM_SVCS: usize = 16;
+
+/// `svc 0` to `svc 15`, in Thumb encoding.
+static SVC_INSTRUCTIONS: [u16; NUM_SVCS] = [0xdf00, 0xdf01, 0xdf02, 0xdf03, 0xdf04, 0xdf05,
+                                            0xdf06, 0xdf07, 0xdf08, 0xdf09, 0xdf0a, 0xdf0b,
+                                            0xdf0c, 0xdf0d, 0xdf0e, 0xdf0f];
+
+/// What happened since an app last ran.
+#[derive(Copy,Clone,Debug,PartialEq,Eq)]
+pub enum Event {
+    /// The kernel started the app at its entry point or delivered a callback.
+    /// `pc` is the entry point or the callback address passed to `subscribe`.
+    Callback { pc: usize, args: [usize; 4] },
+
+    /// The app's last system call returned `value`.
+    Return(isize),
+}
+
+/// What a simulated app does next.
+#[derive(Copy,Clone,Debug,PartialEq,Eq)]
+pub enum Syscall {
+    Yield,
+
+    /// Yield without waiting. `flag` is the address of the byte the kernel
+    /// sets to whether a callback was delivered.
+    YieldNoWait { flag: usize },
+    Subscribe {
+        driver: usize,
+        subscribe_num: usize,
+        callback: usize,
+        userdata: usize,
+    },
+    Command {
+        driver: usize,
+        command_num: usize,
+        data: usize,
+        arg2: usize,
+    },
+    Allow {
+        driver: usize,
+        allow_num: usize,
+        ptr: usize,
+        len: usize,
+    },
+    Memop { op: usize, arg: usize },
+    Exit,
+
+    /// Any system call number below 16, with the values of r0-r3.
+    Raw { svc: u8, args: [usize; 4] },
+
+    /// A CPU fault, such as an access the MPU does not allow.
+    Fault,
+}
+
+impl Syscall {
+    /// The system call number and r0-r3.
+    fn registers(&self) -> (u8, [usize; 4]) {
+        match *self {
+            Syscall::Yield => (syscall::YIELD, [0; 4]),
+            Syscall::YieldNoWait { flag } => (syscall::YIELD_NO_WAIT, [flag, 0, 0, 0]),
+            Syscall::Subscribe { driver, subscribe_num, callback, userdata } => {
+                (syscall::SUBSCRIBE, [driver, subscribe_num, callback, userdata])
+            }
+            Syscall::Command { driver, command_num, data, arg2 } => {
+                (syscall::COMMAND, [driver, command_num, data, arg2])
+            }
+            Syscall::Allow { driver, allow_num, ptr, len } => {
+                (syscall::ALLOW, [driver, allow_num, ptr, len])
+            }
+            Syscall::Memop { op, arg } => (syscall::MEMOP, [op, arg, 0, 0]),
+            Syscall::Exit => (syscall::EXIT, [0; 4]),
+            Syscall::Raw { svc, args } => (svc, args),
+            Syscall::Fault => (0, [0; 4]),
+        }
+    }
+}
+
+/// App logic, run one system call at a time.
+pub trait App {
+    /// Runs the app until its next system call, given what happened since it
+    /// last ran.
+    fn step(&mut self, event: Event) -> Syscall;
+}
+
+/// Closures make apps that keep their state in what they capture.
+impl<F: FnMut(Event) -> Syscall> App for F {
+    fn step(&mut self, event: Event) -> Syscall {
+        self(event)
+    }
+}
+
+thread_local!(static APPS: RefCell<Vec<(usize, Box<App>)>> = RefCell::new(Vec::new()));
+
+/// Runs `app` in the process whose memory starts at `mem_start`, replacing
+/// any app run there before. The app keeps its state if the process restarts.
+pub fn register(mem_start: *const u8, app: Box<App>) {
+    APPS.with(|apps| {
+        let mut apps = apps.borrow_mut();
+        apps.retain(|entry| entry.0 != mem_start as usize);
+        apps.push((mem_start as usize, app));
+    });
+}
+
+/// Returns an app image with the given memory requirements. Its text is a
+/// single word holding the entry point, and it has no data or BSS. Its
+/// permission list is empty, so the app may not use any driver.
+pub fn image(min_stack_len: usize,
+             min_app_heap_len: usize,
+             min_kernel_heap_len: usize)
+             -> &'static [u8] {
+    image_with_permissions(min_stack_len, min_app_heap_len, min_kernel_heap_len, &[])
+}
+
+/// Like `image`, but the app may only use the drivers in `permissions`, each
+/// given with a mask of the commands it may send.
+pub fn image_with_permissions(min_stack_len: usize,
+                              min_app_heap_len: usize,
+                              min_kernel_heap_len: usize,
+                              permissions: &[(usize, u32)])
+                              -> &'static [u8] {
+    let text_len = 4;
+    let permissions_size = permissions.len() * 8;
+    let total_size = (loader::HEADER_LEN + permissions_size + text_len).next_power_of_two();
+    let mut words = vec![total_size,
+                         0, // rel_data_size
+                         0, // entry_loc
+                         text_len, // init_data_loc
+                         0, // init_data_size
+                         0, // got_start_offset
+                         0, // got_end_offset
+                         0, // bss_start_offset
+                         0, // bss_end_offset
+                         min_stack_len,
+                         min_app_heap_len,
+                         min_kernel_heap_len,
+                         permissions.len()];
+    for &(driver, commands) in permissions {
+        words.push(driver);
+        words.push(commands as usize);
+    }
+    let mut image = vec![0u8; total_size];
+    for (i, field) in words.iter().enumerate() {
+        for byte in 0..4 {
+            image[i * 4 + byte] = (*field >> (8 * byte)) as u8;
+        }
+    }
+    &leak(image)[..]
+}
+
+/// Loads `image` into a new process that runs `app`.
+pub unsafe fn create_process(app: Box<App>,
+                             image: &'static [u8],
+                             fault_response: FaultResponse,
+                             callback_depth: usize)
+                             -> Result<Process<'static>, LoadError> {
+    let pool = &mut leak(vec![0u8; MEMORY_POOL_LEN])[..];
+    let (process, _) = try!(Process::create(image.as_ptr(),
+                                            image.len(),
+                                            pool,
+                                            fault_response,
+                                            callback_depth));
+    register(process.mem_start(), app);
+    Ok(process)
+}
+
+/// Runs the app registered for the process whose memory starts at
+/// `mem_base`, given the exception frame at `user_stack`, and returns the
+/// process's new stack pointer.
+///
+/// Returning from an exception pops the frame and the next SVC pushes one at
+/// the same place, so the frame is rewritten where it is.
+#[no_mangle]
+pub unsafe extern "C" fn switch_to_user(user_stack: *const u8, mem_base: *const u8) -> *mut u8 {
+    let frame = user_stack as *mut usize;
+    let pc = ptr::read(frame.offset(6));
+    let svc_table = SVC_INSTRUCTIONS.as_ptr() as usize;
+    let svc_table_end = svc_table + NUM_SVCS * 2;
+    let event = if pc > svc_table && pc <= svc_table_end {
+        Event::Return(ptr::read(frame) as isize)
+    } else {
+        Event::Callback {
+            pc: pc & !1,
+            args: [ptr::read(frame),
+                   ptr::read(frame.offset(1)),
+                   ptr::read(frame.offset(2)),
+                   ptr::read(frame.offset(3))],
+        }
+    };
+
+    let syscall = APPS.with(|apps| {
+        let mut apps = apps.borrow_mut();
+        match apps.iter_mut().find(|entry| entry.0 == mem_base as usize) {
+            Some(entry) => entry.1.step(event),
+            None => panic!("No app registered for the process at {:p}", mem_base),
+        }
+    });
+
+    match syscall {
+        Syscall::Fault => process::APP_FAULT = 1,
+        _ => {
+            let (svc, args) = syscall.registers();
+            for (i, arg) in args.iter().enumerate() {
+                ptr::write(frame.offset(i as isize), *arg);
+            }
+            // The stacked pc is the instruction after the `svc`.
+            let svc_instruction = &SVC_INSTRUCTIONS[svc as usize] as *const u16;
+            ptr::write(frame.offset(6), svc_instruction.offset(1) as usize);
+            process::SYSCALL_FIRED = 1;
+        }
+    }
+    user_stack as *mut u8
+}
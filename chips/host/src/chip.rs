@@ -0,0 +1,104 @@
+use alarm::Alarm;
+use gpio::Pin;
+use i2c::I2C;
+use kernel::{self, AppId, Platform, Scheduler};
+use kernel::process;
+use leak;
+use mpu::Mpu;
+use spi::Spi;
+use systick::SysTick;
+use uart::Uart;
+
+/// Number of simulated GPIO pins.
+pub const NUM_PINS: usize = 8;
+
+/// The simulated chip. Peripherals raise interrupts by having work pending,
+/// which `service_pending_interrupts` hands to their clients.
+pub struct Host {
+    pub uart: &'static Uart,
+    pub alarm: &'static Alarm,
+    pub pins: &'static [Pin],
+    pub i2c: &'static I2C,
+    pub spi: &'static Spi,
+    mpu: Mpu,
+    systick: SysTick,
+}
+
+impl Host {
+    pub fn new() -> Host {
+        let pins: Vec<Pin> = (0..NUM_PINS).map(|_| Pin::new()).collect();
+        Host {
+            uart: leak(Uart::new()),
+            alarm: leak(Alarm::new()),
+            pins: &leak(pins)[..],
+            i2c: leak(I2C::new()),
+            spi: leak(Spi::new()),
+            mpu: Mpu::new(),
+            systick: SysTick::new(),
+        }
+    }
+}
+
+impl kernel::Chip for Host {
+    type MPU = Mpu;
+    type SysTick = SysTick;
+
+    fn service_pending_interrupts(&mut self) {
+        self.uart.handle_interrupt();
+        self.alarm.handle_interrupt();
+        for pin in self.pins.iter() {
+            pin.handle_interrupt();
+        }
+        self.i2c.handle_interrupt();
+        self.spi.handle_interrupt();
+    }
+
+    fn has_pending_interrupts(&self) -> bool {
+        self.uart.is_pending() || self.alarm.is_pending() ||
+        self.pins.iter().any(|pin| pin.is_pending()) || self.i2c.is_pending() ||
+        self.spi.is_pending()
+    }
+
+    fn mpu(&self) -> &Mpu {
+        &self.mpu
+    }
+
+    fn systick(&self) -> &SysTick {
+        &self.systick
+    }
+}
+
+/// Makes `processes` the kernel's process table.
+pub unsafe fn set_processes(processes: Vec<Option<process::Process<'static>>>) {
+    process::PROCS = &mut leak(processes)[..];
+}
+
+/// Runs the kernel's main loop until it would sleep: no interrupts are
+/// pending and no process is ready. Stops after `max_rounds` passes around
+/// the loop, for apps that never go idle. Returns the number of passes made.
+pub unsafe fn run<P: Platform, S: Scheduler>(platform: &mut P,
+                                             chip: &mut Host,
+                                             scheduler: &mut S,
+                                             max_rounds: usize)
+                                             -> usize {
+    use kernel::Chip;
+
+    for round in 0..max_rounds {
+        chip.service_pending_interrupts();
+        match scheduler.next(&*process::PROCS) {
+            Some(i) => {
+                let timeslice = scheduler.timeslice();
+                process::PROCS[i].as_mut().map(|process| {
+                    let appid = AppId::new(i, process.generation());
+                    kernel::sched::do_process(platform, chip, process, appid, timeslice);
+                });
+            }
+            None => {
+                if !chip.has_pending_interrupts() {
+                    return round;
+                }
+            }
+        }
+    }
+    max_rounds
+}
@@ -0,0 +1,151 @@
+//! Simulated GPIO pins. Tests drive input pins with `drive` and read output
+//! pins with `level`.
+
+use CallLog;
+use kernel::hil::gpio::{self, InputMode, InterruptMode, PinCtl};
+use std::cell::Cell;
+
+#[derive(Copy,Clone,Debug,PartialEq,Eq)]
+pub enum PinCall {
+    MakeOutput,
+    MakeInput,
+    Disable,
+    Set,
+    Clear,
+    Toggle,
+    Read,
+    EnableInterrupt(usize, InterruptMode),
+    DisableInterrupt,
+    SetInputMode(InputMode),
+}
+
+pub struct Pin {
+    client: Cell<Option<&'static gpio::Client>>,
+    output: Cell<bool>,
+    level: Cell<bool>,
+
+    /// The identifier passed to `enable_interrupt`, and whether to interrupt
+    /// on rising and on falling edges.
+    interrupt: Cell<Option<(usize, bool, bool)>>,
+    pending: Cell<bool>,
+    log: CallLog<PinCall>,
+}
+
+impl Pin {
+    pub fn new() -> Pin {
+        Pin {
+            client: Cell::new(None),
+            output: Cell::new(false),
+            level: Cell::new(false),
+            interrupt: Cell::new(None),
+            pending: Cell::new(false),
+            log: CallLog::new(),
+        }
+    }
+
+    pub fn set_client(&self, client: &'static gpio::Client) {
+        self.client.set(Some(client));
+    }
+
+    pub fn calls(&self) -> Vec<PinCall> {
+        self.log.calls()
+    }
+
+    pub fn take_calls(&self) -> Vec<PinCall> {
+        self.log.take()
+    }
+
+    pub fn is_output(&self) -> bool {
+        self.output.get()
+    }
+
+    pub fn level(&self) -> bool {
+        self.level.get()
+    }
+
+    /// Drives the pin to `level` from outside, interrupting if the edge
+    /// matches the one the pin's interrupt is enabled for.
+    pub fn drive(&self, level: bool) {
+        let old = self.level.get();
+        self.level.set(level);
+        self.interrupt.get().map(|(_, rising, falling)| {
+            if (level && !old && rising) || (!level && old && falling) {
+                self.pending.set(true);
+            }
+        });
+    }
+
+    pub fn is_pending(&self) -> bool {
+        self.pending.get()
+    }
+
+    pub fn handle_interrupt(&self) {
+        if self.pending.get() {
+            self.pending.set(false);
+            self.interrupt.get().map(|(identifier, _, _)| {
+                self.client.get().map(|client| client.fired(identifier));
+            });
+        }
+    }
+}
+
+impl PinCtl for Pin {
+    fn set_input_mode(&self, mode: InputMode) {
+        self.log.record(PinCall::SetInputMode(mode));
+    }
+}
+
+impl gpio::Pin for Pin {
+    fn make_output(&self) {
+        self.log.record(PinCall::MakeOutput);
+        self.output.set(true);
+    }
+
+    fn make_input(&self) {
+        self.log.record(PinCall::MakeInput);
+        self.output.set(false);
+    }
+
+    fn disable(&self) {
+        self.log.record(PinCall::Disable);
+        self.output.set(false);
+        self.interrupt.set(None);
+        self.pending.set(false);
+    }
+
+    fn set(&self) {
+        self.log.record(PinCall::Set);
+        self.level.set(true);
+    }
+
+    fn clear(&self) {
+        self.log.record(PinCall::Clear);
+        self.level.set(false);
+    }
+
+    fn toggle(&self) {
+        self.log.record(PinCall::Toggle);
+        self.level.set(!self.level.get());
+    }
+
+    fn read(&self) -> bool {
+        self.log.record(PinCall::Read);
+        self.level.get()
+    }
+
+    fn enable_interrupt(&self, identifier: usize, mode: InterruptMode) {
+        self.log.record(PinCall::EnableInterrupt(identifier, mode));
+        let (rising, falling) = match mode {
+            InterruptMode::RisingEdge => (true, false),
+            InterruptMode::FallingEdge => (false, true),
+            InterruptMode::EitherEdge => (true, true),
+        };
+        self.interrupt.set(Some((identifier, rising, falling)));
+    }
+
+    fn disable_interrupt(&self) {
+        self.log.record(PinCall::DisableInterrupt);
+        self.interrupt.set(None);
+        self.pending.set(false);
+    }
+}
@@ -0,0 +1,146 @@
+//! Simulated I2C bus. Tests attach `Device`s at addresses; transfers to an
+//! address with no device are not acknowledged.
+
+use CallLog;
+use kernel::common::take_cell::TakeCell;
+use kernel::hil::i2c::{Error, I2CClient, I2CController};
+use std::cell::{Cell, RefCell};
+use std::cmp;
+
+/// A device on the simulated bus.
+pub trait Device {
+    /// Receives the bytes written to the device. Returns whether the device
+    /// acknowledged them.
+    fn write(&mut self, data: &[u8]) -> bool;
+
+    /// Fills `buffer` with bytes read from the device. Returns whether the
+    /// device acknowledged the read.
+    fn read(&mut self, buffer: &mut [u8]) -> bool;
+}
+
+#[derive(Clone,Debug,PartialEq,Eq)]
+pub enum I2CCall {
+    Enable,
+    Disable,
+    WriteRead { addr: u8, data: Vec<u8>, read_len: u8 },
+    Write { addr: u8, data: Vec<u8> },
+    Read { addr: u8, len: u8 },
+}
+
+pub struct I2C {
+    client: Cell<Option<&'static I2CClient>>,
+    devices: RefCell<Vec<(u8, Box<Device>)>>,
+    enabled: Cell<bool>,
+
+    /// The buffer of a finished transfer and its result, waiting to be
+    /// returned.
+    buffer: TakeCell<&'static mut [u8]>,
+    error: Cell<Error>,
+    log: CallLog<I2CCall>,
+}
+
+impl I2C {
+    pub fn new() -> I2C {
+        I2C {
+            client: Cell::new(None),
+            devices: RefCell::new(Vec::new()),
+            enabled: Cell::new(false),
+            buffer: TakeCell::empty(),
+            error: Cell::new(Error::CommandComplete),
+            log: CallLog::new(),
+        }
+    }
+
+    pub fn set_client(&self, client: &'static I2CClient) {
+        self.client.set(Some(client));
+    }
+
+    pub fn calls(&self) -> Vec<I2CCall> {
+        self.log.calls()
+    }
+
+    pub fn take_calls(&self) -> Vec<I2CCall> {
+        self.log.take()
+    }
+
+    /// Attaches `device` to the bus at `addr`.
+    pub fn add_device(&self, addr: u8, device: Box<Device>) {
+        self.devices.borrow_mut().push((addr, device));
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.get()
+    }
+
+    pub fn is_pending(&self) -> bool {
+        self.buffer.is_some()
+    }
+
+    pub fn handle_interrupt(&self) {
+        self.buffer.take().map(|buffer| {
+            let error = self.error.get();
+            self.client.get().map(move |client| client.command_complete(buffer, error));
+        });
+    }
+
+    /// Writes `write_len` bytes of `data` to the device at `addr`, then reads
+    /// `read_len` bytes back into `data`.
+    fn transfer(&self, addr: u8, data: &'static mut [u8], write_len: usize, read_len: usize) {
+        let error = {
+            let mut devices = self.devices.borrow_mut();
+            match devices.iter_mut().find(|device| device.0 == addr) {
+                None => Error::AddressNak,
+                Some(&mut (_, ref mut device)) => {
+                    let write_len = cmp::min(write_len, data.len());
+                    let read_len = cmp::min(read_len, data.len());
+                    if write_len > 0 && !device.write(&data[..write_len]) {
+                        Error::DataNak
+                    } else if read_len > 0 && !device.read(&mut data[..read_len]) {
+                        Error::DataNak
+                    } else {
+                        Error::CommandComplete
+                    }
+                }
+            }
+        };
+        self.error.set(error);
+        self.buffer.replace(data);
+    }
+}
+
+impl I2CController for I2C {
+    fn enable(&self) {
+        self.log.record(I2CCall::Enable);
+        self.enabled.set(true);
+    }
+
+    fn disable(&self) {
+        self.log.record(I2CCall::Disable);
+        self.enabled.set(false);
+    }
+
+    fn write_read(&self, addr: u8, data: &'static mut [u8], write_len: u8, read_len: u8) {
+        self.log.record(I2CCall::WriteRead {
+            addr: addr,
+            data: data[..cmp::min(write_len as usize, data.len())].to_vec(),
+            read_len: read_len,
+        });
+        self.transfer(addr, data, write_len as usize, read_len as usize);
+    }
+
+    fn write(&self, addr: u8, data: &'static mut [u8], len: u8) {
+        self.log.record(I2CCall::Write {
+            addr: addr,
+            data: data[..cmp::min(len as usize, data.len())].to_vec(),
+        });
+        self.transfer(addr, data, len as usize, 0);
+    }
+
+    fn read(&self, addr: u8, buffer: &'static mut [u8], len: u8) {
+        self.log.record(I2CCall::Read {
+            addr: addr,
+            len: len,
+        });
+        self.transfer(addr, buffer, 0, len as usize);
+    }
+}
@@ -0,0 +1,31 @@
+//! A simulated chip for running the kernel, capsules and apps on a Linux host.
+//!
+//! `Host` implements `kernel::Chip`, along with a recording `MPU` and a
+//! `SysTick` that counts simulated time. Its UART, alarm, GPIO pins, I2C bus
+//! and SPI bus implement the kernel's HIL traits and are driven by test code
+//! instead of hardware. Each records the calls made to it, so capsules can
+//! also be tested against them directly, calling `handle_interrupt` to have
+//! a peripheral call its client. Apps are simulated by implementations of `app::App`,
+//! which the kernel switches to through this crate's `switch_to_user`, so
+//! `kernel::sched::do_process` handles their system calls exactly as it does
+//! on hardware.
+//!
+//! The kernel keeps its processes and containers in global state, so tests
+//! built on this crate must run one at a time (`--test-threads=1`). The
+//! scenarios under `tests/` each have a file of their own for this reason.
+
+extern crate kernel;
+extern crate test_support;
+
+pub mod alarm;
+pub mod app;
+pub mod chip;
+pub mod gpio;
+pub mod i2c;
+pub mod mpu;
+pub mod spi;
+pub mod systick;
+pub mod uart;
+
+pub use chip::Host;
+pub use test_support::{CallLog, leak};
@@ -0,0 +1,67 @@
+//! Simulated MPU. Records the regions the kernel programs so tests can check
+//! what a process was allowed to access.
+
+use kernel;
+use kernel::mpu::{Permissions, Region};
+use std::cell::Cell;
+
+/// Number of regions, as on the Cortex-M4.
+pub const NUM_REGIONS: usize = 8;
+
+pub struct Mpu {
+    enabled: Cell<bool>,
+    regions: [Cell<Option<Region>>; NUM_REGIONS],
+}
+
+impl Mpu {
+    pub fn new() -> Mpu {
+        Mpu {
+            enabled: Cell::new(false),
+            regions: [Cell::new(None),
+                      Cell::new(None),
+                      Cell::new(None),
+                      Cell::new(None),
+                      Cell::new(None),
+                      Cell::new(None),
+                      Cell::new(None),
+                      Cell::new(None)],
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.get()
+    }
+
+    /// The region programmed into `region_num`, if it is enabled.
+    pub fn region(&self, region_num: usize) -> Option<Region> {
+        self.regions.get(region_num).and_then(|region| region.get())
+    }
+
+    /// Whether the running process can access `addr`. As on the Cortex-M,
+    /// the highest-numbered region containing the address decides.
+    pub fn accessible(&self, addr: usize) -> bool {
+        for region in self.regions.iter().rev() {
+            match region.get() {
+                Some(region) if region.contains(addr) => {
+                    return region.permissions() != Permissions::NoAccess;
+                }
+                _ => {}
+            }
+        }
+        false
+    }
+}
+
+impl kernel::MPU for Mpu {
+    fn enable_mpu(&self) {
+        self.enabled.set(true);
+    }
+
+    fn set_region(&self, region_num: u32, region: Region) {
+        self.regions[region_num as usize].set(Some(region));
+    }
+
+    fn disable_region(&self, region_num: u32) {
+        self.regions[region_num as usize].set(None);
+    }
+}
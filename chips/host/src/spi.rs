@@ -0,0 +1,210 @@
+//! Simulated SPI bus. Transfers go to the attached `Device`, or are looped
+//! back if there is none.
+
+use CallLog;
+use kernel::common::take_cell::TakeCell;
+use kernel::hil::spi::{ClockPhase, ClockPolarity, SpiMaster, SpiMasterClient};
+use std::cell::{Cell, RefCell};
+use std::cmp;
+
+/// A device on the simulated bus.
+pub trait Device {
+    /// Receives the bytes written in a transfer and fills `read`, which is
+    /// the same length, with the bytes clocked back.
+    fn transfer(&mut self, chip_select: u8, write: &[u8], read: &mut [u8]);
+}
+
+#[derive(Clone,Debug,PartialEq)]
+pub enum SpiCall {
+    Init,
+    ReadWriteBytes { write: Vec<u8>, read: bool, len: usize },
+    WriteByte(u8),
+    ReadByte,
+    ReadWriteByte(u8),
+    SpecifyChipSelect(u8),
+    SetRate(u32),
+    SetClock(ClockPolarity),
+    SetPhase(ClockPhase),
+    HoldLow,
+    ReleaseLow,
+}
+
+pub struct Spi {
+    client: Cell<Option<&'static SpiMasterClient>>,
+    device: RefCell<Option<Box<Device>>>,
+    chip_select: Cell<u8>,
+    rate: Cell<u32>,
+    polarity: Cell<ClockPolarity>,
+    phase: Cell<ClockPhase>,
+    hold_low: Cell<bool>,
+
+    /// The buffers of a finished transfer and its length, waiting to be
+    /// returned.
+    write_buffer: TakeCell<&'static mut [u8]>,
+    read_buffer: TakeCell<&'static mut [u8]>,
+    len: Cell<usize>,
+    log: CallLog<SpiCall>,
+}
+
+impl Spi {
+    pub fn new() -> Spi {
+        Spi {
+            client: Cell::new(None),
+            device: RefCell::new(None),
+            chip_select: Cell::new(0),
+            rate: Cell::new(0),
+            polarity: Cell::new(ClockPolarity::IdleLow),
+            phase: Cell::new(ClockPhase::SampleLeading),
+            hold_low: Cell::new(false),
+            write_buffer: TakeCell::empty(),
+            read_buffer: TakeCell::empty(),
+            len: Cell::new(0),
+            log: CallLog::new(),
+        }
+    }
+
+    /// Attaches `device` to the bus, replacing any device attached before.
+    pub fn set_device(&self, device: Box<Device>) {
+        *self.device.borrow_mut() = Some(device);
+    }
+
+    pub fn calls(&self) -> Vec<SpiCall> {
+        self.log.calls()
+    }
+
+    pub fn take_calls(&self) -> Vec<SpiCall> {
+        self.log.take()
+    }
+
+    /// Whether chip select is held low between transfers.
+    pub fn is_held_low(&self) -> bool {
+        self.hold_low.get()
+    }
+
+    pub fn is_pending(&self) -> bool {
+        self.write_buffer.is_some()
+    }
+
+    pub fn handle_interrupt(&self) {
+        self.write_buffer.take().map(|write_buffer| {
+            let read_buffer = self.read_buffer.take();
+            let len = self.len.get();
+            self.client.get().map(move |client| {
+                client.read_write_done(write_buffer, read_buffer, len)
+            });
+        });
+    }
+
+    fn exchange(&self, write: &[u8], read: &mut [u8]) {
+        match *self.device.borrow_mut() {
+            Some(ref mut device) => device.transfer(self.chip_select.get(), write, read),
+            None => read.clone_from_slice(write),
+        }
+    }
+
+    fn exchange_byte(&self, val: u8) -> u8 {
+        let mut read = [0];
+        self.exchange(&[val], &mut read);
+        read[0]
+    }
+}
+
+impl SpiMaster for Spi {
+    type ChipSelect = u8;
+
+    fn set_client(&self, client: &'static SpiMasterClient) {
+        self.client.set(Some(client));
+    }
+
+    fn init(&self) {
+        self.log.record(SpiCall::Init);
+    }
+
+    fn is_busy(&self) -> bool {
+        self.write_buffer.is_some()
+    }
+
+    fn read_write_bytes(&self,
+                        write_buffer: &'static mut [u8],
+                        mut read_buffer: Option<&'static mut [u8]>,
+                        len: usize)
+                        -> bool {
+        let mut len = cmp::min(len, write_buffer.len());
+        read_buffer.as_ref().map(|read| len = cmp::min(len, read.len()));
+        self.log.record(SpiCall::ReadWriteBytes {
+            write: write_buffer[..len].to_vec(),
+            read: read_buffer.is_some(),
+            len: len,
+        });
+        if self.is_busy() {
+            return false;
+        }
+
+        let mut read = vec![0; len];
+        self.exchange(&write_buffer[..len], &mut read);
+        read_buffer.as_mut().map(|buffer| buffer[..len].clone_from_slice(&read));
+
+        self.write_buffer.replace(write_buffer);
+        read_buffer.map(|buffer| self.read_buffer.replace(buffer));
+        self.len.set(len);
+        true
+    }
+
+    fn write_byte(&self, val: u8) {
+        self.log.record(SpiCall::WriteByte(val));
+        self.exchange_byte(val);
+    }
+
+    fn read_byte(&self) -> u8 {
+        self.log.record(SpiCall::ReadByte);
+        self.exchange_byte(0)
+    }
+
+    fn read_write_byte(&self, val: u8) -> u8 {
+        self.log.record(SpiCall::ReadWriteByte(val));
+        self.exchange_byte(val)
+    }
+
+    fn specify_chip_select(&self, cs: u8) {
+        self.log.record(SpiCall::SpecifyChipSelect(cs));
+        self.chip_select.set(cs);
+    }
+
+    fn set_rate(&self, rate: u32) -> u32 {
+        self.log.record(SpiCall::SetRate(rate));
+        self.rate.set(rate);
+        rate
+    }
+
+    fn get_rate(&self) -> u32 {
+        self.rate.get()
+    }
+
+    fn set_clock(&self, polarity: ClockPolarity) {
+        self.log.record(SpiCall::SetClock(polarity));
+        self.polarity.set(polarity);
+    }
+
+    fn get_clock(&self) -> ClockPolarity {
+        self.polarity.get()
+    }
+
+    fn set_phase(&self, phase: ClockPhase) {
+        self.log.record(SpiCall::SetPhase(phase));
+        self.phase.set(phase);
+    }
+
+    fn get_phase(&self) -> ClockPhase {
+        self.phase.get()
+    }
+
+    fn hold_low(&self) {
+        self.log.record(SpiCall::HoldLow);
+        self.hold_low.set(true);
+    }
+
+    fn release_low(&self) {
+        self.log.record(SpiCall::ReleaseLow);
+        self.hold_low.set(false);
+    }
+}
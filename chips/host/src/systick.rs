@@ -0,0 +1,88 @@
+//! Simulated SysTick.
+//!
+//! Time only passes when something calls `elapse`, usually a simulated app
+//! using up its timeslice. The timer counts down while it is enabled, as the
+//! hardware does.
+
+use kernel;
+use std::cell::Cell;
+
+thread_local!(static NOW_US: Cell<u64> = Cell::new(0));
+
+/// Passes `us` microseconds of simulated time.
+pub fn elapse(us: u32) {
+    NOW_US.with(|now| now.set(now.get() + us as u64));
+}
+
+fn now() -> u64 {
+    NOW_US.with(|now| now.get())
+}
+
+pub struct SysTick {
+    /// Microseconds left when the timer was last set.
+    remaining: Cell<u32>,
+
+    /// When the timer was enabled, if it is counting.
+    enabled_at: Cell<Option<u64>>,
+
+    /// Whether the timer has been set since the last reset.
+    armed: Cell<bool>,
+
+    /// Whether the overflow has been read since the timer was set.
+    overflow_read: Cell<bool>,
+}
+
+impl SysTick {
+    pub fn new() -> SysTick {
+        SysTick {
+            remaining: Cell::new(0),
+            enabled_at: Cell::new(None),
+            armed: Cell::new(false),
+            overflow_read: Cell::new(false),
+        }
+    }
+}
+
+impl kernel::SysTick for SysTick {
+    fn set_timer(&self, us: u32) {
+        self.remaining.set(us);
+        self.enabled_at.set(self.enabled_at.get().map(|_| now()));
+        self.armed.set(true);
+        self.overflow_read.set(false);
+    }
+
+    fn value(&self) -> u32 {
+        let elapsed = self.enabled_at.get().map_or(0, |at| now() - at);
+        (self.remaining.get() as u64).saturating_sub(elapsed) as u32
+    }
+
+    /// Reading the overflow clears it, as on the hardware.
+    fn overflowed(&self) -> bool {
+        if self.armed.get() && self.value() == 0 && !self.overflow_read.get() {
+            self.overflow_read.set(true);
+            true
+        } else {
+            false
+        }
+    }
+
+    fn reset(&self) {
+        self.remaining.set(0);
+        self.enabled_at.set(None);
+        self.armed.set(false);
+        self.overflow_read.set(false);
+    }
+
+    /// The timer counts whether or not its interrupt is enabled. Interrupts
+    /// are not simulated: the kernel notices the timeslice ran out when the
+    /// app next makes a system call.
+    fn enable(&self, _with_interrupt: bool) {
+        if self.enabled_at.get().is_none() {
+            self.enabled_at.set(Some(now()));
+        }
+    }
+
+    fn overflow_fired() -> bool {
+        false
+    }
+}
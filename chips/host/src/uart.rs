@@ -0,0 +1,141 @@
+//! Simulated UART.
+//!
+//! Bytes sent are collected for the test to read with `take_output`. Bytes
+//! passed to `receive` are delivered to the client one per interrupt while
+//! reception is enabled.
+
+use CallLog;
+use kernel::common::take_cell::TakeCell;
+use kernel::hil::uart::{self, UART, UARTParams};
+use std::cell::{Cell, RefCell};
+use std::collections::VecDeque;
+use std::{cmp, mem};
+
+#[derive(Clone,Debug,PartialEq,Eq)]
+pub enum UartCall {
+    Init { baud_rate: u32 },
+    SendByte(u8),
+    SendBytes(Vec<u8>),
+    ReadByte,
+    EnableRx,
+    DisableRx,
+    EnableTx,
+    DisableTx,
+}
+
+pub struct Uart {
+    client: Cell<Option<&'static uart::Client>>,
+    params: Cell<Option<UARTParams>>,
+
+    /// The buffer of a finished transfer, waiting to be returned.
+    tx_buffer: TakeCell<&'static mut [u8]>,
+    output: RefCell<Vec<u8>>,
+    input: RefCell<VecDeque<u8>>,
+    rx_enabled: Cell<bool>,
+    log: CallLog<UartCall>,
+}
+
+impl Uart {
+    pub fn new() -> Uart {
+        Uart {
+            client: Cell::new(None),
+            params: Cell::new(None),
+            tx_buffer: TakeCell::empty(),
+            output: RefCell::new(Vec::new()),
+            input: RefCell::new(VecDeque::new()),
+            rx_enabled: Cell::new(false),
+            log: CallLog::new(),
+        }
+    }
+
+    pub fn set_client(&self, client: &'static uart::Client) {
+        self.client.set(Some(client));
+    }
+
+    pub fn calls(&self) -> Vec<UartCall> {
+        self.log.calls()
+    }
+
+    pub fn take_calls(&self) -> Vec<UartCall> {
+        self.log.take()
+    }
+
+    /// The parameters passed to `init`, if it has been called.
+    pub fn params(&self) -> Option<UARTParams> {
+        self.params.get()
+    }
+
+    /// Returns everything sent since the last call.
+    pub fn take_output(&self) -> Vec<u8> {
+        mem::replace(&mut *self.output.borrow_mut(), Vec::new())
+    }
+
+    /// Queues `bytes` to be received.
+    pub fn receive(&self, bytes: &[u8]) {
+        self.input.borrow_mut().extend(bytes.iter().cloned());
+    }
+
+    pub fn is_pending(&self) -> bool {
+        self.tx_buffer.is_some() || (self.rx_enabled.get() && !self.input.borrow().is_empty())
+    }
+
+    pub fn handle_interrupt(&self) {
+        self.tx_buffer.take().map(|buffer| {
+            self.client.get().map(move |client| client.write_done(buffer));
+        });
+        if self.rx_enabled.get() {
+            let byte = self.input.borrow_mut().pop_front();
+            byte.map(|byte| self.client.get().map(|client| client.read_done(byte)));
+        }
+    }
+}
+
+impl UART for Uart {
+    fn init(&mut self, params: UARTParams) {
+        self.log.record(UartCall::Init { baud_rate: params.baud_rate });
+        self.params.set(Some(params));
+    }
+
+    fn send_byte(&self, byte: u8) {
+        self.log.record(UartCall::SendByte(byte));
+        self.output.borrow_mut().push(byte);
+    }
+
+    fn send_bytes(&self, bytes: &'static mut [u8], len: usize) {
+        let len = cmp::min(len, bytes.len());
+        self.log.record(UartCall::SendBytes(bytes[..len].to_vec()));
+        self.output.borrow_mut().extend_from_slice(&bytes[..len]);
+        self.tx_buffer.replace(bytes);
+    }
+
+    fn read_byte(&self) -> u8 {
+        self.log.record(UartCall::ReadByte);
+        self.input.borrow_mut().pop_front().unwrap_or(0)
+    }
+
+    fn rx_ready(&self) -> bool {
+        !self.input.borrow().is_empty()
+    }
+
+    fn tx_ready(&self) -> bool {
+        self.tx_buffer.is_none()
+    }
+
+    fn enable_rx(&self) {
+        self.log.record(UartCall::EnableRx);
+        self.rx_enabled.set(true);
+    }
+
+    fn disable_rx(&mut self) {
+        self.log.record(UartCall::DisableRx);
+        self.rx_enabled.set(false);
+    }
+
+    fn enable_tx(&self) {
+        self.log.record(UartCall::EnableTx);
+    }
+
+    fn disable_tx(&mut self) {
+        self.log.record(UartCall::DisableTx);
+    }
+}
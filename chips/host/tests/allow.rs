@@ -0,0 +1,106 @@
+//! An app moving its break around buffers it has allowed to the console and
+//! taken back.
+
+extern crate capsules;
+extern crate host;
+extern crate kernel;
+
+use capsules::console::Console;
+use host::app::{self, Event, Syscall};
+use host::uart::Uart;
+use host::{Host, chip, leak};
+use kernel::{Cooperative, FaultResponse, ReturnCode};
+use kernel::loader::ALL_COMMANDS;
+use std::cell::RefCell;
+use std::rc::Rc;
+
+struct Platform {
+    console: &'static Console<'static, Uart>,
+}
+
+impl kernel::Platform for Platform {
+    fn with_driver<F, R>(&mut self, driver_num: usize, f: F) -> R
+        where F: FnOnce(Option<&kernel::Driver>) -> R
+    {
+        match driver_num {
+            0 => f(Some(self.console)),
+            _ => f(None),
+        }
+    }
+}
+
+fn allow(allow_num: usize, ptr: usize, len: usize) -> Syscall {
+    Syscall::Allow {
+        driver: 0,
+        allow_num: allow_num,
+        ptr: ptr,
+        len: len,
+    }
+}
+
+fn brk(addr: usize) -> Syscall {
+    Syscall::Memop { op: 0, arg: addr }
+}
+
+/// The system calls the app makes once it has 64 bytes of heap at `heap`,
+/// each with the value it should return.
+fn script(heap: usize) -> Vec<(Syscall, ReturnCode)> {
+    let invalid = ReturnCode::EINVAL;
+    let success = ReturnCode::SUCCESS;
+    vec![(allow(0, heap + 32, 16), success),
+         (allow(1, heap, 16), success),
+         // The read buffer ends at heap + 48.
+         (brk(heap + 40), invalid),
+         (allow(0, 0, 0), success),
+         // Only the write buffer, ending at heap + 16, is left.
+         (brk(heap + 40), success),
+         (brk(heap + 8), invalid),
+         // Allowing a smaller write buffer replaces the old one.
+         (allow(1, heap, 4), success),
+         (brk(heap + 8), success),
+         (brk(heap + 2), invalid),
+         (allow(1, heap, 0), success),
+         (brk(heap), success)]
+}
+
+#[test]
+fn revoking_a_buffer_lets_the_break_move_below_it() {
+    let mut chip = Host::new();
+    let console: &'static Console<'static, Uart> = unsafe {
+        leak(Console::new(chip.uart,
+                          &mut leak([0u8; 64])[..],
+                          kernel::Container::create()))
+    };
+    chip.uart.set_client(console);
+    let mut platform = Platform { console: console };
+
+    let results = Rc::new(RefCell::new(Vec::new()));
+    let app_results = results.clone();
+    let mut script_calls = Vec::new();
+    let mut step = 0;
+    let app = move |event: Event| {
+        step += 1;
+        match (step, event) {
+            (1, _) => Syscall::Memop { op: 1 /* sbrk */, arg: 64 },
+            (2, Event::Return(heap)) => {
+                script_calls = script(heap as usize).into_iter().map(|(call, _)| call).collect();
+                script_calls.reverse();
+                script_calls.pop().unwrap()
+            }
+            (_, Event::Return(value)) => {
+                app_results.borrow_mut().push(value);
+                script_calls.pop().unwrap_or(Syscall::Yield)
+            }
+            (_, event) => panic!("Unexpected {:?}", event),
+        }
+    };
+
+    let expected: Vec<isize> = script(0).into_iter().map(|(_, res)| res.into()).collect();
+    unsafe {
+        let image = app::image_with_permissions(512, 64, 1024, &[(0, ALL_COMMANDS)]);
+        let process = app::create_process(Box::new(app), image, FaultResponse::Panic, 4).unwrap();
+        chip::set_processes(vec![Some(process)]);
+        chip::run(&mut platform, &mut chip, &mut Cooperative::new(), 100);
+    }
+    assert_eq!(*results.borrow(), expected);
+}
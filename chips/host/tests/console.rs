@@ -0,0 +1,118 @@
+//! An app writing a line to the console and reading one back.
+
+extern crate capsules;
+extern crate host;
+extern crate kernel;
+
+use capsules::console::Console;
+use host::app::{self, Event, Syscall};
+use host::uart::Uart;
+use host::{Host, chip, leak};
+use kernel::{Cooperative, FaultResponse};
+use kernel::loader::ALL_COMMANDS;
+use std::cell::RefCell;
+use std::ptr;
+use std::rc::Rc;
+
+const WRITE_DONE: usize = 0x1000;
+const READ_LINE: usize = 0x2000;
+
+struct Platform {
+    console: &'static Console<'static, Uart>,
+}
+
+impl kernel::Platform for Platform {
+    fn with_driver<F, R>(&mut self, driver_num: usize, f: F) -> R
+        where F: FnOnce(Option<&kernel::Driver>) -> R
+    {
+        match driver_num {
+            0 => f(Some(self.console)),
+            _ => f(None),
+        }
+    }
+}
+
+#[test]
+fn write_and_read_line() {
+    let mut chip = Host::new();
+    let console: &'static Console<'static, Uart> = unsafe {
+        leak(Console::new(chip.uart,
+                          &mut leak([0u8; 64])[..],
+                          kernel::Container::create()))
+    };
+    chip.uart.set_client(console);
+    console.initialize();
+    let mut platform = Platform { console: console };
+
+    let callbacks = Rc::new(RefCell::new(Vec::new()));
+    let app_callbacks = callbacks.clone();
+    let mut buffer = 0;
+    let mut step = 0;
+    let app = move |event: Event| {
+        step += 1;
+        match (step, event) {
+            (1, _) => Syscall::Memop { op: 1 /* sbrk */, arg: 16 },
+            (2, Event::Return(old_break)) => {
+                buffer = old_break as usize;
+                unsafe { ptr::copy_nonoverlapping(b"hello\n".as_ptr(), buffer as *mut u8, 6) };
+                Syscall::Allow {
+                    driver: 0,
+                    allow_num: 1,
+                    ptr: buffer,
+                    len: 6,
+                }
+            }
+            (3, Event::Return(0)) => {
+                Syscall::Subscribe {
+                    driver: 0,
+                    subscribe_num: 1,
+                    callback: WRITE_DONE,
+                    userdata: 0,
+                }
+            }
+            (4, Event::Return(0)) => {
+                Syscall::Allow {
+                    driver: 0,
+                    allow_num: 0,
+                    ptr: buffer + 8,
+                    len: 8,
+                }
+            }
+            (5, Event::Return(0)) => {
+                Syscall::Subscribe {
+                    driver: 0,
+                    subscribe_num: 0,
+                    callback: READ_LINE,
+                    userdata: 0,
+                }
+            }
+            (_, Event::Callback { pc, args }) => {
+                app_callbacks.borrow_mut().push((pc, args));
+                Syscall::Yield
+            }
+            (_, Event::Return(0)) => Syscall::Yield,
+            (_, event) => panic!("Unexpected {:?}", event),
+        }
+    };
+
+    unsafe {
+        let image = app::image_with_permissions(512, 64, 1024, &[(0, ALL_COMMANDS)]);
+        let process = app::create_process(Box::new(app), image, FaultResponse::Panic, 4).unwrap();
+        chip::set_processes(vec![Some(process)]);
+        chip::run(&mut platform, &mut chip, &mut Cooperative::new(), 100);
+    }
+    assert_eq!(chip.uart.take_output(), b"hello\n".to_vec());
+    assert_eq!(callbacks.borrow()[0].0, WRITE_DONE);
+    assert_eq!(callbacks.borrow()[0].1[0], 6);
+
+    chip.uart.receive(b"ok\n");
+    unsafe {
+        chip::run(&mut platform, &mut chip, &mut Cooperative::new(), 100);
+    }
+    assert_eq!(callbacks.borrow().len(), 2);
+    let (pc, args) = callbacks.borrow()[1];
+    assert_eq!(pc, READ_LINE);
+    assert_eq!(args[0], 2);
+    let line = unsafe { std::slice::from_raw_parts(args[1] as *const u8, 2) };
+    assert_eq!(line, b"ok");
+}
@@ -0,0 +1,51 @@
+//! CPU time accounted to an app under a scheduler without timeslices.
+
+extern crate host;
+extern crate kernel;
+
+use host::app::{self, Event, Syscall};
+use host::systick;
+use host::{Host, chip};
+use kernel::{Cooperative, FaultResponse};
+use kernel::process;
+
+struct Platform;
+
+impl kernel::Platform for Platform {
+    fn with_driver<F, R>(&mut self, _: usize, f: F) -> R
+        where F: FnOnce(Option<&kernel::Driver>) -> R
+    {
+        f(None)
+    }
+}
+
+#[test]
+fn cooperative_process_time_is_measured() {
+    let mut chip = Host::new();
+    let mut step = 0;
+    let app = move |_: Event| {
+        step += 1;
+        match step {
+            1 => {
+                systick::elapse(300);
+                Syscall::Memop { op: 2 /* memory start */, arg: 0 }
+            }
+            2 => {
+                systick::elapse(200);
+                Syscall::Yield
+            }
+            _ => panic!("Ran after yielding"),
+        }
+    };
+
+    unsafe {
+        let process =
+            app::create_process(Box::new(app), app::image(512, 0, 1024), FaultResponse::Panic, 4)
+                .unwrap();
+        chip::set_processes(vec![Some(process)]);
+        chip::run(&mut Platform, &mut chip, &mut Cooperative::new(), 10);
+    }
+    let statistics = process::statistics(0).unwrap();
+    assert_eq!(statistics.cpu_time_us, 500);
+    assert_eq!(statistics.timeslice_expirations, 0);
+}
@@ -0,0 +1,51 @@
+//! `debug!` output overflowing the debug buffer, and the count of dropped
+//! bytes written once the buffer drains.
+
+extern crate host;
+#[macro_use(debug)]
+extern crate kernel;
+
+use host::leak;
+use host::uart::Uart;
+use kernel::debug::{self, DebugWriter};
+
+#[test]
+fn dropped_output_is_counted_once_buffer_drains() {
+    let uart: &'static Uart = leak(Uart::new());
+    // The output buffer holds one byte less than its length.
+    let writer: &'static DebugWriter =
+        leak(DebugWriter::new(uart, &mut leak([0u8; 64])[..], &mut leak([0u8; 32])[..]));
+    uart.set_client(writer);
+    unsafe {
+        debug::set_debug_writer(writer);
+    }
+
+    // Nothing is being sent, so the first line goes out at once.
+    debug!("0123456789");
+    assert_eq!(uart.take_output(), b"0123456789\r\n".to_vec());
+
+    // While it is in flight, 31 bytes fit in the buffer and the other 14 are
+    // dropped.
+    debug!("{}", "abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMN");
+    debug!("x");
+    assert_eq!(uart.take_output(), Vec::new());
+
+    uart.handle_interrupt();
+    assert_eq!(uart.take_output(),
+               b"abcdefghijklmnopqrstuvwxyzABCDE".to_vec());
+    uart.handle_interrupt();
+    assert_eq!(uart.take_output(),
+               b"\r\n[14 bytes of debug output dropped]\r\n".to_vec());
+    uart.handle_interrupt();
+    assert_eq!(uart.take_output(), Vec::new());
+
+    // The count starts again from zero.
+    debug!("x");
+    assert_eq!(uart.take_output(), b"x\r\n".to_vec());
+    debug!("{}", "abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMN");
+    uart.handle_interrupt();
+    assert_eq!(uart.take_output(), b"abcdefghijklmnopqrstuvwxyzABCDE".to_vec());
+    uart.handle_interrupt();
+    assert_eq!(uart.take_output(),
+               b"\r\n[11 bytes of debug output dropped]\r\n".to_vec());
+}
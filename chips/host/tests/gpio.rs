@@ -0,0 +1,98 @@
+//! An app driving an output pin and waiting for an input pin to change.
+
+extern crate capsules;
+extern crate host;
+extern crate kernel;
+
+use capsules::gpio::GPIO;
+use host::app::{self, Event, Syscall};
+use host::gpio::Pin;
+use host::{Host, chip, leak};
+use kernel::{Cooperative, FaultResponse};
+use kernel::loader::ALL_COMMANDS;
+use std::cell::RefCell;
+use std::rc::Rc;
+
+const PIN_CHANGED: usize = 0x1000;
+const OUTPUT_PIN: usize = 2;
+const INPUT_PIN: usize = 5;
+
+struct Platform {
+    gpio: &'static GPIO<'static, Pin>,
+}
+
+impl kernel::Platform for Platform {
+    fn with_driver<F, R>(&mut self, driver_num: usize, f: F) -> R
+        where F: FnOnce(Option<&kernel::Driver>) -> R
+    {
+        match driver_num {
+            1 => f(Some(self.gpio)),
+            _ => f(None),
+        }
+    }
+}
+
+fn command(command_num: usize, data: usize, arg2: usize) -> Syscall {
+    Syscall::Command {
+        driver: 1,
+        command_num: command_num,
+        data: data,
+        arg2: arg2,
+    }
+}
+
+#[test]
+fn set_output_and_interrupt_on_input() {
+    let mut chip = Host::new();
+    let pins: Vec<&'static Pin> = chip.pins.iter().collect();
+    let gpio: &'static GPIO<'static, Pin> = leak(GPIO::new(&leak(pins)[..]));
+    for pin in chip.pins.iter() {
+        pin.set_client(gpio);
+    }
+    let mut platform = Platform { gpio: gpio };
+
+    let callbacks = Rc::new(RefCell::new(Vec::new()));
+    let app_callbacks = callbacks.clone();
+    let mut step = 0;
+    let app = move |event: Event| {
+        step += 1;
+        match (step, event) {
+            (1, _) => {
+                Syscall::Subscribe {
+                    driver: 1,
+                    subscribe_num: 0,
+                    callback: PIN_CHANGED,
+                    userdata: 0,
+                }
+            }
+            (2, Event::Return(0)) => command(0 /* enable output */, OUTPUT_PIN, 0),
+            (3, Event::Return(0)) => command(1 /* set */, OUTPUT_PIN, 0),
+            (4, Event::Return(0)) => command(4 /* enable input */, INPUT_PIN, 2 /* no pull */),
+            (5, Event::Return(0)) => command(6 /* enable interrupt */, INPUT_PIN, 1 /* rising */),
+            (_, Event::Callback { pc, args }) => {
+                app_callbacks.borrow_mut().push((pc, args));
+                Syscall::Yield
+            }
+            (_, Event::Return(0)) => Syscall::Yield,
+            (_, event) => panic!("Unexpected {:?}", event),
+        }
+    };
+
+    unsafe {
+        let image = app::image_with_permissions(512, 0, 1024, &[(1, ALL_COMMANDS)]);
+        let process = app::create_process(Box::new(app), image, FaultResponse::Panic, 4).unwrap();
+        chip::set_processes(vec![Some(process)]);
+        chip::run(&mut platform, &mut chip, &mut Cooperative::new(), 100);
+    }
+    assert!(chip.pins[OUTPUT_PIN].is_output());
+    assert!(chip.pins[OUTPUT_PIN].level());
+    assert!(!chip.pins[INPUT_PIN].is_output());
+
+    // Only rising edges interrupt.
+    chip.pins[INPUT_PIN].drive(true);
+    chip.pins[INPUT_PIN].drive(false);
+    unsafe {
+        chip::run(&mut platform, &mut chip, &mut Cooperative::new(), 100);
+    }
+    assert_eq!(*callbacks.borrow(), vec![(PIN_CHANGED, [INPUT_PIN, 0, 0, 0])]);
+}
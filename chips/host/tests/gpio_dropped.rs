@@ -0,0 +1,56 @@
+//! Counting the pin interrupts an app's callback queue had no room for.
+
+extern crate capsules;
+extern crate host;
+extern crate kernel;
+
+use capsules::gpio::GPIO;
+use host::app::{self, Event, Syscall};
+use host::gpio::Pin;
+use host::{Host, chip, leak};
+use kernel::{AppId, Callback, Driver, FaultResponse, State};
+use kernel::common::Queue;
+use kernel::hil::gpio::Client;
+use kernel::process;
+
+const PIN_CHANGED: usize = 0x1000;
+const INPUT_PIN: usize = 5;
+
+#[test]
+fn only_interrupts_for_a_live_app_count_as_dropped() {
+    let chip = Host::new();
+    let pins: Vec<&'static Pin> = chip.pins.iter().collect();
+    let gpio: &'static GPIO<'static, Pin> = leak(GPIO::new(&leak(pins)[..]));
+
+    let app = |_: Event| Syscall::Yield;
+    unsafe {
+        let image = app::image(512, 0, 1024);
+        let created = app::create_process(Box::new(app), image, FaultResponse::Panic, 4).unwrap();
+        chip::set_processes(vec![Some(created)]);
+    }
+    let p = unsafe { process::process_at(0).unwrap() };
+    let callback = unsafe {
+        Callback::new(AppId::new(0, p.generation()), 0, PIN_CHANGED as *mut ())
+    };
+    gpio.subscribe(0, Some(callback), callback.app_id());
+
+    // Two interrupts find the queue full.
+    while !p.callbacks.is_full() {
+        gpio.fired(INPUT_PIN);
+    }
+    gpio.fired(INPUT_PIN);
+    gpio.fired(INPUT_PIN);
+
+    // None of these are counted, since the app is not running.
+    p.state = State::Terminated;
+    for _ in 0..3 {
+        gpio.fired(INPUT_PIN);
+    }
+    p.state = State::Yielded;
+
+    while p.callbacks.dequeue().is_some() {}
+    gpio.fired(INPUT_PIN);
+    let delivered = p.callbacks.dequeue().unwrap();
+    assert_eq!((delivered.pc, delivered.r0, delivered.r2), (PIN_CHANGED, INPUT_PIN, 2));
+    assert_eq!(process::statistics(0).unwrap().callbacks_dropped, 2);
+}
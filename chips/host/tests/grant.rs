@@ -0,0 +1,150 @@
+//! Container memory allocated from, and given back to, a process's grant
+//! region.
+
+extern crate capsules;
+extern crate host;
+extern crate kernel;
+
+use capsules::timer::TimerDriver;
+use host::alarm::Alarm;
+use host::app::{self, Event, Syscall};
+use host::{Host, chip, leak};
+use kernel::{Cooperative, FaultResponse};
+use kernel::loader::ALL_COMMANDS;
+use kernel::process::{self, Process};
+
+const TIMER_FIRED: usize = 0x1000;
+
+fn process() -> Process<'static> {
+    let app = |_: Event| Syscall::Yield;
+    unsafe {
+        app::create_process(Box::new(app), app::image(512, 0, 1024), FaultResponse::Panic, 4)
+            .unwrap()
+    }
+}
+
+fn alloc(process: &mut Process, size: usize) -> *mut u8 {
+    unsafe { process.alloc(size).unwrap().as_mut_ptr() }
+}
+
+#[test]
+fn freeing_every_block_gives_the_memory_back() {
+    let mut process = process();
+    let initial_break = process.kernel_memory_break();
+
+    let first = alloc(&mut process, 16);
+    let second = alloc(&mut process, 16);
+    assert!(second < first);
+    assert!(process.kernel_memory_break() < initial_break);
+
+    unsafe {
+        process.free(first);
+        process.free(second);
+    }
+    assert_eq!(process.kernel_memory_break(), initial_break);
+}
+
+#[test]
+fn free_block_is_split_for_a_smaller_allocation() {
+    let mut process = process();
+
+    let large = alloc(&mut process, 256);
+    let _below = alloc(&mut process, 16);
+    let grant_break = process.kernel_memory_break();
+    unsafe {
+        process.free(large);
+    }
+    assert_eq!(process.kernel_memory_break(), grant_break);
+
+    // Both small blocks fit in the freed one, without lowering the break.
+    let first = alloc(&mut process, 16);
+    let second = alloc(&mut process, 16);
+    assert_eq!(first, large);
+    assert!(second > first && (second as usize) < large as usize + 256);
+    assert_eq!(process.kernel_memory_break(), grant_break);
+}
+
+#[test]
+fn adjacent_free_blocks_are_merged() {
+    let mut process = process();
+
+    let top = alloc(&mut process, 64);
+    let middle = alloc(&mut process, 64);
+    let _bottom = alloc(&mut process, 16);
+    let grant_break = process.kernel_memory_break();
+    unsafe {
+        process.free(top);
+        process.free(middle);
+    }
+
+    // Neither block alone holds 100 bytes, but the two together do.
+    let merged = alloc(&mut process, 100);
+    assert_eq!(merged, middle);
+    assert_eq!(process.kernel_memory_break(), grant_break);
+}
+
+#[test]
+fn allocation_fails_when_grant_region_would_reach_the_app() {
+    let mut process = process();
+    let size = process.kernel_memory_break() as usize - process.app_memory_break() as usize;
+    unsafe {
+        assert!(process.alloc(size).is_none());
+    }
+}
+
+struct Platform {
+    timer: &'static TimerDriver<'static, Alarm>,
+}
+
+impl kernel::Platform for Platform {
+    fn with_driver<F, R>(&mut self, driver_num: usize, f: F) -> R
+        where F: FnOnce(Option<&kernel::Driver>) -> R
+    {
+        match driver_num {
+            3 => f(Some(self.timer)),
+            _ => f(None),
+        }
+    }
+}
+
+#[test]
+fn unsubscribing_releases_the_container() {
+    const ROUNDS: usize = 200;
+
+    let mut chip = Host::new();
+    let timer: &'static TimerDriver<'static, Alarm> =
+        unsafe { leak(TimerDriver::new(chip.alarm, kernel::Container::create())) };
+    chip.alarm.set_client(timer);
+    let mut platform = Platform { timer: timer };
+
+    // Subscribes and unsubscribes over and over. Each unsubscribe should
+    // give the timer's state back, and the next subscribe allocate it again.
+    let mut step = 0;
+    let app = move |event: Event| {
+        step += 1;
+        match event {
+            Event::Return(0) | Event::Callback { .. } if step <= 2 * ROUNDS => {
+                Syscall::Subscribe {
+                    driver: 3,
+                    subscribe_num: 0,
+                    callback: if step % 2 == 1 { TIMER_FIRED } else { 0 },
+                    userdata: 0,
+                }
+            }
+            Event::Return(0) => Syscall::Yield,
+            event => panic!("Unexpected {:?} at step {}", event, step),
+        }
+    };
+
+    unsafe {
+        let image = app::image_with_permissions(512, 0, 1024, &[(3, ALL_COMMANDS)]);
+        let created = app::create_process(Box::new(app), image, FaultResponse::Panic, 4).unwrap();
+        let initial_break = created.kernel_memory_break();
+        chip::set_processes(vec![Some(created)]);
+        chip::run(&mut platform, &mut chip, &mut Cooperative::new(), 4 * ROUNDS);
+
+        let running = process::process_at(0).unwrap();
+        assert!(!running.ready());
+        assert_eq!(running.kernel_memory_break(), initial_break);
+    }
+}
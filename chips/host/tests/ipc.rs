@@ -0,0 +1,174 @@
+//! A client finding a service over IPC, sharing a buffer with it and taking
+//! the buffer back.
+
+extern crate host;
+extern crate kernel;
+
+use host::app::{self, Event, Syscall};
+use host::{Host, chip, leak};
+use kernel::{AppId, Callback, Cooperative, FaultResponse, ReturnCode};
+use kernel::ipc::IPC;
+use kernel::loader::ALL_COMMANDS;
+use kernel::process;
+use std::cell::RefCell;
+use std::ptr;
+use std::rc::Rc;
+
+const IPC_DRIVER: usize = 0xff;
+const SERVICE_NOTIFIED: usize = 0x1000;
+const CLIENT_WOKEN: usize = 0x2000;
+
+struct Platform {
+    ipc: &'static IPC,
+}
+
+impl kernel::Platform for Platform {
+    fn with_driver<F, R>(&mut self, driver_num: usize, f: F) -> R
+        where F: FnOnce(Option<&kernel::Driver>) -> R
+    {
+        match driver_num {
+            IPC_DRIVER => f(Some(self.ipc)),
+            _ => f(None),
+        }
+    }
+}
+
+fn allow(allow_num: usize, ptr: usize, len: usize) -> Syscall {
+    Syscall::Allow {
+        driver: IPC_DRIVER,
+        allow_num: allow_num,
+        ptr: ptr,
+        len: len,
+    }
+}
+
+/// The system calls the client makes once it has 256 bytes of heap at
+/// `heap`, holding the name "svc" at `heap` and "nope" at `heap + 8`, each
+/// with the value it should return.
+fn script(heap: usize) -> Vec<(Syscall, ReturnCode)> {
+    let shared = (heap + 16 + 63) & !63;
+    vec![(allow(1, heap, 3), ReturnCode::SuccessWithValue { value: 0 }),
+         (allow(1, heap + 8, 4), ReturnCode::EINVAL),
+         // Another process already offers "svc".
+         (allow(0, heap, 3), ReturnCode::EALREADY),
+         // Shared buffers must be a power of two of at least 32 bytes,
+         // aligned to their length.
+         (allow(2, shared, 16), ReturnCode::EINVAL),
+         (allow(2, shared, 48), ReturnCode::EINVAL),
+         (allow(2, shared + 32, 64), ReturnCode::EINVAL),
+         (allow(2 + 4, shared, 64), ReturnCode::EINVAL),
+         (allow(2, shared, 64), ReturnCode::SUCCESS),
+         (Syscall::Subscribe {
+              driver: IPC_DRIVER,
+              subscribe_num: 1,
+              callback: CLIENT_WOKEN,
+              userdata: 0,
+          },
+          ReturnCode::SUCCESS),
+         (Syscall::Command {
+              driver: IPC_DRIVER,
+              command_num: 0,
+              data: 0,
+              arg2: 0,
+          },
+          ReturnCode::SUCCESS)]
+}
+
+#[test]
+fn client_shares_buffer_with_service() {
+    let mut chip = Host::new();
+    let ipc: &'static IPC = unsafe { leak(IPC::new()) };
+    let mut platform = Platform { ipc: ipc };
+
+    let service_callbacks = Rc::new(RefCell::new(Vec::new()));
+    let app_service_callbacks = service_callbacks.clone();
+    let mut service_step = 0;
+    let service = move |event: Event| {
+        service_step += 1;
+        match (service_step, event) {
+            (1, _) => Syscall::Memop { op: 1 /* sbrk */, arg: 16 },
+            (2, Event::Return(name)) => {
+                unsafe { ptr::copy_nonoverlapping(b"svc".as_ptr(), name as *mut u8, 3) };
+                allow(0, name as usize, 3)
+            }
+            (3, Event::Return(0)) => {
+                Syscall::Subscribe {
+                    driver: IPC_DRIVER,
+                    subscribe_num: 0,
+                    callback: SERVICE_NOTIFIED,
+                    userdata: 0,
+                }
+            }
+            (_, Event::Callback { pc, args }) => {
+                app_service_callbacks.borrow_mut().push((pc, args));
+                Syscall::Yield
+            }
+            (_, Event::Return(0)) => Syscall::Yield,
+            (_, event) => panic!("Service got unexpected {:?}", event),
+        }
+    };
+
+    let results = Rc::new(RefCell::new(Vec::new()));
+    let app_results = results.clone();
+    let shared = Rc::new(RefCell::new(0));
+    let app_shared = shared.clone();
+    let mut script_calls = Vec::new();
+    let mut client_step = 0;
+    let client = move |event: Event| {
+        client_step += 1;
+        match (client_step, event) {
+            (1, _) => Syscall::Memop { op: 1 /* sbrk */, arg: 256 },
+            (2, Event::Return(heap)) => {
+                let heap = heap as usize;
+                unsafe {
+                    ptr::copy_nonoverlapping(b"svc".as_ptr(), heap as *mut u8, 3);
+                    ptr::copy_nonoverlapping(b"nope".as_ptr(), (heap + 8) as *mut u8, 4);
+                }
+                *app_shared.borrow_mut() = (heap + 16 + 63) & !63;
+                script_calls = script(heap).into_iter().map(|(call, _)| call).collect();
+                script_calls.reverse();
+                script_calls.pop().unwrap()
+            }
+            (_, Event::Callback { pc: CLIENT_WOKEN, .. }) => {
+                // Stop sharing.
+                allow(2, 0, 0)
+            }
+            (_, Event::Return(value)) => {
+                app_results.borrow_mut().push(value);
+                script_calls.pop().unwrap_or(Syscall::Yield)
+            }
+            (_, event) => panic!("Client got unexpected {:?}", event),
+        }
+    };
+
+    let image = app::image_with_permissions(512, 256, 1024, &[(IPC_DRIVER, ALL_COMMANDS)]);
+    unsafe {
+        let service = app::create_process(Box::new(service), image, FaultResponse::Panic, 4)
+            .unwrap();
+        let client = app::create_process(Box::new(client), image, FaultResponse::Panic, 4)
+            .unwrap();
+        chip::set_processes(vec![Some(service), Some(client)]);
+        chip::run(&mut platform, &mut chip, &mut Cooperative::new(), 100);
+    }
+
+    let mut expected: Vec<isize> = script(0).into_iter().map(|(_, res)| res.into()).collect();
+    assert_eq!(*results.borrow(), expected);
+    let shared = *shared.borrow();
+    assert_eq!(*service_callbacks.borrow(),
+               vec![(SERVICE_NOTIFIED, [1, 64, shared, 0])]);
+    unsafe {
+        assert_eq!(process::process_at(0).unwrap().shared_region(0), Some((shared, 64)));
+    }
+
+    // Wake the client, which stops sharing. The service loses access.
+    unsafe {
+        let client_id = AppId::new(1, process::process_at(1).unwrap().generation());
+        assert!(Callback::new(client_id, 0, CLIENT_WOKEN as *mut ()).schedule(0, 0, 0));
+        chip::run(&mut platform, &mut chip, &mut Cooperative::new(), 100);
+    }
+    expected.push(ReturnCode::SUCCESS.into());
+    assert_eq!(*results.borrow(), expected);
+    unsafe {
+        assert_eq!(process::process_at(0).unwrap().shared_region(0), None);
+    }
+}
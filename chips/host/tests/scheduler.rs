@@ -0,0 +1,179 @@
+//! The order the schedulers pick processes in.
+
+extern crate host;
+extern crate kernel;
+
+use host::app::{self, Event, Syscall};
+use host::systick;
+use host::{Host, chip};
+use kernel::{Cooperative, FaultResponse, FixedPriority, Process, RoundRobin, Scheduler, State};
+use kernel::common::Queue;
+use kernel::process;
+use std::cell::RefCell;
+use std::rc::Rc;
+
+const TIMESLICE_US: u32 = 10000;
+
+/// A process with its entry point queued, so it is ready to run.
+fn ready() -> Option<Process<'static>> {
+    let app = |_: Event| Syscall::Yield;
+    unsafe {
+        Some(app::create_process(Box::new(app), app::image(512, 0, 1024), FaultResponse::Panic, 4)
+            .unwrap())
+    }
+}
+
+/// A process that has yielded with no callbacks waiting.
+fn idle() -> Option<Process<'static>> {
+    let mut process = ready();
+    process.as_mut().map(|p| p.callbacks.dequeue());
+    process
+}
+
+fn with_state(state: State) -> Option<Process<'static>> {
+    let mut process = ready();
+    process.as_mut().map(|p| p.state = state);
+    process
+}
+
+fn stopped() -> Option<Process<'static>> {
+    let mut process = ready();
+    process.as_mut().map(|p| p.stop());
+    process
+}
+
+fn picks<S: Scheduler>(scheduler: &mut S,
+                       processes: &[Option<Process<'static>>],
+                       count: usize)
+                       -> Vec<Option<usize>> {
+    (0..count).map(|_| scheduler.next(processes)).collect()
+}
+
+#[test]
+fn round_robin_takes_turns() {
+    let processes = vec![ready(), ready(), ready()];
+    let mut scheduler = RoundRobin::new(TIMESLICE_US);
+    assert_eq!(picks(&mut scheduler, &processes, 4),
+               vec![Some(0), Some(1), Some(2), Some(0)]);
+    assert_eq!(scheduler.timeslice(), Some(TIMESLICE_US));
+}
+
+#[test]
+fn round_robin_skips_processes_that_cannot_run() {
+    let mut processes = vec![None,
+                             idle(),
+                             stopped(),
+                             with_state(State::Faulted),
+                             ready(),
+                             with_state(State::Terminated)];
+    let mut scheduler = RoundRobin::new(TIMESLICE_US);
+    assert_eq!(picks(&mut scheduler, &processes, 2), vec![Some(4), Some(4)]);
+
+    processes[4].as_mut().map(|p| p.callbacks.dequeue());
+    assert_eq!(scheduler.next(&processes), None);
+}
+
+#[test]
+fn fixed_priority_runs_highest_priority_first() {
+    let priorities = [2, 0, 1];
+    let mut processes = vec![ready(), ready(), ready()];
+    let mut scheduler = FixedPriority::new(&priorities, TIMESLICE_US);
+    assert_eq!(picks(&mut scheduler, &processes, 2), vec![Some(1), Some(1)]);
+
+    processes[1].as_mut().map(|p| p.stop());
+    assert_eq!(scheduler.next(&processes), Some(2));
+    processes[2] = with_state(State::Faulted);
+    assert_eq!(scheduler.next(&processes), Some(0));
+}
+
+#[test]
+fn fixed_priority_alternates_equal_priorities() {
+    // The third process has no priority, so it comes last.
+    let priorities = [1, 1];
+    let processes = vec![ready(), ready(), ready()];
+    let mut scheduler = FixedPriority::new(&priorities, TIMESLICE_US);
+    assert_eq!(picks(&mut scheduler, &processes, 4),
+               vec![Some(1), Some(0), Some(1), Some(0)]);
+}
+
+#[test]
+fn fixed_priority_preempts_running_process() {
+    let priorities = [1, 0];
+    let mut processes = vec![with_state(State::Running), idle()];
+    let mut scheduler = FixedPriority::new(&priorities, TIMESLICE_US);
+    assert_eq!(scheduler.next(&processes), Some(0));
+
+    // A callback for the higher priority process makes it run next, even
+    // though the other has not yielded.
+    processes[1].as_mut().map(|p| {
+        p.callbacks.enqueue(process::Callback {
+            r0: 0,
+            r1: 0,
+            r2: 0,
+            r3: 0,
+            pc: 0x1000,
+        })
+    });
+    assert_eq!(scheduler.next(&processes), Some(1));
+}
+
+#[test]
+fn cooperative_runs_process_until_it_yields() {
+    let mut processes = vec![with_state(State::Running), ready()];
+    let mut scheduler = Cooperative::new();
+    assert_eq!(picks(&mut scheduler, &processes, 3), vec![Some(0), Some(0), Some(0)]);
+    assert_eq!(scheduler.timeslice(), None);
+
+    processes[0] = idle();
+    assert_eq!(scheduler.next(&processes), Some(1));
+}
+
+#[test]
+fn cooperative_moves_on_from_stopped_process() {
+    let mut processes = vec![with_state(State::Running), with_state(State::Running)];
+    let mut scheduler = Cooperative::new();
+    assert_eq!(scheduler.next(&processes), Some(0));
+
+    processes[0].as_mut().map(|p| p.stop());
+    assert_eq!(picks(&mut scheduler, &processes, 2), vec![Some(1), Some(1)]);
+}
+
+struct Platform;
+
+impl kernel::Platform for Platform {
+    fn with_driver<F, R>(&mut self, _: usize, f: F) -> R
+        where F: FnOnce(Option<&kernel::Driver>) -> R
+    {
+        f(None)
+    }
+}
+
+#[test]
+fn round_robin_preempts_when_timeslice_expires() {
+    let mut chip = Host::new();
+    let order = Rc::new(RefCell::new(Vec::new()));
+
+    // Each app uses up a whole timeslice between system calls and never
+    // yields, so only the timer gets it off the CPU.
+    let busy = |id: usize| {
+        let order = order.clone();
+        move |_: Event| {
+            order.borrow_mut().push(id);
+            systick::elapse(TIMESLICE_US);
+            Syscall::Memop { op: 2 /* memory start */, arg: 0 }
+        }
+    };
+
+    unsafe {
+        let image = app::image(512, 0, 1024);
+        let first = app::create_process(Box::new(busy(0)), image, FaultResponse::Panic, 4)
+            .unwrap();
+        let second = app::create_process(Box::new(busy(1)), image, FaultResponse::Panic, 4)
+            .unwrap();
+        chip::set_processes(vec![Some(first), Some(second)]);
+        chip::run(&mut Platform, &mut chip, &mut RoundRobin::new(TIMESLICE_US), 4);
+    }
+    assert_eq!(*order.borrow(), vec![0, 1, 0, 1]);
+    assert_eq!(process::statistics(0).unwrap().timeslice_expirations, 2);
+    assert_eq!(process::statistics(1).unwrap().timeslice_expirations, 2);
+}
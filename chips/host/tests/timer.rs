@@ -0,0 +1,99 @@
+//! An app waiting on a one-shot timer.
+
+extern crate capsules;
+extern crate host;
+extern crate kernel;
+
+use capsules::timer::TimerDriver;
+use host::alarm::Alarm;
+use host::app::{self, Event, Syscall};
+use host::{Host, chip, leak};
+use kernel::{Cooperative, FaultResponse};
+use kernel::loader::ALL_COMMANDS;
+use std::cell::RefCell;
+use std::rc::Rc;
+
+const TIMER_FIRED: usize = 0x1000;
+
+struct Platform {
+    timer: &'static TimerDriver<'static, Alarm>,
+}
+
+impl kernel::Platform for Platform {
+    fn with_driver<F, R>(&mut self, driver_num: usize, f: F) -> R
+        where F: FnOnce(Option<&kernel::Driver>) -> R
+    {
+        match driver_num {
+            3 => f(Some(self.timer)),
+            _ => f(None),
+        }
+    }
+}
+
+#[test]
+fn oneshot_fires_after_interval() {
+    let mut chip = Host::new();
+    let timer: &'static TimerDriver<'static, Alarm> =
+        unsafe { leak(TimerDriver::new(chip.alarm, kernel::Container::create())) };
+    chip.alarm.set_client(timer);
+    let mut platform = Platform { timer: timer };
+
+    let callbacks = Rc::new(RefCell::new(Vec::new()));
+    let app_callbacks = callbacks.clone();
+    let mut step = 0;
+    let app = move |event: Event| {
+        step += 1;
+        match (step, event) {
+            (1, _) => {
+                Syscall::Subscribe {
+                    driver: 3,
+                    subscribe_num: 0,
+                    callback: TIMER_FIRED,
+                    userdata: 0,
+                }
+            }
+            (2, Event::Return(0)) => {
+                Syscall::Command {
+                    driver: 3,
+                    command_num: 0, // one-shot
+                    data: 10, // ms
+                    arg2: 0,
+                }
+            }
+            (_, Event::Callback { pc, args }) => {
+                app_callbacks.borrow_mut().push((pc, args));
+                Syscall::Yield
+            }
+            (_, Event::Return(0)) => Syscall::Yield,
+            (_, event) => panic!("Unexpected {:?}", event),
+        }
+    };
+
+    unsafe {
+        let image = app::image_with_permissions(512, 0, 1024, &[(3, ALL_COMMANDS)]);
+        let process = app::create_process(Box::new(app), image, FaultResponse::Panic, 4).unwrap();
+        chip::set_processes(vec![Some(process)]);
+        chip::run(&mut platform, &mut chip, &mut Cooperative::new(), 100);
+    }
+    assert!(callbacks.borrow().is_empty());
+
+    // 10ms of the 32kHz alarm is 327 tics.
+    chip.alarm.advance(326);
+    unsafe {
+        chip::run(&mut platform, &mut chip, &mut Cooperative::new(), 100);
+    }
+    assert!(callbacks.borrow().is_empty());
+
+    chip.alarm.advance(1);
+    unsafe {
+        chip::run(&mut platform, &mut chip, &mut Cooperative::new(), 100);
+    }
+    assert_eq!(*callbacks.borrow(), vec![(TIMER_FIRED, [327, 0, 0, 0])]);
+
+    // A one-shot timer does not fire again.
+    chip.alarm.advance(1000);
+    unsafe {
+        chip::run(&mut platform, &mut chip, &mut Cooperative::new(), 100);
+    }
+    assert_eq!(callbacks.borrow().len(), 1);
+}
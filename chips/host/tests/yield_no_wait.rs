@@ -0,0 +1,115 @@
+//! An app polling for callbacks with yield-no-wait.
+
+extern crate host;
+extern crate kernel;
+
+use host::app::{self, Event, Syscall};
+use host::{Host, chip, leak};
+use kernel::{AppId, Callback, Cooperative, Driver, FaultResponse, ReturnCode};
+use kernel::loader::ALL_COMMANDS;
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+
+const ECHOED: usize = 0x1000;
+
+/// Schedules the app's callback with the value passed to command 0.
+struct Echo {
+    callback: Cell<Option<Callback>>,
+}
+
+impl Driver for Echo {
+    fn subscribe(&self, _: usize, callback: Option<Callback>, _: AppId) -> ReturnCode {
+        self.callback.set(callback);
+        ReturnCode::SUCCESS
+    }
+
+    fn command(&self, _: usize, data: usize, _: usize, _: AppId) -> ReturnCode {
+        self.callback.get().map(|mut cb| cb.schedule(data, 0, 0));
+        ReturnCode::SUCCESS
+    }
+}
+
+struct Platform {
+    echo: &'static Echo,
+}
+
+impl kernel::Platform for Platform {
+    fn with_driver<F, R>(&mut self, driver_num: usize, f: F) -> R
+        where F: FnOnce(Option<&kernel::Driver>) -> R
+    {
+        match driver_num {
+            0 => f(Some(self.echo)),
+            _ => f(None),
+        }
+    }
+}
+
+#[test]
+fn invalid_flag_leaves_callback_queued() {
+    let mut chip = Host::new();
+    let echo: &'static Echo = leak(Echo { callback: Cell::new(None) });
+    let mut platform = Platform { echo: echo };
+
+    // Each event after the app set its flag up, with the flag's value then.
+    let events = Rc::new(RefCell::new(Vec::new()));
+    let app_events = events.clone();
+    let mut flag = 0;
+    let mut step = 0;
+    let app = move |event: Event| {
+        step += 1;
+        if flag != 0 {
+            app_events.borrow_mut().push((event, unsafe { *(flag as *const u8) }));
+        }
+        match (step, event) {
+            (1, _) => Syscall::Memop { op: 1 /* sbrk */, arg: 4 },
+            (2, Event::Return(old_break)) => {
+                flag = old_break as usize;
+                Syscall::Subscribe {
+                    driver: 0,
+                    subscribe_num: 0,
+                    callback: ECHOED,
+                    userdata: 0,
+                }
+            }
+            (3, Event::Return(0)) => {
+                Syscall::Command {
+                    driver: 0,
+                    command_num: 0,
+                    data: 7,
+                    arg2: 0,
+                }
+            }
+            // The kernel's own memory, not the app's.
+            (4, Event::Return(0)) => Syscall::YieldNoWait { flag: 0x10 },
+            (5, _) | (6, _) => Syscall::YieldNoWait { flag: flag },
+            (_, _) => Syscall::Yield,
+        }
+    };
+
+    unsafe {
+        let image = app::image_with_permissions(512, 64, 1024, &[(0, ALL_COMMANDS)]);
+        let process = app::create_process(Box::new(app), image, FaultResponse::Panic, 4).unwrap();
+        chip::set_processes(vec![Some(process)]);
+        chip::run(&mut platform, &mut chip, &mut Cooperative::new(), 100);
+    }
+
+    let events = events.borrow();
+    let einval: isize = ReturnCode::EINVAL.into();
+    // Subscribe and command succeed.
+    assert_eq!(events[0], (Event::Return(0), 0));
+    assert_eq!(events[1], (Event::Return(0), 0));
+    // The bad flag is refused and the callback stays queued...
+    assert_eq!(events[2], (Event::Return(einval), 0));
+    // ...until the app passes a flag it owns.
+    let echoed = Event::Callback {
+        pc: ECHOED,
+        args: [7, 0, 0, 0],
+    };
+    assert_eq!(events[3], (echoed, 1));
+    // With nothing queued the call returns at once and clears the flag.
+    match events[4] {
+        (Event::Return(_), 0) => {}
+        event => panic!("Expected a return with the flag clear, got {:?}", event),
+    }
+    assert_eq!(events.len(), 5);
+}
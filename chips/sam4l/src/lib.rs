@@ -52,6 +52,7 @@ extern "C" {
     // Defined in src/arch/cortex-m4/ctx_switch.S
     fn SVC_Handler();
     fn systick_handler();
+    fn hard_fault_handler();
 
     fn generic_isr();
 
@@ -202,7 +203,10 @@ pub unsafe fn init() {
     pm::configure_48mhz_dfll();
 }
 
-unsafe extern "C" fn hard_fault_handler() {
+/// Called by the Cortex-M4 hard fault handler for faults that occur in the
+/// kernel rather than in a process.
+#[no_mangle]
+pub unsafe extern "C" fn hard_fault_handler_kernel() {
     use core::intrinsics::offset;
 
     let faulting_stack: *mut u32;
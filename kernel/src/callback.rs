@@ -1,19 +1,33 @@
 use core::nonzero::NonZero;
 use process;
 
-#[derive(Clone,Copy)]
+/// Identifies a process.
+///
+/// Besides the index of the process slot, an `AppId` records the generation
+/// of the process in that slot. The generation changes whenever the process
+/// restarts or exits, so ids (and callbacks and buffers) held from before are
+/// recognized as stale and rejected rather than applied to the new occupant.
+#[derive(Clone,Copy,PartialEq,Eq)]
 pub struct AppId {
     idx: usize,
+    generation: usize,
 }
 
 impl AppId {
-    pub unsafe fn new(idx: usize) -> AppId {
-        AppId { idx: idx }
+    pub unsafe fn new(idx: usize, generation: usize) -> AppId {
+        AppId {
+            idx: idx,
+            generation: generation,
+        }
     }
 
     pub fn idx(&self) -> usize {
         self.idx
     }
+
+    pub fn generation(&self) -> usize {
+        self.generation
+    }
 }
 
 #[derive(Clone, Copy)]
@@ -24,6 +38,8 @@ pub struct Callback {
 }
 
 impl Callback {
+    /// `fn_ptr` must not be null. Apps pass a null pointer to unsubscribe,
+    /// which drivers see as no callback at all.
     pub unsafe fn new(appid: AppId, appdata: usize, fn_ptr: *mut ()) -> Callback {
         Callback {
             app_id: appid,
@@ -32,6 +48,12 @@ impl Callback {
         }
     }
 
+    /// Queues the callback to run in its process with the given arguments.
+    ///
+    /// Returns `false` if the callback was not delivered, because the
+    /// process's callback queue is full or the process has since exited or
+    /// restarted. Drivers for event sources that can fire faster than an app
+    /// keeps up should count these and report them with a later callback.
     pub fn schedule(&mut self, r0: usize, r1: usize, r2: usize) -> bool {
         process::schedule(process::Callback {
                               r0: r0,
@@ -46,4 +68,10 @@ impl Callback {
     pub fn app_id(&self) -> AppId {
         self.app_id
     }
+
+    /// Whether the process the callback belongs to is still running, so a
+    /// failed `schedule` means its callback queue was full.
+    pub fn is_live(&self) -> bool {
+        unsafe { process::live_process(self.app_id).is_some() }
+    }
 }
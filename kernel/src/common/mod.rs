@@ -7,8 +7,10 @@ pub mod take_cell;
 pub mod volatile_cell;
 pub mod list;
 pub mod math;
+pub mod slice_writer;
 
 pub use self::list::{List, ListLink, ListNode};
 pub use self::queue::Queue;
 pub use self::ring_buffer::RingBuffer;
+pub use self::slice_writer::SliceWriter;
 pub use self::volatile_cell::VolatileCell;
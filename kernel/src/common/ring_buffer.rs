@@ -15,6 +15,24 @@ impl<'a, T: Copy> RingBuffer<'a, T> {
             ring: ring,
         }
     }
+
+    /// Returns the number of elements in the buffer.
+    pub fn len(&self) -> usize {
+        unsafe {
+            let head = read_volatile(&self.head);
+            let tail = read_volatile(&self.tail);
+            (tail + self.ring.len() - head) % self.ring.len()
+        }
+    }
+
+    /// Returns the element `i` places from the head without removing it.
+    pub fn get(&self, i: usize) -> Option<T> {
+        if i < self.len() {
+            Some(self.ring[(self.head + i) % self.ring.len()])
+        } else {
+            None
+        }
+    }
 }
 
 impl<'a, T: Copy> queue::Queue<T> for RingBuffer<'a, T> {
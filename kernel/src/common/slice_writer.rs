@@ -0,0 +1,63 @@
+//! Formatting into a fixed buffer.
+
+use core::cmp;
+use core::fmt::{self, Write};
+
+/// Formats into a fixed buffer, dropping whatever does not fit. Writes never
+/// fail, so a message too long for the buffer comes out cut short rather than
+/// not at all.
+pub struct SliceWriter<'a> {
+    buf: &'a mut [u8],
+    len: usize,
+}
+
+impl<'a> SliceWriter<'a> {
+    pub fn new(buf: &'a mut [u8]) -> SliceWriter<'a> {
+        SliceWriter { buf: buf, len: 0 }
+    }
+
+    /// The number of bytes written to the start of the buffer.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+}
+
+impl<'a> Write for SliceWriter<'a> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        let n = cmp::min(s.len(), self.buf.len() - self.len);
+        self.buf[self.len..self.len + n].clone_from_slice(&s.as_bytes()[..n]);
+        self.len += n;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::fmt::Write;
+    use super::SliceWriter;
+
+    #[test]
+    fn writes_fit() {
+        let mut buf = [0; 8];
+        let len = {
+            let mut out = SliceWriter::new(&mut buf);
+            write!(out, "{}-{}", 12, 34).unwrap();
+            out.len()
+        };
+        assert_eq!(&buf[..len], b"12-34");
+    }
+
+    #[test]
+    fn truncates_what_does_not_fit() {
+        let mut buf = [0; 4];
+        let len = {
+            let mut out = SliceWriter::new(&mut buf);
+            out.write_str("abc").unwrap();
+            out.write_str("def").unwrap();
+            out.write_str("g").unwrap();
+            out.len()
+        };
+        assert_eq!(len, 4);
+        assert_eq!(&buf, b"abcd");
+    }
+}
@@ -1,8 +1,8 @@
 use callback::AppId;
 use core::marker::PhantomData;
-use core::mem::size_of;
+use core::mem::{self, size_of};
 use core::ops::{Deref, DerefMut};
-use core::ptr::{read_volatile, write_volatile, Unique};
+use core::ptr::{self, read_volatile, write_volatile, Unique};
 use process::{self, Error};
 
 pub static mut CONTAINER_COUNTER: usize = 0;
@@ -13,7 +13,7 @@ pub struct Container<T: Default> {
 }
 
 pub struct AppliedContainer<T> {
-    appid: usize,
+    appid: AppId,
     container: *mut T,
     _phantom: PhantomData<T>,
 }
@@ -24,26 +24,29 @@ impl<T> AppliedContainer<T> {
               R: Copy
     {
         let mut allocator = Allocator {
-            app: unsafe { process::PROCS[self.appid].as_mut().unwrap() },
+            app: unsafe { process::PROCS[self.appid.idx()].as_mut().unwrap() },
             app_id: self.appid,
         };
         let mut root = unsafe { Owned::new(self.container, self.appid) };
-        fun(&mut root, &mut allocator)
+        let res = fun(&mut root, &mut allocator);
+        // The root stays allocated until the container is released.
+        mem::forget(root);
+        res
     }
 }
 
 pub struct Allocator<'a> {
     app: &'a mut process::Process<'a>,
-    app_id: usize,
+    app_id: AppId,
 }
 
 pub struct Owned<T: ?Sized> {
     data: Unique<T>,
-    app_id: usize,
+    app_id: AppId,
 }
 
 impl<T: ?Sized> Owned<T> {
-    pub unsafe fn new(data: *mut T, app_id: usize) -> Owned<T> {
+    pub unsafe fn new(data: *mut T, app_id: AppId) -> Owned<T> {
         Owned {
             data: Unique::new(data),
             app_id: app_id,
@@ -51,21 +54,20 @@ impl<T: ?Sized> Owned<T> {
     }
 
     pub fn appid(&self) -> AppId {
-        unsafe { AppId::new(self.app_id) }
+        self.app_id
     }
 }
 
 impl<T: ?Sized> Drop for Owned<T> {
     fn drop(&mut self) {
         unsafe {
-            let app_id = self.app_id;
-            let data = self.data.get_mut() as *mut T as *mut u8;
-            match process::PROCS[app_id] {
-                None => {}
-                Some(ref mut app) => {
-                    app.free(data);
-                }
-            }
+            let data = self.data.get_mut() as *mut T;
+            // Memory of a process that has since restarted or exited belongs
+            // to the new occupant, so it must not be touched on its behalf.
+            process::live_process(self.app_id).map(|app| {
+                ptr::drop_in_place(data);
+                app.free(data as *mut u8);
+            });
         }
     }
 }
@@ -88,9 +90,9 @@ impl<'a> Allocator<'a> {
         unsafe {
             let app_id = self.app_id;
             self.app.alloc(size_of::<T>()).map_or(Err(Error::OutOfMemory), |arr| {
-                let mut owned = Owned::new(arr.as_mut_ptr() as *mut T, app_id);
-                *owned = data;
-                Ok(owned)
+                let ptr = arr.as_mut_ptr() as *mut T;
+                ptr::write(ptr, data);
+                Ok(Owned::new(ptr, app_id))
             })
         }
     }
@@ -108,15 +110,14 @@ impl<T: Default> Container<T> {
 
     pub fn container(&self, appid: AppId) -> Option<AppliedContainer<T>> {
         unsafe {
-            let app_id = appid.idx();
-            match process::PROCS[app_id] {
-                Some(ref mut app) => {
+            match process::live_process(appid) {
+                Some(app) => {
                     let cntr = app.container_for::<T>(self.container_num);
-                    if cntr.is_null() {
+                    if (*cntr).is_null() {
                         None
                     } else {
                         Some(AppliedContainer {
-                            appid: app_id,
+                            appid: appid,
                             container: *cntr,
                             _phantom: PhantomData,
                         })
@@ -132,17 +133,19 @@ impl<T: Default> Container<T> {
               R: Copy
     {
         unsafe {
-            let app_id = appid.idx();
-            match process::PROCS[app_id] {
-                Some(ref mut app) => {
+            match process::live_process(appid) {
+                Some(app) => {
                     app.container_for_or_alloc::<T>(self.container_num)
                         .map_or(Err(Error::OutOfMemory), move |root_ptr| {
-                            let mut root = Owned::new(root_ptr, app_id);
+                            let mut root = Owned::new(root_ptr, appid);
                             let mut allocator = Allocator {
                                 app: app,
-                                app_id: app_id,
+                                app_id: appid,
                             };
                             let res = fun(&mut root, &mut allocator);
+                            // The root stays allocated until the container
+                            // is released.
+                            mem::forget(root);
                             Ok(res)
                         })
                 }
@@ -151,17 +154,38 @@ impl<T: Default> Container<T> {
         }
     }
 
+    /// Releases the state this container holds for `appid`, for example
+    /// when the app unsubscribes. The state is dropped, freeing any memory
+    /// it owns, and its memory is returned to the process. The next `enter`
+    /// starts again from `T::default()`.
+    pub fn release(&self, appid: AppId) {
+        unsafe {
+            let root_ptr = process::live_process(appid).map_or(ptr::null_mut(), |app| {
+                let ctr_ptr = app.container_for::<T>(self.container_num);
+                let root_ptr = *ctr_ptr;
+                write_volatile(ctr_ptr, ptr::null_mut());
+                root_ptr
+            });
+            if !root_ptr.is_null() {
+                drop(Owned::new(root_ptr, appid));
+            }
+        }
+    }
+
     pub fn each<F>(&self, fun: F)
         where F: Fn(&mut Owned<T>)
     {
         unsafe {
-            let itr = process::PROCS.iter_mut().filter_map(|p| p.as_mut());
-            for (app_id, app) in itr.enumerate() {
+            let itr = process::PROCS.iter_mut()
+                .enumerate()
+                .filter_map(|(i, p)| p.as_mut().map(|app| (i, app)));
+            for (idx, app) in itr {
                 let ctr_ptr = app.container_for::<T>(self.container_num);
-                if !ctr_ptr.is_null() {
+                if !(*ctr_ptr).is_null() {
                     let root_ptr = *ctr_ptr;
-                    let mut root = Owned::new(root_ptr, app_id);
+                    let mut root = Owned::new(root_ptr, AppId::new(idx, app.generation()));
                     fun(&mut root);
+                    mem::forget(root);
                 }
             }
         }
@@ -191,7 +215,7 @@ impl<'a, T: Default> Iterator for Iter<'a, T> {
         while self.index < self.len {
             let idx = self.index;
             self.index += 1;
-            let res = self.container.container(unsafe { AppId::new(idx) });
+            let res = process::appid(idx).and_then(|appid| self.container.container(appid));
             if res.is_some() {
                 return res;
             }
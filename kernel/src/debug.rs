@@ -0,0 +1,188 @@
+//! Buffered, non-blocking debug output for the kernel.
+//!
+//! `debug!` formats into a RAM ring buffer and returns immediately. The
+//! buffer is drained in the background through a UART's `send_bytes`, a chunk
+//! at a time, so printing never stalls the scheduler. The UART is usually a
+//! device on a shared UART, so debug output interleaves with the app console
+//! instead of colliding with its transfers.
+//!
+//! Output written before a board has set up a `DebugWriter` is discarded.
+//! Output written while the buffer is full is dropped, and the number of bytes
+//! dropped is written out once the output buffered before them has been sent.
+
+use common::{Queue, RingBuffer, SliceWriter};
+use common::take_cell::TakeCell;
+use core::cell::Cell;
+use core::fmt::{self, Arguments, Write};
+use hil::uart::{self, UART};
+use process;
+
+/// The kernel's version, as given in its Cargo manifest.
+pub const VERSION: &'static str = env!("CARGO_PKG_VERSION");
+
+/// Writes formatted output, followed by a newline, to the kernel's debug
+/// buffer.
+#[macro_export]
+macro_rules! debug {
+    () => ({
+        debug!("")
+    });
+    ($msg:expr) => ({
+        $crate::debug::debug_fmt(format_args!(concat!($msg, "\r\n")))
+    });
+    ($fmt:expr, $($arg:tt)+) => ({
+        $crate::debug::debug_fmt(format_args!(concat!($fmt, "\r\n"), $($arg)+))
+    });
+}
+
+pub static mut WRITE_BUF: [u8; 64] = [0; 64];
+pub static mut OUTPUT_BUF: [u8; 1024] = [0; 1024];
+
+static mut WRITER: Option<&'static DebugWriter> = None;
+
+/// Sets the writer `debug!` output goes to.
+pub unsafe fn set_debug_writer(writer: &'static DebugWriter) {
+    WRITER = Some(writer);
+}
+
+/// Queues formatted output without a trailing newline. Used by `debug!`.
+pub fn debug_fmt(args: Arguments) {
+    match unsafe { WRITER } {
+        Some(writer) => {
+            let mut output = Output { writer: writer };
+            let _ = output.write_fmt(args);
+            writer.publish();
+        }
+        None => {}
+    }
+}
+
+/// Passes debug output that has not been sent yet to `send`, a byte at a
+/// time. For panic handlers, which cannot wait for the UART to drain the
+/// buffer.
+pub unsafe fn flush<F: FnMut(u8)>(mut send: F) {
+    match WRITER {
+        Some(writer) => {
+            writer.output.map(|output| {
+                while let Some(byte) = output.dequeue() {
+                    send(byte);
+                }
+            });
+        }
+        None => {}
+    }
+}
+
+/// Writes the kernel version, the process that was running and the state of
+/// every process. For panic handlers, so a panic or fault can be diagnosed
+/// from a serial log.
+pub unsafe fn panic_dump<W: Write>(writer: &mut W) {
+    let _ = write!(writer, "Tock kernel {}\r\n", VERSION);
+    match process::CURRENT_PROCESS {
+        Some(idx) => {
+            let _ = write!(writer, "Running process {}\r\n", idx);
+        }
+        None => {
+            let _ = writer.write_str("No process running\r\n");
+        }
+    }
+    for idx in 0..process::num_procs() {
+        match process::process_at(idx) {
+            Some(p) => {
+                let _ = write!(writer, "Process {}:\r\n", idx);
+                p.dump(writer);
+            }
+            None => {}
+        }
+    }
+}
+
+pub struct DebugWriter {
+    uart: &'static UART,
+    output: TakeCell<RingBuffer<'static, u8>>,
+    tx_buffer: TakeCell<&'static mut [u8]>,
+    dropped: Cell<usize>,
+}
+
+impl DebugWriter {
+    /// `tx_buffer` is handed to the UART for each transfer. `output_buffer`
+    /// holds the output waiting to be sent.
+    pub fn new(uart: &'static UART,
+               tx_buffer: &'static mut [u8],
+               output_buffer: &'static mut [u8])
+               -> DebugWriter {
+        DebugWriter {
+            uart: uart,
+            output: TakeCell::new(RingBuffer::new(output_buffer)),
+            tx_buffer: TakeCell::new(tx_buffer),
+            dropped: Cell::new(0),
+        }
+    }
+
+    /// Queues `bytes`, counting those that do not fit as dropped.
+    fn write(&self, bytes: &[u8]) {
+        let queued = self.output.map(|output| {
+            for (i, byte) in bytes.iter().enumerate() {
+                if !output.enqueue(*byte) {
+                    return i;
+                }
+            }
+            bytes.len()
+        });
+        let dropped = bytes.len() - queued.unwrap_or(0);
+        self.dropped.set(self.dropped.get() + dropped);
+    }
+
+    /// Starts sending queued output if no transfer is in progress.
+    fn publish(&self) {
+        self.tx_buffer.take().map(|tx_buffer| {
+            let mut len = 0;
+            self.output.map(|output| {
+                while len < tx_buffer.len() {
+                    match output.dequeue() {
+                        Some(byte) => {
+                            tx_buffer[len] = byte;
+                            len += 1;
+                        }
+                        None => break,
+                    }
+                }
+            });
+
+            let dropped = self.dropped.get();
+            if len == 0 && dropped > 0 {
+                let mut out = SliceWriter::new(&mut tx_buffer[..]);
+                let _ = write!(out, "\r\n[{} bytes of debug output dropped]\r\n", dropped);
+                len = out.len();
+                self.dropped.set(0);
+            }
+
+            if len > 0 {
+                self.uart.send_bytes(tx_buffer, len);
+            } else {
+                self.tx_buffer.replace(tx_buffer);
+            }
+        });
+    }
+}
+
+impl uart::Client for DebugWriter {
+    fn write_done(&self, buffer: &'static mut [u8]) {
+        self.tx_buffer.replace(buffer);
+        self.publish();
+    }
+
+    fn read_done(&self, _byte: u8) {}
+}
+
+/// Queues formatted output into a `DebugWriter`.
+struct Output {
+    writer: &'static DebugWriter,
+}
+
+impl Write for Output {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.writer.write(s.as_bytes());
+        Ok(())
+    }
+}
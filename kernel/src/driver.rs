@@ -49,33 +49,47 @@ pub trait Driver {
     /// each minor number subscription. Thus, a second call to subscribe from
     /// the same application would replace a previous callback.
     ///
+    /// An app unsubscribes by passing a null callback pointer, which the
+    /// driver receives as `None`. The driver should forget the app's callback
+    /// for that minor number and stop any work whose only purpose was to
+    /// deliver it.
+    ///
     /// This pushes most per-application virtualization to the application
     /// itself. For example, a timer driver exposes only one timer to each
     /// application, and the application is responsible for virtualizing that
     /// timer if it needs to.
     ///
-    /// The driver should signal success or failure through the sign of the
-    /// return value from `subscribe`. A negative return value signifies an
-    /// error, while positive a return values signifies success. In addition,
-    /// the magnitude of the return value of can signify extra information such
-    /// as error type.
+    /// The driver signals success or failure through the returned
+    /// `ReturnCode`, which the app receives as a negative error code or a
+    /// non-negative value.
     #[allow(unused_variables)]
-    fn subscribe(&self, minor_num: usize, callback: ::Callback) -> isize {
-        -1
+    fn subscribe(&self,
+                 minor_num: usize,
+                 callback: Option<::Callback>,
+                 app_id: ::AppId)
+                 -> ::ReturnCode {
+        ::ReturnCode::ENOSUPPORT
     }
 
     /// `command` instructs a driver to perform some action synchronously.
+    /// `r2` and `r3` are the two arguments the app passed with the command.
     ///
     /// The return value should reflect the result of an action. For example,
-    /// enabling/disabling a peripheral should return a success or error code.
-    /// Reading the current system time should return the time as an integer.
+    /// enabling/disabling a peripheral should return `SUCCESS` or an error
+    /// code. Reading the current system time should return the time as
+    /// `SuccessWithValue`.
     ///
     /// Commands should not execute long running tasks synchronously. However,
     /// commands might "kick-off" asynchronous tasks in coordination with a
     /// `subscribe` call.
     #[allow(unused_variables)]
-    fn command(&self, minor_num: usize, r2: usize, caller_id: ::AppId) -> isize {
-        -1
+    fn command(&self,
+               minor_num: usize,
+               r2: usize,
+               r3: usize,
+               caller_id: ::AppId)
+               -> ::ReturnCode {
+        ::ReturnCode::ENOSUPPORT
     }
 
     /// `allow` lets an application give the driver access to a buffer in the
@@ -84,8 +98,23 @@ pub trait Driver {
     /// The buffer is __shared__ between the application and driver, meaning the
     /// driver should not rely on the contents of the buffer to remain
     /// unchanged.
+    ///
+    /// An app takes back a buffer by allowing a null or zero-length one,
+    /// which the driver receives as `None`. Once the driver returns success
+    /// it must not touch the old buffer again, so the app can reuse the
+    /// memory straight away. A driver that can't cancel an operation using
+    /// the buffer should return `EBUSY` until the operation completes.
+    ///
+    /// Unsubscribing does not take back a buffer, and does not always stop
+    /// an operation already using one. For example, after the console's
+    /// `subscribe` 1 with `None`, a write that has reached the UART still
+    /// runs to the end, but the app is not told when it finishes.
     #[allow(unused_variables)]
-    fn allow(&self, app: ::AppId, minor_num: usize, slice: ::AppSlice<::Shared, u8>) -> isize {
-        -1
+    fn allow(&self,
+             app: ::AppId,
+             minor_num: usize,
+             slice: Option<::AppSlice<::Shared, u8>>)
+             -> ::ReturnCode {
+        ::ReturnCode::ENOSUPPORT
     }
 }
@@ -0,0 +1,255 @@
+//! Inter-process communication.
+//!
+//! A process offers a service by registering its name with `allow` 0 and a
+//! callback with `subscribe` 0. Other processes find the service by name with
+//! `allow` 1, which returns the service's id: the index of its process. A
+//! client shares a buffer with a service with `allow` 2 + id and notifies the
+//! service with `command` 0. The service's callback receives the client's id
+//! and the length and address of the buffer the client shared with it. The
+//! service answers with `command` 1, which calls the callback the client
+//! registered with `subscribe` 1 with the service's id and the same buffer.
+//! A process withdraws its service by allowing no buffer with `allow` 0.
+//!
+//! The service is given access to a client's buffer through an MPU region when
+//! the client notifies it, so shared buffers must be a power of two long, at
+//! least 32 bytes and aligned to their length. The service loses that access
+//! as soon as the client shares another buffer with it or stops sharing.
+
+use {AppId, AppSlice, Callback, Container, Driver, ReturnCode, Shared};
+use process;
+
+/// Number of services a process can share buffers with. Services in higher
+/// process slots can still be notified, but without a buffer.
+const MAX_SERVICES: usize = 4;
+
+/// The smallest buffer that can be shared, the size of the smallest MPU
+/// region.
+const MIN_SHARED_LEN: usize = 32;
+
+pub struct IPCData {
+    /// The name this process offers a service under.
+    name: Option<AppSlice<Shared, u8>>,
+
+    /// Called when a client notifies this process's service.
+    service_callback: Option<Callback>,
+
+    /// Called when a service notifies this process.
+    client_callback: Option<Callback>,
+
+    /// Buffers this process has shared, indexed by the id of the service they
+    /// are shared with.
+    shared_memory: [Option<AppSlice<Shared, u8>>; MAX_SERVICES],
+}
+
+impl Default for IPCData {
+    fn default() -> IPCData {
+        IPCData {
+            name: None,
+            service_callback: None,
+            client_callback: None,
+            shared_memory: [None, None, None, None],
+        }
+    }
+}
+
+pub struct IPC {
+    data: Container<IPCData>,
+}
+
+impl IPC {
+    pub unsafe fn new() -> IPC {
+        IPC { data: Container::create() }
+    }
+
+    /// Returns the id of the service registered under `name`.
+    fn discover(&self, name: &[u8]) -> Option<usize> {
+        self.data
+            .iter()
+            .filter_map(|cntr| {
+                cntr.enter(|data, _| {
+                    let registered = data.service_callback.is_some() &&
+                                     data.name.as_ref().map_or(false, |n| n.as_ref() == name);
+                    if registered {
+                        Some(data.appid().idx())
+                    } else {
+                        None
+                    }
+                })
+            })
+            .next()
+    }
+
+    /// Returns the address and length of the buffer `appid` shared with
+    /// `service`, if any.
+    fn shared_buffer(&self, appid: AppId, service: usize) -> Option<(usize, usize)> {
+        self.data.container(appid).and_then(|cntr| {
+            cntr.enter(|data, _| {
+                data.shared_memory
+                    .get(service)
+                    .and_then(|slice| slice.as_ref())
+                    .map(|slice| (slice.ptr() as usize, slice.len()))
+            })
+        })
+    }
+
+    /// Schedules the service callback of process `service` on behalf of
+    /// `client`, first giving the service access to the buffer `client`
+    /// shared with it.
+    fn notify_service(&self, client: AppId, service: usize) -> ReturnCode {
+        let service_id = match process::appid(service) {
+            Some(appid) => appid,
+            None => return ReturnCode::EINVAL,
+        };
+        let callback = self.data
+            .container(service_id)
+            .and_then(|cntr| cntr.enter(|data, _| data.service_callback));
+        let mut callback = match callback {
+            Some(callback) => callback,
+            None => return ReturnCode::EINVAL,
+        };
+
+        let (ptr, len) = self.shared_buffer(client, service).unwrap_or((0, 0));
+        if len > 0 {
+            let mapped = unsafe {
+                process::live_process(service_id)
+                    .map_or(false, |p| p.add_shared_region(client, ptr, len))
+            };
+            if !mapped {
+                return ReturnCode::ENOMEM;
+            }
+        }
+
+        if callback.schedule(client.idx(), len, ptr) {
+            ReturnCode::SUCCESS
+        } else {
+            ReturnCode::EBUSY
+        }
+    }
+
+    /// Schedules the client callback of process `client` on behalf of
+    /// `service`.
+    fn notify_client(&self, service: AppId, client: usize) -> ReturnCode {
+        let client_id = match process::appid(client) {
+            Some(appid) => appid,
+            None => return ReturnCode::EINVAL,
+        };
+        let callback = self.data
+            .container(client_id)
+            .and_then(|cntr| cntr.enter(|data, _| data.client_callback));
+        let mut callback = match callback {
+            Some(callback) => callback,
+            None => return ReturnCode::EINVAL,
+        };
+
+        let (ptr, len) = self.shared_buffer(client_id, service.idx()).unwrap_or((0, 0));
+        if callback.schedule(service.idx(), len, ptr) {
+            ReturnCode::SUCCESS
+        } else {
+            ReturnCode::EBUSY
+        }
+    }
+}
+
+impl Driver for IPC {
+    /// subscribe_num: 0 - register the callback for this process's service
+    /// subscribe_num: 1 - register the callback for notifications from
+    ///                    services
+    fn subscribe(&self,
+                 subscribe_num: usize,
+                 callback: Option<Callback>,
+                 appid: AppId)
+                 -> ReturnCode {
+        match subscribe_num {
+            0 => {
+                self.data
+                    .enter(appid, |data, _| {
+                        data.service_callback = callback;
+                        ReturnCode::SUCCESS
+                    })
+                    .unwrap_or_else(|err| err.into())
+            }
+            1 => {
+                self.data
+                    .enter(appid, |data, _| {
+                        data.client_callback = callback;
+                        ReturnCode::SUCCESS
+                    })
+                    .unwrap_or_else(|err| err.into())
+            }
+            _ => ReturnCode::ENOSUPPORT,
+        }
+    }
+
+    /// command_num: 0 - notify the service with id `data`
+    /// command_num: 1 - notify the client with id `data`
+    fn command(&self, command_num: usize, data: usize, _: usize, appid: AppId) -> ReturnCode {
+        match command_num {
+            0 => self.notify_service(appid, data),
+            1 => self.notify_client(appid, data),
+            _ => ReturnCode::ENOSUPPORT,
+        }
+    }
+
+    /// allow_num: 0      - register a service under the name in the buffer,
+    ///                     which must not change while the service is offered.
+    ///                     No buffer withdraws the service.
+    /// allow_num: 1      - return the id of the service named in the buffer
+    /// allow_num: 2 + id - share the buffer with the service with that id. No
+    ///                     buffer stops sharing.
+    fn allow(&self,
+             appid: AppId,
+             allow_num: usize,
+             slice: Option<AppSlice<Shared, u8>>)
+             -> ReturnCode {
+        match allow_num {
+            0 => {
+                let taken = slice.as_ref().map_or(false, |slice| {
+                    self.discover(slice.as_ref()).map_or(false, |idx| idx != appid.idx())
+                });
+                if taken {
+                    return ReturnCode::EALREADY;
+                }
+                self.data
+                    .enter(appid, |data, _| {
+                        data.name = slice;
+                        ReturnCode::SUCCESS
+                    })
+                    .unwrap_or_else(|err| err.into())
+            }
+            1 => {
+                slice.and_then(|slice| self.discover(slice.as_ref()))
+                    .map_or(ReturnCode::EINVAL, |idx| ReturnCode::SuccessWithValue { value: idx })
+            }
+            _ => {
+                let service = allow_num - 2;
+                if service >= MAX_SERVICES {
+                    return ReturnCode::EINVAL;
+                }
+                let valid = slice.as_ref().map_or(true, |slice| {
+                    let len = slice.len();
+                    len >= MIN_SHARED_LEN && len.is_power_of_two() &&
+                    slice.ptr() as usize % len == 0
+                });
+                if !valid {
+                    return ReturnCode::EINVAL;
+                }
+                let res = self.data
+                    .enter(appid, |data, _| {
+                        data.shared_memory[service] = slice;
+                        ReturnCode::SUCCESS
+                    })
+                    .unwrap_or_else(|err| err.into());
+                if res == ReturnCode::SUCCESS {
+                    // The service could still reach the old buffer through its
+                    // MPU region. It is given the new one on the next notify.
+                    unsafe {
+                        process::appid(service)
+                            .and_then(|id| process::live_process(id))
+                            .map(|p| p.remove_shared_region(appid));
+                    }
+                }
+                res
+            }
+        }
+    }
+}
@@ -5,16 +5,23 @@ pub mod common;
 
 pub mod callback;
 pub mod container;
+#[macro_use]
+pub mod debug;
 pub mod driver;
+pub mod ipc;
 pub mod mem;
+pub mod mpu;
 pub mod process;
+pub mod returncode;
 pub mod hil;
+pub mod loader;
+pub mod scheduler;
 
 pub mod support;
 
-mod sched;
+pub mod sched;
 
-mod syscall;
+pub mod syscall;
 mod platform;
 
 pub use callback::{AppId, Callback};
@@ -22,11 +29,15 @@ pub use container::Container;
 pub use driver::Driver;
 pub use mem::{AppSlice, AppPtr, Private, Shared};
 pub use platform::{Chip, MPU, Platform, SysTick};
-pub use process::{Process, State};
+pub use loader::LoadError;
+pub use process::{FaultResponse, Process, State};
+pub use returncode::ReturnCode;
+pub use scheduler::{Cooperative, FixedPriority, RoundRobin, Scheduler};
 
-pub fn main<P: Platform, C: Chip>(platform: &mut P,
-                                  chip: &mut C,
-                                  processes: &'static mut [Option<process::Process<'static>>]) {
+pub fn main<P: Platform, C: Chip, S: Scheduler>(platform: &mut P,
+                                                chip: &mut C,
+                                                processes: &'static mut [Option<process::Process<'static>>],
+                                                scheduler: &mut S) {
     let processes = unsafe {
         process::PROCS = processes;
         &mut process::PROCS
@@ -36,24 +47,22 @@ pub fn main<P: Platform, C: Chip>(platform: &mut P,
         unsafe {
             chip.service_pending_interrupts();
 
-            let mut running_left = false;
-            for (i, p) in processes.iter_mut().enumerate() {
-                p.as_mut().map(|process| {
-                    sched::do_process(platform, chip, process, AppId::new(i));
-                    if process.state == process::State::Running {
-                        running_left = true;
-                    }
-                });
-                if chip.has_pending_interrupts() {
-                    break;
+            match scheduler.next(processes) {
+                Some(i) => {
+                    let timeslice = scheduler.timeslice();
+                    processes[i].as_mut().map(|process| {
+                        let appid = AppId::new(i, process.generation());
+                        sched::do_process(platform, chip, process, appid, timeslice);
+                    });
                 }
-            }
-
-            support::atomic(|| {
-                if !chip.has_pending_interrupts() && !running_left {
-                    support::wfi();
+                None => {
+                    support::atomic(|| {
+                        if !chip.has_pending_interrupts() {
+                            support::wfi();
+                        }
+                    })
                 }
-            })
+            }
         };
     }
 }
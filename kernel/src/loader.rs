@@ -0,0 +1,612 @@
+//! Parsing and relocation of app images in the Tock Binary Format (TBF).
+//!
+//! An image starts with a `LoadInfo` header, followed by the app's driver
+//! permission list, the `.rel.data` relocation entries, the text section and
+//! the initial contents of the GOT and data sections. Loading copies the
+//! initial data into the process's memory, zeroes BSS and patches the GOT and
+//! relocated data words with the addresses the image was loaded at.
+//!
+//! Everything here works on plain byte slices and only depends on `core`, so
+//! it can be exercised off target by feeding it `elf2tbf` output.
+
+/// Size of a word in the image. Images are built for 32-bit targets.
+const WORD_LEN: usize = 4;
+
+/// Number of words in the `LoadInfo` header.
+const HEADER_WORDS: usize = 13;
+
+/// Size of the `LoadInfo` header at the start of every image.
+pub const HEADER_LEN: usize = HEADER_WORDS * WORD_LEN;
+
+/// Size of an entry in the permission list: a driver number followed by a
+/// mask of the commands the app may send it.
+const PERMISSION_LEN: usize = 2 * WORD_LEN;
+
+/// A command mask that allows every command, including those numbered 32 and
+/// above.
+pub const ALL_COMMANDS: u32 = 0xffffffff;
+
+/// Words with this bit set are relative to the text section rather than to
+/// the process's memory.
+const TEXT_RELATIVE: u32 = 0x80000000;
+
+/// Reasons an app image can be rejected.
+#[derive(Copy,Clone,PartialEq,Eq,Debug)]
+pub enum LoadError {
+    /// The total size in the header does not match the image, is smaller
+    /// than the header or is not a power of two.
+    BadTotalSize,
+
+    /// The permission list, relocation data, text or initialized data extend
+    /// past the end of the image, or the GOT or relocations refer outside the
+    /// data they initialize.
+    SectionOutsideImage,
+
+    /// BSS overlaps the initialized data, ends before it starts or does not
+    /// fit in the process's memory.
+    BssOutsideMemory,
+
+    /// The entry point is not word aligned.
+    MisalignedEntryPoint,
+
+    /// The memory available is too small for the app's memory requirements.
+    NotEnoughMemory,
+}
+
+/// The header at the start of every app image.
+#[derive(Copy,Clone,Debug)]
+pub struct LoadInfo {
+    pub total_size: usize, // Total padded size of the program image
+    pub rel_data_size: usize,
+    pub entry_loc: usize, // Entry point for user application
+    pub init_data_loc: usize, // Data initialization information in flash
+    pub init_data_size: usize, // Size of initialization information
+    pub got_start_offset: usize, // Offset to start of GOT
+    pub got_end_offset: usize, // Offset to end of GOT
+    pub bss_start_offset: usize, // Offset to start of BSS
+    pub bss_end_offset: usize, // Offset to end of BSS
+    pub min_stack_len: usize, // Minimum stack size
+    pub min_app_heap_len: usize, // Minimum size of the heap the app grows with brk/sbrk
+    pub min_kernel_heap_len: usize, // Minimum size of the heap for container allocations
+    pub permissions_len: usize, // Number of entries in the permission list
+}
+
+impl LoadInfo {
+    /// Reads and validates the header of `image`, checking that every section
+    /// it describes lies within the image and is laid out the way `load`
+    /// expects.
+    pub fn parse(image: &[u8]) -> Result<LoadInfo, LoadError> {
+        if image.len() < HEADER_LEN {
+            return Err(LoadError::BadTotalSize);
+        }
+        let field = |i| read_word(image, i * WORD_LEN) as usize;
+        let load_info = LoadInfo {
+            total_size: field(0),
+            rel_data_size: field(1),
+            entry_loc: field(2),
+            init_data_loc: field(3),
+            init_data_size: field(4),
+            got_start_offset: field(5),
+            got_end_offset: field(6),
+            bss_start_offset: field(7),
+            bss_end_offset: field(8),
+            min_stack_len: field(9),
+            min_app_heap_len: field(10),
+            min_kernel_heap_len: field(11),
+            permissions_len: field(12),
+        };
+        try!(load_info.check(image.len()));
+        try!(load_info.check_relocations(load_info.rel_data(image)));
+        Ok(load_info)
+    }
+
+    /// Offset in the image of the relocation data, which follows the
+    /// permission list.
+    fn rel_data_offset(&self) -> usize {
+        HEADER_LEN + self.permissions_len * PERMISSION_LEN
+    }
+
+    /// Offset in the image of the text section.
+    pub fn text_offset(&self) -> usize {
+        self.rel_data_offset() + self.rel_data_size
+    }
+
+    /// The drivers and commands the app declares it uses.
+    pub fn permissions<'a>(&self, image: &'a [u8]) -> DriverPermissions<'a> {
+        DriverPermissions { list: &image[HEADER_LEN..self.rel_data_offset()] }
+    }
+
+    /// Offset of the entry point from the start of the text section.
+    pub fn entry_offset(&self) -> usize {
+        self.entry_loc
+    }
+
+    /// Offset in memory of the first byte after BSS, rounded up to 8 bytes.
+    /// The stack starts here.
+    pub fn app_mem_offset(&self) -> usize {
+        align8(self.bss_end_offset)
+    }
+
+    /// The stack size, rounded up to 8 bytes.
+    pub fn stack_len(&self) -> usize {
+        align8(self.min_stack_len)
+    }
+
+    /// The smallest amount of memory that satisfies the requirements in the
+    /// header, plus `kernel_len` bytes for the kernel's own per-process
+    /// state. Returns `None` if the requirements overflow.
+    pub fn min_memory_len(&self, kernel_len: usize) -> Option<usize> {
+        checked_align8(self.bss_end_offset)
+            .and_then(|len| checked_align8(self.min_stack_len).and_then(|s| len.checked_add(s)))
+            .and_then(|len| len.checked_add(self.min_app_heap_len))
+            .and_then(|len| len.checked_add(self.min_kernel_heap_len))
+            .and_then(|len| len.checked_add(kernel_len))
+    }
+
+    fn rel_data<'a>(&self, image: &'a [u8]) -> &'a [u8] {
+        &image[self.rel_data_offset()..self.text_offset()]
+    }
+
+    fn init_data<'a>(&self, image: &'a [u8]) -> &'a [u8] {
+        let start = self.text_offset() + self.init_data_loc;
+        &image[start..start + self.init_data_size]
+    }
+
+    fn check(&self, image_len: usize) -> Result<(), LoadError> {
+        if self.total_size != image_len || !image_len.is_power_of_two() {
+            return Err(LoadError::BadTotalSize);
+        }
+
+        // The permission list, relocation data, text and initialized data
+        // follow the header in that order.
+        let image_end = self.permissions_len
+            .checked_mul(PERMISSION_LEN)
+            .and_then(|len| len.checked_add(HEADER_LEN))
+            .and_then(|end| end.checked_add(self.rel_data_size))
+            .and_then(|end| end.checked_add(self.init_data_loc))
+            .and_then(|end| end.checked_add(self.init_data_size));
+        if self.rel_data_size % WORD_LEN != 0 || image_end.map_or(true, |end| end > image_len) {
+            return Err(LoadError::SectionOutsideImage);
+        }
+
+        // The GOT is at the start of the initialized data.
+        if self.got_start_offset > self.got_end_offset ||
+           self.got_end_offset > self.init_data_size ||
+           (self.got_end_offset - self.got_start_offset) % WORD_LEN != 0 {
+            return Err(LoadError::SectionOutsideImage);
+        }
+
+        if self.bss_start_offset < self.init_data_size ||
+           self.bss_end_offset < self.bss_start_offset {
+            return Err(LoadError::BssOutsideMemory);
+        }
+
+        // The entry point must be in the text section. It is the address of a
+        // Thumb function, so ignore the Thumb bit when checking its alignment.
+        let entry = self.entry_loc & !1;
+        if entry >= self.init_data_loc {
+            return Err(LoadError::SectionOutsideImage);
+        }
+        if entry % WORD_LEN != 0 {
+            return Err(LoadError::MisalignedEntryPoint);
+        }
+
+        Ok(())
+    }
+
+    /// Checks that every relocation patches a word within the initialized
+    /// data.
+    fn check_relocations(&self, rel_data: &[u8]) -> Result<(), LoadError> {
+        for offset in relocations(rel_data) {
+            let end = offset.checked_add(WORD_LEN);
+            if end.map_or(true, |end| end > self.init_data_size) {
+                return Err(LoadError::SectionOutsideImage);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The drivers an app may use, and the commands it may send each of them.
+///
+/// Each entry in the list is a driver number and a mask with bit `n` set if
+/// the app may send command `n`, or `ALL_COMMANDS`. An app may subscribe to
+/// and allow buffers to every driver in its list, and may not use any driver
+/// missing from it. An image with an empty list may use no drivers at all.
+#[derive(Copy,Clone)]
+pub struct DriverPermissions<'a> {
+    list: &'a [u8],
+}
+
+impl<'a> DriverPermissions<'a> {
+    /// Whether the app may use `driver` at all.
+    pub fn allows_driver(&self, driver: usize) -> bool {
+        self.command_mask(driver).is_some()
+    }
+
+    /// Whether the app may send command `command` to `driver`.
+    pub fn allows_command(&self, driver: usize, command: usize) -> bool {
+        match self.command_mask(driver) {
+            Some(ALL_COMMANDS) => true,
+            Some(mask) => command < 32 && mask & (1 << command) != 0,
+            None => false,
+        }
+    }
+
+    /// The command mask of the first entry for `driver`.
+    fn command_mask(&self, driver: usize) -> Option<u32> {
+        self.list
+            .chunks(PERMISSION_LEN)
+            .find(|entry| read_word(entry, 0) as usize == driver)
+            .map(|entry| read_word(entry, WORD_LEN))
+    }
+}
+
+/// Loads `image`, whose header has been parsed into `load_info`, into
+/// `memory`.
+///
+/// `mem_base` and `text_base` are the addresses the process will see its
+/// memory and the image's text section at. Initialized data is copied to the
+/// start of `memory`, BSS is zeroed and every GOT entry and relocated data
+/// word is patched: words with the top bit set become `text_base` plus the
+/// offset in the remaining bits, other words become `mem_base` plus their
+/// value.
+pub fn load(load_info: &LoadInfo,
+            image: &[u8],
+            memory: &mut [u8],
+            mem_base: usize,
+            text_base: usize)
+            -> Result<(), LoadError> {
+    if load_info.bss_end_offset > memory.len() {
+        return Err(LoadError::BssOutsideMemory);
+    }
+
+    // Zero out BSS
+    for byte in memory[load_info.bss_start_offset..load_info.bss_end_offset].iter_mut() {
+        *byte = 0;
+    }
+
+    // Copy data into Data section
+    memory[..load_info.init_data_size].clone_from_slice(load_info.init_data(image));
+
+    // Fixup Global Offset Table
+    let mut got_cur = load_info.got_start_offset;
+    while got_cur < load_info.got_end_offset {
+        fixup(memory, got_cur, mem_base, text_base);
+        got_cur += WORD_LEN;
+    }
+
+    // Fixup relocation data
+    for offset in relocations(load_info.rel_data(image)) {
+        fixup(memory, offset, mem_base, text_base);
+    }
+
+    Ok(())
+}
+
+/// Rewrites the word at `offset` in `memory` to an absolute address.
+fn fixup(memory: &mut [u8], offset: usize, mem_base: usize, text_base: usize) {
+    let entry = read_word(memory, offset);
+    let addr = if (entry & TEXT_RELATIVE) == 0 {
+        // Regular data (memory relative)
+        (mem_base as u32).wrapping_add(entry)
+    } else {
+        // rodata or function pointer (code relative)
+        (text_base as u32).wrapping_add(entry ^ TEXT_RELATIVE)
+    };
+    write_word(memory, offset, addr);
+}
+
+/// Iterates over the offsets patched by the `.rel.data` entries in
+/// `rel_data`. Each entry is two words, of which only the first is an
+/// address.
+fn relocations<'a>(rel_data: &'a [u8]) -> Relocations<'a> {
+    Relocations {
+        rel_data: rel_data,
+        index: 0,
+    }
+}
+
+struct Relocations<'a> {
+    rel_data: &'a [u8],
+    index: usize,
+}
+
+impl<'a> Iterator for Relocations<'a> {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<usize> {
+        if self.index + WORD_LEN > self.rel_data.len() {
+            return None;
+        }
+        let offset = read_word(self.rel_data, self.index) as usize;
+        self.index += 2 * WORD_LEN;
+        Some(offset)
+    }
+}
+
+/// Reads the little-endian word at `offset` in `buf`.
+fn read_word(buf: &[u8], offset: usize) -> u32 {
+    (buf[offset] as u32) | (buf[offset + 1] as u32) << 8 | (buf[offset + 2] as u32) << 16 |
+    (buf[offset + 3] as u32) << 24
+}
+
+/// Writes `val` as a little-endian word at `offset` in `buf`.
+fn write_word(buf: &mut [u8], offset: usize, val: u32) {
+    buf[offset] = val as u8;
+    buf[offset + 1] = (val >> 8) as u8;
+    buf[offset + 2] = (val >> 16) as u8;
+    buf[offset + 3] = (val >> 24) as u8;
+}
+
+/// Rounds `len` up to a multiple of 8 bytes.
+fn align8(len: usize) -> usize {
+    (len + 7) & !7
+}
+
+/// Rounds `len` up to a multiple of 8 bytes, or returns `None` on overflow.
+fn checked_align8(len: usize) -> Option<usize> {
+    len.checked_add(7).map(|len| len & !7)
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+
+    use self::std::vec::Vec;
+    use super::*;
+
+    /// Header of a valid image: a word of text, a one-word GOT followed by a
+    /// word of data, and two words of BSS.
+    fn header() -> [usize; HEADER_WORDS] {
+        [64, // total_size
+         0, // rel_data_size
+         0, // entry_loc
+         4, // init_data_loc
+         8, // init_data_size
+         0, // got_start_offset
+         4, // got_end_offset
+         8, // bss_start_offset
+         16, // bss_end_offset
+         128, // min_stack_len
+         0, // min_app_heap_len
+         0, // min_kernel_heap_len
+         0 /* permissions_len */]
+    }
+
+    /// Builds a `len` byte image from `header`, followed by `body`.
+    fn image(header: &[usize; HEADER_WORDS], body: &[u32], len: usize) -> Vec<u8> {
+        let mut image = Vec::new();
+        image.resize(len, 0);
+        for (i, word) in header.iter().enumerate() {
+            write_word(&mut image, i * WORD_LEN, *word as u32);
+        }
+        for (i, word) in body.iter().enumerate() {
+            write_word(&mut image, HEADER_LEN + i * WORD_LEN, *word);
+        }
+        image
+    }
+
+    fn parse(header: &[usize; HEADER_WORDS]) -> Result<LoadInfo, LoadError> {
+        LoadInfo::parse(&image(header, &[], header[0]))
+    }
+
+    #[test]
+    fn valid_image() {
+        let load_info = parse(&header()).unwrap();
+        assert_eq!(load_info.text_offset(), HEADER_LEN);
+        assert_eq!(load_info.app_mem_offset(), 16);
+    }
+
+    #[test]
+    fn shorter_than_header() {
+        assert_eq!(LoadInfo::parse(&[0; HEADER_LEN - 1]).err(),
+                   Some(LoadError::BadTotalSize));
+    }
+
+    #[test]
+    fn total_size_mismatch() {
+        let mut header = header();
+        header[0] = 128;
+        assert_eq!(LoadInfo::parse(&image(&header, &[], 64)).err(),
+                   Some(LoadError::BadTotalSize));
+    }
+
+    #[test]
+    fn total_size_not_power_of_two() {
+        let mut header = header();
+        header[0] = 96;
+        assert_eq!(parse(&header).err(), Some(LoadError::BadTotalSize));
+    }
+
+    #[test]
+    fn init_data_outside_image() {
+        let mut header = header();
+        header[4] = 64;
+        header[7] = 64;
+        header[8] = 64;
+        assert_eq!(parse(&header).err(), Some(LoadError::SectionOutsideImage));
+    }
+
+    #[test]
+    fn section_sizes_overflow() {
+        let mut header = header();
+        header[1] = usize::max_value() & !3;
+        assert_eq!(parse(&header).err(), Some(LoadError::SectionOutsideImage));
+    }
+
+    #[test]
+    fn permission_list_outside_image() {
+        let mut header = header();
+        header[12] = 2;
+        assert_eq!(parse(&header).err(), Some(LoadError::SectionOutsideImage));
+    }
+
+    #[test]
+    fn got_outside_init_data() {
+        let mut header = header();
+        header[6] = 12;
+        assert_eq!(parse(&header).err(), Some(LoadError::SectionOutsideImage));
+    }
+
+    #[test]
+    fn entry_point_outside_text() {
+        let mut header = header();
+        header[2] = 4;
+        assert_eq!(parse(&header).err(), Some(LoadError::SectionOutsideImage));
+    }
+
+    #[test]
+    fn bss_overlaps_init_data() {
+        let mut header = header();
+        header[7] = 4;
+        assert_eq!(parse(&header).err(), Some(LoadError::BssOutsideMemory));
+    }
+
+    #[test]
+    fn bss_ends_before_it_starts() {
+        let mut header = header();
+        header[8] = 4;
+        header[7] = 8;
+        assert_eq!(parse(&header).err(), Some(LoadError::BssOutsideMemory));
+    }
+
+    #[test]
+    fn bss_outside_memory() {
+        let image = image(&header(), &[], 64);
+        let load_info = LoadInfo::parse(&image).unwrap();
+        let mut memory = [0; 8];
+        assert_eq!(load(&load_info, &image, &mut memory, 0, 0).err(),
+                   Some(LoadError::BssOutsideMemory));
+    }
+
+    #[test]
+    fn misaligned_entry_point() {
+        let mut header = header();
+        header[0] = 128;
+        header[3] = 8;
+        header[2] = 2;
+        let image = image(&header, &[], 128);
+        assert_eq!(LoadInfo::parse(&image).err(),
+                   Some(LoadError::MisalignedEntryPoint));
+    }
+
+    #[test]
+    fn thumb_bit_ignored_in_entry_point() {
+        let mut header = header();
+        header[2] = 1;
+        assert!(parse(&header).is_ok());
+    }
+
+    #[test]
+    fn relocation_outside_init_data() {
+        let mut header = header();
+        header[1] = 8;
+        // The relocated word would end past the 8 bytes of initialized data.
+        let image = image(&header, &[6, 0x17], 64);
+        assert_eq!(LoadInfo::parse(&image).err(),
+                   Some(LoadError::SectionOutsideImage));
+    }
+
+    #[test]
+    fn relocation_offset_overflows() {
+        let mut header = header();
+        header[1] = 8;
+        let image = image(&header, &[u32::max_value(), 0x17], 64);
+        assert_eq!(LoadInfo::parse(&image).err(),
+                   Some(LoadError::SectionOutsideImage));
+    }
+
+    #[test]
+    fn load_patches_got_and_relocations() {
+        let header = [128, // total_size
+                      16, // rel_data_size
+                      0, // entry_loc
+                      4, // init_data_loc
+                      16, // init_data_size
+                      0, // got_start_offset
+                      8, // got_end_offset
+                      16, // bss_start_offset
+                      24, // bss_end_offset
+                      128, // min_stack_len
+                      0, // min_app_heap_len
+                      0, // min_kernel_heap_len
+                      0 /* permissions_len */];
+        let body = [// .rel.data: relocate both data words
+                    8,
+                    0x17,
+                    12,
+                    0x17,
+                    // .text
+                    0xbf00bf00,
+                    // GOT: a memory relative and a text relative entry
+                    0x10,
+                    TEXT_RELATIVE | 0x2,
+                    // .data: a text relative and a memory relative word
+                    TEXT_RELATIVE,
+                    0x20];
+        let image = image(&header, &body, 128);
+        let load_info = LoadInfo::parse(&image).unwrap();
+        assert_eq!(load_info.text_offset(), HEADER_LEN + 16);
+
+        let mut memory = [0xaa; 32];
+        load(&load_info, &image, &mut memory, 0x20000000, 0x10000).unwrap();
+        assert_eq!(read_word(&memory, 0), 0x20000010);
+        assert_eq!(read_word(&memory, 4), 0x10002);
+        assert_eq!(read_word(&memory, 8), 0x10000);
+        assert_eq!(read_word(&memory, 12), 0x20000020);
+        // BSS is zeroed and the rest of memory is left alone.
+        assert_eq!(&memory[16..24], &[0; 8]);
+        assert_eq!(&memory[24..], &[0xaa; 8]);
+    }
+
+    #[test]
+    fn permission_list_precedes_relocations() {
+        let mut header = header();
+        header[0] = 128;
+        header[1] = 8;
+        header[12] = 1;
+        let body = [3, ALL_COMMANDS, 4, 0x17, 0xbf00bf00, TEXT_RELATIVE | 0x8, 0];
+        let image = image(&header, &body, 128);
+        let load_info = LoadInfo::parse(&image).unwrap();
+        assert_eq!(load_info.text_offset(), HEADER_LEN + 16);
+
+        let mut memory = [0; 16];
+        load(&load_info, &image, &mut memory, 0x20000000, 0x10000).unwrap();
+        assert_eq!(read_word(&memory, 0), 0x10008);
+        assert_eq!(read_word(&memory, 4), 0x20000000);
+    }
+
+    #[test]
+    fn empty_permission_list_allows_nothing() {
+        let image = image(&header(), &[], 64);
+        let permissions = LoadInfo::parse(&image).unwrap().permissions(&image);
+        assert!(!permissions.allows_driver(0));
+        assert!(!permissions.allows_command(0, 0));
+    }
+
+    #[test]
+    fn permission_list_limits_drivers_and_commands() {
+        let mut header = header();
+        header[0] = 128;
+        header[12] = 2;
+        let image = image(&header, &[1, 0b101, 3, ALL_COMMANDS], 128);
+        let permissions = LoadInfo::parse(&image).unwrap().permissions(&image);
+        assert!(permissions.allows_driver(1));
+        assert!(permissions.allows_driver(3));
+        assert!(!permissions.allows_driver(2));
+
+        assert!(permissions.allows_command(1, 0));
+        assert!(!permissions.allows_command(1, 1));
+        assert!(permissions.allows_command(1, 2));
+        assert!(!permissions.allows_command(1, 40));
+        assert!(permissions.allows_command(3, 40));
+        assert!(!permissions.allows_command(2, 0));
+    }
+
+    #[test]
+    fn min_memory_len_overflows() {
+        let load_info = parse(&header()).unwrap();
+        assert_eq!(load_info.min_memory_len(usize::max_value()), None);
+    }
+}
@@ -10,6 +10,9 @@ use process;
 pub struct Private;
 pub struct Shared;
 
+/// A pointer into an app's memory. Dropping it leaves the memory alone:
+/// container memory is freed by `Owned`, and memory an app allowed belongs to
+/// the app.
 pub struct AppPtr<L, T> {
     ptr: Unique<T>,
     process: AppId,
@@ -40,17 +43,6 @@ impl<L, T> DerefMut for AppPtr<L, T> {
     }
 }
 
-impl<L, T> Drop for AppPtr<L, T> {
-    fn drop(&mut self) {
-        unsafe {
-            let ps = &mut process::PROCS;
-            if ps.len() < self.process.idx() {
-                ps[self.process.idx()].as_mut().map(|process| process.free(self.ptr.get_mut()));
-            }
-        }
-    }
-}
-
 pub struct AppSlice<L, T> {
     ptr: AppPtr<L, T>,
     len: usize,
@@ -64,19 +56,32 @@ impl<L, T> AppSlice<L, T> {
         }
     }
 
+    /// The length of the slice, or zero if its process is no longer live.
     pub fn len(&self) -> usize {
-        self.len
+        if self.is_live() { self.len } else { 0 }
+    }
+
+    /// The address of the start of the slice in the app's memory.
+    pub fn ptr(&self) -> *const T {
+        unsafe { self.ptr.ptr.get() as *const T }
+    }
+
+    /// Whether the process that shared this slice is still the one running in
+    /// its slot. Once it restarts or exits the slice is no longer accessible.
+    pub fn is_live(&self) -> bool {
+        unsafe { process::live_process(self.ptr.process).is_some() }
     }
 }
 
 impl<L, T> AsRef<[T]> for AppSlice<L, T> {
     fn as_ref(&self) -> &[T] {
-        unsafe { slice::from_raw_parts(self.ptr.ptr.get(), self.len) }
+        unsafe { slice::from_raw_parts(self.ptr.ptr.get(), self.len()) }
     }
 }
 
 impl<L, T> AsMut<[T]> for AppSlice<L, T> {
     fn as_mut(&mut self) -> &mut [T] {
-        unsafe { slice::from_raw_parts_mut(self.ptr.ptr.get_mut(), self.len) }
+        let len = self.len();
+        unsafe { slice::from_raw_parts_mut(self.ptr.ptr.get_mut(), len) }
     }
 }
@@ -0,0 +1,222 @@
+//! Memory protection regions.
+//!
+//! A `Region` describes a block of memory processes may access and how. The
+//! Cortex-M MPU only supports regions whose size is a power of two, at least
+//! 32 bytes, and whose base is aligned to their size. Regions of 256 bytes or
+//! more are split into eight equal subregions, each of which can be disabled.
+//! `Region::covering` finds the smallest such region containing a range of
+//! memory, disabling the subregions that lie outside it.
+
+/// The smallest region the MPU supports.
+pub const MIN_REGION_SIZE: usize = 32;
+
+/// The smallest region that is split into subregions.
+pub const MIN_SUBREGION_REGION_SIZE: usize = 256;
+
+/// Number of subregions in a region.
+pub const NUM_SUBREGIONS: usize = 8;
+
+/// Access processes have to a region. The kernel can always read and write
+/// process memory.
+#[derive(Copy,Clone,Debug,PartialEq,Eq)]
+pub enum Permissions {
+    ReadWriteExecute,
+    ReadWriteOnly,
+    ReadExecuteOnly,
+    ReadOnly,
+
+    /// Processes cannot access the region at all. Used to take away access
+    /// granted by a lower-numbered region.
+    NoAccess,
+}
+
+#[derive(Copy,Clone,Debug,PartialEq,Eq)]
+pub struct Region {
+    base: usize,
+    size: usize,
+    permissions: Permissions,
+
+    /// Bit `i` disables subregion `i`, counting up from the base.
+    subregion_mask: u8,
+}
+
+impl Region {
+    /// Returns a region of `size` bytes at `base` with all subregions
+    /// enabled, or `None` if the MPU cannot protect such a region.
+    pub fn new(base: usize, size: usize, permissions: Permissions) -> Option<Region> {
+        if size < MIN_REGION_SIZE || !size.is_power_of_two() || base % size != 0 {
+            return None;
+        }
+        Some(Region {
+            base: base,
+            size: size,
+            permissions: permissions,
+            subregion_mask: 0,
+        })
+    }
+
+    /// Returns the smallest region containing the `len` bytes at `start`,
+    /// with the subregions that hold none of those bytes disabled. Returns
+    /// `None` if `len` is zero or no region can contain the range.
+    pub fn covering(start: usize, len: usize, permissions: Permissions) -> Option<Region> {
+        if len == 0 {
+            return None;
+        }
+        // Work with the last byte rather than the end, which may not fit in a
+        // `usize` at the top of the address space.
+        let last = match start.checked_add(len - 1) {
+            Some(last) => last,
+            None => return None,
+        };
+        let mut size = match len.checked_next_power_of_two() {
+            Some(size) if size < MIN_REGION_SIZE => MIN_REGION_SIZE,
+            Some(size) => size,
+            None => return None,
+        };
+        loop {
+            let base = start & !(size - 1);
+            if last - base < size {
+                let region = Region {
+                    base: base,
+                    size: size,
+                    permissions: permissions,
+                    subregion_mask: 0,
+                };
+                return Some(region.with_unused_subregions_disabled(start, last));
+            }
+            size = match size.checked_mul(2) {
+                Some(size) => size,
+                None => return None,
+            };
+        }
+    }
+
+    /// Returns the region with the subregions in `mask` disabled, or `None`
+    /// if the region is too small to have subregions.
+    pub fn with_subregion_mask(self, mask: u8) -> Option<Region> {
+        if mask != 0 && self.size < MIN_SUBREGION_REGION_SIZE {
+            return None;
+        }
+        Some(Region { subregion_mask: mask, ..self })
+    }
+
+    /// Disables the subregions holding no bytes between `first` and `last`.
+    fn with_unused_subregions_disabled(self, first: usize, last: usize) -> Region {
+        if self.size < MIN_SUBREGION_REGION_SIZE {
+            return self;
+        }
+        let subregion_size = self.subregion_size();
+        let mut mask = 0;
+        for i in 0..NUM_SUBREGIONS {
+            let subregion_first = self.base + i * subregion_size;
+            let subregion_last = subregion_first + (subregion_size - 1);
+            if subregion_last < first || subregion_first > last {
+                mask |= 1 << i;
+            }
+        }
+        Region { subregion_mask: mask, ..self }
+    }
+
+    pub fn base(&self) -> usize {
+        self.base
+    }
+
+    /// Size of the region in bytes, including disabled subregions.
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    pub fn permissions(&self) -> Permissions {
+        self.permissions
+    }
+
+    pub fn subregion_mask(&self) -> u8 {
+        self.subregion_mask
+    }
+
+    /// Size of each of the region's subregions in bytes.
+    pub fn subregion_size(&self) -> usize {
+        self.size / NUM_SUBREGIONS
+    }
+
+    /// Whether processes can access `addr` through the region.
+    pub fn contains(&self, addr: usize) -> bool {
+        if addr < self.base || addr - self.base >= self.size {
+            return false;
+        }
+        if self.size < MIN_SUBREGION_REGION_SIZE {
+            return true;
+        }
+        let subregion = (addr - self.base) / self.subregion_size();
+        self.subregion_mask & (1 << subregion) == 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RW: Permissions = Permissions::ReadWriteOnly;
+
+    #[test]
+    fn aligned_power_of_two_range() {
+        let region = Region::covering(0x2000, 0x1000, RW).unwrap();
+        assert_eq!(region.base(), 0x2000);
+        assert_eq!(region.size(), 0x1000);
+        assert_eq!(region.subregion_mask(), 0);
+        assert_eq!(Region::new(0x2000, 0x1000, RW), Some(region));
+    }
+
+    #[test]
+    fn range_straddling_alignment_boundary() {
+        // 32 bytes either side of 0x200 need a region aligned below both.
+        let region = Region::covering(0x1f0, 0x20, RW).unwrap();
+        assert_eq!(region.base(), 0);
+        assert_eq!(region.size(), 0x400);
+        assert!(region.contains(0x1f0));
+        assert!(region.contains(0x20f));
+    }
+
+    #[test]
+    fn small_range_has_no_subregions() {
+        let region = Region::covering(0x1030, 0x30, RW).unwrap();
+        assert_eq!(region.base(), 0x1000);
+        assert_eq!(region.size(), 0x80);
+        assert_eq!(region.subregion_mask(), 0);
+        assert!(region.contains(0x1000));
+        assert!(region.contains(0x107f));
+        assert_eq!(region.with_subregion_mask(1), None);
+    }
+
+    #[test]
+    fn range_at_top_of_address_space() {
+        let top = usize::max_value();
+        let region = Region::covering(top - 31, 32, RW).unwrap();
+        assert_eq!(region.base(), top - 31);
+        assert_eq!(region.size(), 32);
+        assert!(region.contains(top));
+
+        assert_eq!(Region::covering(top - 15, 32, RW), None);
+        // Covering a range across the middle of the address space would take
+        // a region as large as the address space itself.
+        assert_eq!(Region::covering(top / 2, 2, RW), None);
+    }
+
+    #[test]
+    fn partial_cover_disables_unused_subregions() {
+        // Only subregions 3 (0x180..0x200) and 4 (0x200..0x280) hold any of
+        // the range.
+        let region = Region::covering(0x1f0, 0x20, RW).unwrap();
+        assert_eq!(region.subregion_size(), 0x80);
+        assert_eq!(region.subregion_mask(), 0b11100111);
+        assert!(!region.contains(0x17f));
+        assert!(region.contains(0x180));
+        assert!(region.contains(0x27f));
+        assert!(!region.contains(0x280));
+    }
+
+    #[test]
+    fn empty_range() {
+        assert_eq!(Region::covering(0x1000, 0, RW), None);
+    }
+}
@@ -1,4 +1,5 @@
 use driver::Driver;
+use mpu::Region;
 
 pub trait Platform {
     fn with_driver<F, R>(&mut self, driver_num: usize, f: F) -> R
@@ -20,24 +21,22 @@ pub trait MPU {
     /// map.
     fn enable_mpu(&self);
 
-    /// Sets the base address, size and access attributes of the given MPU
-    /// region number.
-    ///
-    /// `region_num`: an MPU region number 0-7
-    /// `start_addr`: the region base address. Lower bits will be masked
-    ///               according to the region size.
-    /// `len`       : region size as a function 2^(len + 1)
-    /// `execute`   : whether to enable code execution from this region
-    /// `ap`        : access permissions as defined in Table 4.47 of the user
-    ///               guide.
-    fn set_mpu(&self, region_num: u32, start_addr: u32, len: u32, execute: bool, ap: u32);
+    /// Programs the given MPU region number (0-7) to give processes access to
+    /// `region`.
+    fn set_region(&self, region_num: u32, region: Region);
+
+    /// Disables the given MPU region number so it no longer grants access to
+    /// any memory.
+    fn disable_region(&self, region_num: u32);
 }
 
 /// Noop implementation of MPU trait
 impl MPU for () {
     fn enable_mpu(&self) {}
 
-    fn set_mpu(&self, _: u32, _: u32, _: u32, _: bool, _: u32) {}
+    fn set_region(&self, _: u32, _: Region) {}
+
+    fn disable_region(&self, _: u32) {}
 }
 
 pub trait SysTick {
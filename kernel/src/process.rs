@@ -1,14 +1,24 @@
 use callback::AppId;
 use common::{RingBuffer, Queue};
+use loader::{self, DriverPermissions, LoadError, LoadInfo};
+use mpu::{self, Permissions, Region};
+use returncode::ReturnCode;
+use syscall;
 
 use container;
 use core::{mem, ptr, slice};
-use core::intrinsics::breakpoint;
+use core::fmt::Write;
 use core::ptr::{read_volatile, write_volatile};
 
 #[no_mangle]
 pub static mut SYSCALL_FIRED: usize = 0;
 
+/// Set by the architecture's fault handler when a CPU fault occurs while a
+/// process is running, so the kernel can attribute the fault to that process
+/// instead of panicking.
+#[no_mangle]
+pub static mut APP_FAULT: usize = 0;
+
 #[allow(improper_ctypes)]
 extern "C" {
     pub fn switch_to_user(user_stack: *const u8, mem_base: *const u8) -> *mut u8;
@@ -16,23 +26,58 @@ extern "C" {
 
 pub static mut PROCS: &'static mut [Option<Process<'static>>] = &mut [];
 
-pub fn schedule(callback: Callback, appid: AppId) -> bool {
-    let procs = unsafe { &mut PROCS };
-    let idx = appid.idx();
-    if idx >= procs.len() {
-        return false;
-    }
+/// Index of the process the scheduler is running or handling a system call
+/// for, if any.
+pub static mut CURRENT_PROCESS: Option<usize> = None;
 
-    match procs[idx] {
+pub fn schedule(callback: Callback, appid: AppId) -> bool {
+    match unsafe { live_process(appid) } {
         None => false,
-        Some(ref mut p) => {
-            // TODO(alevy): validate appid liveness
-
-            p.callbacks.enqueue(callback)
+        Some(p) => {
+            let enqueued = p.callbacks.enqueue(callback);
+            if !enqueued {
+                p.statistics.callbacks_dropped += 1;
+            }
+            enqueued
         }
     }
 }
 
+/// Returns the process `appid` refers to, or `None` if its slot is empty, or
+/// the process has exited or restarted since `appid` was handed out.
+pub unsafe fn live_process(appid: AppId) -> Option<&'static mut Process<'static>> {
+    PROCS.get_mut(appid.idx()).and_then(|p| p.as_mut()).and_then(|p| {
+        if p.generation == appid.generation() && p.state != State::Terminated {
+            Some(p)
+        } else {
+            None
+        }
+    })
+}
+
+/// Returns the id of the process currently in slot `idx`, if any.
+pub fn appid(idx: usize) -> Option<AppId> {
+    let procs = unsafe { &PROCS };
+    procs.get(idx)
+        .and_then(|p| p.as_ref().map(|process| unsafe { AppId::new(idx, process.generation) }))
+}
+
+/// Returns the process in slot `idx`, if any, whatever its state.
+pub unsafe fn process_at(idx: usize) -> Option<&'static mut Process<'static>> {
+    PROCS.get_mut(idx).and_then(|p| p.as_mut())
+}
+
+/// Returns the number of process slots.
+pub fn num_procs() -> usize {
+    unsafe { PROCS.len() }
+}
+
+/// Returns a copy of the runtime statistics of the process in slot `idx`.
+pub fn statistics(idx: usize) -> Option<Statistics> {
+    let procs = unsafe { &PROCS };
+    procs.get(idx).and_then(|p| p.as_ref().map(|process| process.statistics))
+}
+
 #[derive(Copy,Clone,PartialEq,Eq)]
 pub enum Error {
     NoSuchApp,
@@ -40,13 +85,101 @@ pub enum Error {
     AddressOutOfBounds,
 }
 
-#[derive(Copy,Clone,PartialEq,Eq)]
+impl From<Error> for ReturnCode {
+    fn from(err: Error) -> ReturnCode {
+        match err {
+            Error::NoSuchApp => ReturnCode::EINVAL,
+            Error::OutOfMemory => ReturnCode::ENOMEM,
+            Error::AddressOutOfBounds => ReturnCode::EINVAL,
+        }
+    }
+}
+
+#[derive(Copy,Clone,Debug,PartialEq,Eq)]
 pub enum State {
     Running,
     Yielded,
+
+    /// The process caused a fault and was stopped. It is not scheduled again.
+    Faulted,
+
+    /// The process exited. Its callbacks and container memory have been
+    /// released and it is not scheduled again.
+    Terminated,
+}
+
+/// What the kernel does when a process faults.
+#[derive(Copy,Clone,PartialEq,Eq)]
+pub enum FaultResponse {
+    /// Panic the kernel, taking down the whole board.
+    Panic,
+
+    /// Stop the process, leaving it in the `Faulted` state.
+    Stop,
+
+    /// Reload and restart the process from its image, at most the given
+    /// number of times. After that the process is stopped.
+    Restart(usize),
 }
 
 
+/// Runtime statistics kept for each process.
+#[derive(Copy,Clone,Default)]
+pub struct Statistics {
+    /// Number of system calls made, indexed by system call number.
+    pub syscalls: [usize; syscall::NUM_SYSCALLS],
+
+    /// Number of callbacks delivered to the process.
+    pub callbacks_delivered: usize,
+
+    /// Number of callbacks dropped because the callback queue was full.
+    pub callbacks_dropped: usize,
+
+    /// Number of times the process was preempted because its timeslice
+    /// expired.
+    pub timeslice_expirations: usize,
+
+    /// Total time the process has run, in microseconds, as measured by the
+    /// SysTick. A run longer than the SysTick can measure without a
+    /// timeslice counts as the longest it can.
+    pub cpu_time_us: u64,
+}
+
+/// Length of the no-access guard region below each process's stack. A stack
+/// that overflows into it faults instead of corrupting the app's data.
+pub const STACK_GUARD_LEN: usize = mpu::MIN_REGION_SIZE;
+
+/// Number of buffers other processes can share with a process over IPC at
+/// once. Each is mapped with its own MPU region.
+pub const NUM_SHARED_REGIONS: usize = 4;
+
+/// A buffer in another process's memory that a process has been given access
+/// to.
+#[derive(Copy,Clone)]
+struct SharedRegion {
+    owner: AppId,
+    start: usize,
+    len: usize,
+}
+
+impl SharedRegion {
+    /// Whether the process that shared the region still owns the memory.
+    fn is_live(&self) -> bool {
+        appid(self.owner.idx()) == Some(self.owner)
+    }
+}
+
+/// Number of buffers a process can have allowed to drivers at once.
+pub const NUM_ALLOWED_BUFFERS: usize = 16;
+
+/// A buffer a process has allowed a driver to use.
+#[derive(Copy,Clone)]
+struct AllowedBuffer {
+    driver_num: usize,
+    allow_num: usize,
+    end: usize,
+}
+
 #[derive(Copy,Clone,Debug)]
 pub struct Callback {
     pub r0: usize,
@@ -56,19 +189,6 @@ pub struct Callback {
     pub pc: usize,
 }
 
-#[repr(C)]
-struct LoadInfo {
-    total_size: usize, // Total padded size of the program image
-    rel_data_size: usize,
-    entry_loc: usize, // Entry point for user application
-    init_data_loc: usize, // Data initialization information in flash
-    init_data_size: usize, // Size of initialization information
-    got_start_offset: usize, // Offset to start of GOT
-    got_end_offset: usize, // Offset to end of GOT
-    bss_start_offset: usize, // Offset to start of BSS
-    bss_end_offset: usize, // Offset to end of BSS
-}
-
 pub struct Process<'a> {
     /// The process's memory.
     memory: &'static mut [u8],
@@ -76,17 +196,55 @@ pub struct Process<'a> {
     app_memory_break: *const u8,
     kernel_memory_break: *const u8,
 
+    /// The buffers the process has allowed drivers to use, by driver and
+    /// allow number. Drivers may hold on to them, so the app break cannot be
+    /// moved below the end of any of them.
+    allowed_buffers: [Option<AllowedBuffer>; NUM_ALLOWED_BUFFERS],
+
+    /// The kernel memory break before any container memory was allocated.
+    initial_kernel_memory_break: *const u8,
+
     /// Process text segment
     text: &'static [u8],
 
+    /// The drivers and commands the image says the process may use.
+    permissions: DriverPermissions<'static>,
+
     /// The offset in `memory` to use for the process stack.
     cur_stack: *const u8,
 
+    /// The lowest address the stack may grow down to, just above BSS.
+    stack_bottom: *const u8,
+
     yield_pc: usize,
     psr: usize,
 
     pub state: State,
 
+    /// Set when the process has been stopped from the kernel. A stopped
+    /// process keeps its state but is not scheduled until it is resumed.
+    stopped: bool,
+
+    /// How to respond when this process faults.
+    fault_response: FaultResponse,
+
+    /// Number of times this process has been restarted after a fault.
+    restart_count: usize,
+
+    /// Maximum number of callbacks that can be queued for this process.
+    /// Callbacks scheduled while the queue is full are dropped and counted
+    /// in `statistics.callbacks_dropped`.
+    callback_depth: usize,
+
+    pub statistics: Statistics,
+
+    /// Incremented each time the process restarts or exits, invalidating
+    /// `AppId`s handed out for the previous incarnation.
+    generation: usize,
+
+    /// Buffers other processes have shared with this one over IPC.
+    shared_regions: [Option<SharedRegion>; NUM_SHARED_REGIONS],
+
     pub callbacks: RingBuffer<'a, Callback>,
 }
 
@@ -99,19 +257,128 @@ impl<'a> Process<'a> {
         unsafe { self.memory.as_ptr().offset(self.memory.len() as isize) }
     }
 
-    pub fn memory_regions(&self) -> (usize, usize, usize, usize) {
-        let data_start = self.memory.as_ptr() as usize;
-        let data_len = 12;
+    pub fn app_memory_break(&self) -> *const u8 {
+        self.app_memory_break
+    }
+
+    pub fn kernel_memory_break(&self) -> *const u8 {
+        self.kernel_memory_break
+    }
+
+    pub fn flash_start(&self) -> *const u8 {
+        self.text.as_ptr()
+    }
+
+    pub fn flash_len(&self) -> usize {
+        self.text.len()
+    }
+
+    pub fn stack_bottom(&self) -> *const u8 {
+        self.stack_bottom
+    }
 
-        let text_start = self.text.as_ptr() as usize;
-        let text_len = ((32 - self.text.len().leading_zeros()) - 2) as usize;
-        (data_start, data_len, text_start, text_len)
+    /// The drivers and commands the process may use.
+    pub fn permissions(&self) -> DriverPermissions<'static> {
+        self.permissions
     }
 
+    /// The MPU region giving the app access to its memory. Subregions from
+    /// the app break up are disabled, so the app cannot touch the grant
+    /// region or callback queue above it.
+    pub fn data_region(&self) -> Option<Region> {
+        let start = self.mem_start() as usize;
+        Region::new(start, self.memory.len(), Permissions::ReadWriteExecute).and_then(|region| {
+            if region.size() < mpu::MIN_SUBREGION_REGION_SIZE {
+                return Some(region);
+            }
+            let enabled = (self.accessible_end(self.app_memory_break) - start) /
+                          region.subregion_size();
+            region.with_subregion_mask((0xffu32 << enabled) as u8)
+        })
+    }
+
+    /// The no-access MPU region directly below the stack.
+    pub fn stack_guard_region(&self) -> Option<Region> {
+        Region::new(self.stack_bottom as usize - STACK_GUARD_LEN,
+                    STACK_GUARD_LEN,
+                    Permissions::NoAccess)
+    }
+
+    /// The end of the memory the app can access with its break at
+    /// `app_break`. The data region can only end on a subregion boundary, so
+    /// this is the break rounded up to one. The kernel memory break is kept
+    /// at or above it.
+    fn accessible_end(&self, app_break: *const u8) -> usize {
+        let start = self.mem_start() as usize;
+        let len = self.memory.len();
+        if len < mpu::MIN_SUBREGION_REGION_SIZE {
+            return app_break as usize;
+        }
+        let subregion_size = len / mpu::NUM_SUBREGIONS;
+        let offset = app_break as usize - start;
+        start + (offset + subregion_size - 1) / subregion_size * subregion_size
+    }
+
+    /// Loads the app image at `start_addr` into memory taken from the front of
+    /// `memory_pool`.
+    ///
+    /// The process gets the smallest power-of-two sized, size-aligned block
+    /// that satisfies the minimum stack, heap and kernel heap sizes in the
+    /// image's header, so the block can be covered by a single MPU region.
+    /// Returns the process and the number of bytes of `memory_pool` used,
+    /// including alignment padding.
+    ///
+    /// The image header is checked before anything is written, so a corrupt
+    /// image is rejected with a `LoadError` rather than loaded.
     pub unsafe fn create(start_addr: *const u8,
                          length: usize,
-                         memory: &'static mut [u8])
-                         -> Process<'a> {
+                         memory_pool: &'static mut [u8],
+                         fault_response: FaultResponse,
+                         callback_depth: usize)
+                         -> Result<(Process<'a>, usize), LoadError> {
+        let image = slice::from_raw_parts(start_addr, length);
+        let load_info = try!(LoadInfo::parse(image));
+
+        let container_ptrs_size = read_volatile(&container::CONTAINER_COUNTER) *
+                                  mem::size_of::<*const usize>();
+        let callbacks_size = (callback_depth + 1) * mem::size_of::<Callback>();
+        // The stack guard also needs room to be aligned to its length.
+        let stack_guard_size = 2 * STACK_GUARD_LEN;
+        let kernel_len = callbacks_size + container_ptrs_size + stack_guard_size;
+        let memory_len = try!(load_info.min_memory_len(kernel_len)
+            .and_then(|len| {
+                // Leave a subregion spare, as the app can only be given access
+                // up to a subregion boundary past its break.
+                len.checked_next_power_of_two().and_then(|pow| {
+                    if len > pow - pow / mpu::NUM_SUBREGIONS {
+                        pow.checked_mul(2)
+                    } else {
+                        Some(pow)
+                    }
+                })
+            })
+            .ok_or(LoadError::NotEnoughMemory));
+
+        let pool_start = memory_pool.as_ptr() as usize;
+        let padding = (memory_len - (pool_start % memory_len)) % memory_len;
+        if padding.saturating_add(memory_len) > memory_pool.len() {
+            return Err(LoadError::NotEnoughMemory);
+        }
+
+        let memory = slice::from_raw_parts_mut(memory_pool.as_mut_ptr().offset(padding as isize),
+                                               memory_len);
+        let process = try!(Process::new(&load_info, image, memory, fault_response, callback_depth));
+        Ok((process, padding + memory_len))
+    }
+
+    /// Loads `image` into `memory` and sets the process up to run from its
+    /// entry point.
+    unsafe fn new(load_info: &LoadInfo,
+                  image: &'static [u8],
+                  memory: &'static mut [u8],
+                  fault_response: FaultResponse,
+                  callback_depth: usize)
+                  -> Result<Process<'a>, LoadError> {
         let mut kernel_memory_break = {
             // make room for container pointers
             let psz = mem::size_of::<*const usize>();
@@ -126,9 +393,10 @@ impl<'a> Process<'a> {
             res
         };
 
-        // Take callback buffer from of memory
-        let callback_size = mem::size_of::<Option<Callback>>();
-        let callback_len = 10;
+        // Take callback buffer from of memory. The ring buffer always keeps one
+        // slot empty, so allocate one more than the requested depth.
+        let callback_size = mem::size_of::<Callback>();
+        let callback_len = callback_depth + 1;
         let callback_offset = callback_len * callback_size;
         // Set kernel break to beginning of callback buffer
         kernel_memory_break = kernel_memory_break.offset(-(callback_offset as isize));
@@ -137,31 +405,220 @@ impl<'a> Process<'a> {
 
         let callbacks = RingBuffer::new(callback_buf);
 
-        let load_result = load(start_addr, memory.as_mut_ptr());
+        // Grant blocks are allocated downwards from here, keeping their
+        // alignment.
+        kernel_memory_break = (kernel_memory_break as usize & !(GRANT_ALIGN - 1)) as *mut u8;
 
-        let stack_bottom = load_result.app_mem_start.offset(512);
+        let mem_base = memory.as_mut_ptr();
+        let text_start = image.as_ptr().offset(load_info.text_offset() as isize);
+        try!(loader::load(load_info, image, memory, mem_base as usize, text_start as usize));
+
+        // The stack grows down towards the stack guard just past the end of
+        // BSS, and the app heap grows up from the top of the stack.
+        let app_mem_start = mem_base.offset(load_info.app_mem_offset() as isize) as *const u8;
+        let stack_guard = (app_mem_start as usize + STACK_GUARD_LEN - 1) & !(STACK_GUARD_LEN - 1);
+        let stack_bottom = (stack_guard + STACK_GUARD_LEN) as *const u8;
+        let stack_top = stack_bottom.offset(load_info.stack_len() as isize);
 
         let mut process = Process {
             memory: memory,
-            app_memory_break: stack_bottom,
+            app_memory_break: stack_top,
             kernel_memory_break: kernel_memory_break,
-            text: slice::from_raw_parts(start_addr, length),
-            cur_stack: stack_bottom,
+            allowed_buffers: [None; NUM_ALLOWED_BUFFERS],
+            initial_kernel_memory_break: kernel_memory_break,
+            text: image,
+            permissions: load_info.permissions(image),
+            cur_stack: stack_top,
+            stack_bottom: stack_bottom,
             yield_pc: 0,
             psr: 0x01000000,
             state: State::Yielded,
+            stopped: false,
+            fault_response: fault_response,
+            restart_count: 0,
+            callback_depth: callback_depth,
+            statistics: Default::default(),
+            generation: 0,
+            shared_regions: [None; NUM_SHARED_REGIONS],
             callbacks: callbacks,
         };
 
+        if process.accessible_end(stack_top) > kernel_memory_break as usize {
+            return Err(LoadError::NotEnoughMemory);
+        }
+
+        // Entry point is offset from app code
         process.callbacks.enqueue(Callback {
-            pc: load_result.init_fn,
-            r0: load_result.app_mem_start as usize,
+            pc: text_start as usize + load_info.entry_offset(),
+            r0: app_mem_start as usize,
             r1: process.app_memory_break as usize,
             r2: process.kernel_memory_break as usize,
             r3: 0,
         });
 
-        process
+        Ok(process)
+    }
+
+    /// Whether the process has work to do: it is running, or it has yielded
+    /// and callbacks are waiting for it, and it has not been stopped.
+    pub fn ready(&self) -> bool {
+        if self.stopped {
+            return false;
+        }
+        match self.state {
+            State::Running => true,
+            State::Yielded => self.callbacks.has_elements(),
+            State::Faulted | State::Terminated => false,
+        }
+    }
+
+    /// Handles a fault in this process according to its `FaultResponse`.
+    pub unsafe fn fault_state(&mut self) {
+        write_volatile(&mut APP_FAULT, 0);
+        match self.fault_response {
+            FaultResponse::Panic => {
+                panic!("Process faulted (restarted {} times)", self.restart_count);
+            }
+            FaultResponse::Stop => {
+                self.state = State::Faulted;
+            }
+            FaultResponse::Restart(max_restarts) => {
+                if self.restart_count < max_restarts {
+                    let restart_count = self.restart_count + 1;
+                    self.reload(restart_count);
+                } else {
+                    self.state = State::Faulted;
+                }
+            }
+        }
+    }
+
+    /// Reloads the process image into its memory and starts it again from its
+    /// entry point. All pending callbacks and container state are discarded.
+    ///
+    /// This is for restarts asked for from outside the process, such as from
+    /// the process console. They do not count towards the restarts its
+    /// `FaultResponse` allows.
+    pub unsafe fn restart(&mut self) {
+        let restart_count = self.restart_count;
+        self.reload(restart_count);
+    }
+
+    /// Restarts the process, recording `restart_count` restarts after faults.
+    unsafe fn reload(&mut self, restart_count: usize) {
+        let statistics = self.statistics;
+        let generation = self.generation.wrapping_add(1);
+        let memory = slice::from_raw_parts_mut(self.memory.as_mut_ptr(), self.memory.len());
+        // The image was validated when the process was first created, so it
+        // only fails to load again if flash has changed underneath us.
+        let process = match LoadInfo::parse(self.text) {
+            Ok(load_info) => {
+                Process::new(&load_info,
+                             self.text,
+                             memory,
+                             self.fault_response,
+                             self.callback_depth)
+            }
+            Err(err) => Err(err),
+        };
+        match process {
+            Ok(process) => *self = process,
+            Err(_) => {
+                self.state = State::Faulted;
+                return;
+            }
+        }
+        self.restart_count = restart_count;
+        self.statistics = statistics;
+        self.generation = generation;
+    }
+
+    /// Terminates the process. Pending callbacks are dropped and all container
+    /// memory is released, so drivers can no longer schedule callbacks into or
+    /// enter containers for this process.
+    pub unsafe fn terminate(&mut self) {
+        self.state = State::Terminated;
+        self.generation = self.generation.wrapping_add(1);
+
+        while self.callbacks.dequeue().is_some() {}
+
+        let num_ctrs = read_volatile(&container::CONTAINER_COUNTER);
+        for ctr in 0..num_ctrs {
+            write_volatile(self.container_for::<u8>(ctr), ptr::null_mut());
+        }
+        self.kernel_memory_break = self.initial_kernel_memory_break;
+    }
+
+    /// Stops scheduling the process until `resume` is called.
+    pub fn stop(&mut self) {
+        self.stopped = true;
+    }
+
+    /// Lets a stopped process be scheduled again, from where it stopped.
+    pub fn resume(&mut self) {
+        self.stopped = false;
+    }
+
+    pub fn is_stopped(&self) -> bool {
+        self.stopped
+    }
+
+    /// Number of callbacks queued for the process.
+    pub fn pending_callbacks(&self) -> usize {
+        self.callbacks.len()
+    }
+
+    pub fn restart_count(&self) -> usize {
+        self.restart_count
+    }
+
+    pub fn generation(&self) -> usize {
+        self.generation
+    }
+
+    pub fn callback_depth(&self) -> usize {
+        self.callback_depth
+    }
+
+    /// Gives this process access to the `len` bytes at `start`, a buffer
+    /// `owner` shares with it, replacing any buffer `owner` shared before.
+    /// Returns false if every shared region is in use by another live
+    /// process.
+    pub fn add_shared_region(&mut self, owner: AppId, start: usize, len: usize) -> bool {
+        let slot = self.shared_regions
+            .iter()
+            .position(|r| r.map_or(false, |r| r.owner.idx() == owner.idx()))
+            .or_else(|| self.shared_regions.iter().position(|r| r.map_or(true, |r| !r.is_live())));
+        match slot {
+            Some(i) => {
+                self.shared_regions[i] = Some(SharedRegion {
+                    owner: owner,
+                    start: start,
+                    len: len,
+                });
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Takes away access to the buffer `owner` shared with this process, if
+    /// any.
+    pub fn remove_shared_region(&mut self, owner: AppId) {
+        for region in self.shared_regions.iter_mut() {
+            if region.map_or(false, |r| r.owner.idx() == owner.idx()) {
+                *region = None;
+            }
+        }
+    }
+
+    /// The start and length of shared region `i`, if it is in use and the
+    /// process that shared it is still live.
+    pub fn shared_region(&self, i: usize) -> Option<(usize, usize)> {
+        self.shared_regions
+            .get(i)
+            .and_then(|r| *r)
+            .and_then(|r| if r.is_live() { Some((r.start, r.len)) } else { None })
     }
 
     pub fn sbrk(&mut self, increment: isize) -> Result<*const u8, Error> {
@@ -170,9 +627,10 @@ impl<'a> Process<'a> {
     }
 
     pub fn brk(&mut self, new_break: *const u8) -> Result<*const u8, Error> {
-        if new_break < self.mem_start() || new_break >= self.mem_end() {
+        if new_break < self.mem_start() || new_break >= self.mem_end() ||
+           (new_break as usize) < self.allowed_end() {
             Err(Error::AddressOutOfBounds)
-        } else if new_break > self.kernel_memory_break {
+        } else if self.accessible_end(new_break) > self.kernel_memory_break as usize {
             Err(Error::OutOfMemory)
         } else {
             let old_break = self.app_memory_break;
@@ -181,24 +639,163 @@ impl<'a> Process<'a> {
         }
     }
 
+    /// Whether the `size` bytes at `buf_start_addr` belong to the app, lying
+    /// between the start of its memory and its break. Memory above the break
+    /// holds the grant region and callback queue and is never exposed.
     pub fn in_exposed_bounds(&self, buf_start_addr: *const u8, size: usize) -> bool {
+        range_within(self.mem_start() as usize,
+                     self.app_memory_break as usize,
+                     buf_start_addr as usize,
+                     size)
+    }
 
-        let buf_end_addr = unsafe { buf_start_addr.offset(size as isize) };
+    /// Whether `expose` has room to record a buffer allowed to driver
+    /// `driver_num` as `allow_num`.
+    pub fn can_expose(&self, driver_num: usize, allow_num: usize) -> bool {
+        self.allowed_slot(driver_num, allow_num).is_some()
+    }
 
-        buf_start_addr >= self.mem_start() && buf_end_addr <= self.mem_end()
+    /// Records that the `size` bytes at `buf_start_addr` have been allowed to
+    /// driver `driver_num` as `allow_num`, replacing the buffer allowed there
+    /// before, so that the break is never moved below them. Returns false if
+    /// `NUM_ALLOWED_BUFFERS` other buffers are already allowed.
+    pub fn expose(&mut self,
+                  driver_num: usize,
+                  allow_num: usize,
+                  buf_start_addr: *const u8,
+                  size: usize)
+                  -> bool {
+        match self.allowed_slot(driver_num, allow_num) {
+            Some(i) => {
+                self.allowed_buffers[i] = Some(AllowedBuffer {
+                    driver_num: driver_num,
+                    allow_num: allow_num,
+                    end: buf_start_addr as usize + size,
+                });
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Forgets the buffer allowed to driver `driver_num` as `allow_num`, once
+    /// the driver has given it back, so the break can move below it again.
+    pub fn unexpose(&mut self, driver_num: usize, allow_num: usize) {
+        for buffer in self.allowed_buffers.iter_mut() {
+            if buffer.map_or(false,
+                             |b| b.driver_num == driver_num && b.allow_num == allow_num) {
+                *buffer = None;
+            }
+        }
     }
 
+    /// The slot holding the buffer allowed to driver `driver_num` as
+    /// `allow_num`, or else an empty one.
+    fn allowed_slot(&self, driver_num: usize, allow_num: usize) -> Option<usize> {
+        self.allowed_buffers
+            .iter()
+            .position(|b| {
+                b.map_or(false, |b| b.driver_num == driver_num && b.allow_num == allow_num)
+            })
+            .or_else(|| self.allowed_buffers.iter().position(|b| b.is_none()))
+    }
+
+    /// The end of the highest buffer allowed to a driver, or 0 if there is
+    /// none.
+    fn allowed_end(&self) -> usize {
+        self.allowed_buffers.iter().filter_map(|b| b.map(|b| b.end)).max().unwrap_or(0)
+    }
+
+    /// Allocates `size` bytes of container memory from the grant region.
+    ///
+    /// The grant region runs from the kernel memory break up to where it was
+    /// when the process was created, and is made of contiguous blocks, each
+    /// starting with a `GrantBlock` header. A free block that is large enough
+    /// is reused first, and split if the rest is large enough for another
+    /// block. Otherwise the kernel memory break is lowered to make room for a
+    /// new block.
     pub unsafe fn alloc(&mut self, size: usize) -> Option<&mut [u8]> {
-        let new_break = self.kernel_memory_break.offset(-(size as isize));
-        if new_break < self.app_memory_break {
+        let block_len = match GrantBlock::len_for(size) {
+            Some(len) => len,
+            None => return None,
+        };
+
+        let mut cur = self.kernel_memory_break as *mut GrantBlock;
+        while (cur as *const u8) < self.initial_kernel_memory_break {
+            let block = &mut *cur;
+            if block.free && block.len >= block_len {
+                if block.len - block_len >= GrantBlock::header_len() + GRANT_ALIGN {
+                    let rest = (cur as *mut u8).offset(block_len as isize) as *mut GrantBlock;
+                    ptr::write(rest,
+                               GrantBlock {
+                                   len: block.len - block_len,
+                                   free: true,
+                               });
+                    block.len = block_len;
+                }
+                block.free = false;
+                return Some(block.data(size));
+            }
+            cur = block.next();
+        }
+
+        // Blocks must stay above the memory the app can access.
+        let available = (self.kernel_memory_break as usize)
+            .saturating_sub(self.accessible_end(self.app_memory_break));
+        if block_len > available {
             None
         } else {
-            self.kernel_memory_break = new_break;
-            Some(slice::from_raw_parts_mut(new_break as *mut u8, size))
+            self.kernel_memory_break = self.kernel_memory_break.offset(-(block_len as isize));
+            let block = self.kernel_memory_break as *mut GrantBlock;
+            ptr::write(block,
+                       GrantBlock {
+                           len: block_len,
+                           free: false,
+                       });
+            Some((*block).data(size))
         }
     }
 
-    pub unsafe fn free<T>(&mut self, _: *mut T) {}
+    /// Returns memory allocated with `alloc` to the grant region.
+    ///
+    /// Only container memory may be passed here. The check that `data` is
+    /// the start of a block cannot tell a block apart from app memory the
+    /// app has lined up with one after moving its break, so pointers into
+    /// memory an app allowed must never be freed. Adjacent free blocks are merged, and
+    /// the kernel memory break is raised past free blocks at the bottom of
+    /// the region so the app can grow its heap into that memory again.
+    pub unsafe fn free<T>(&mut self, data: *mut T) {
+        let data = data as *const u8;
+        if data < self.kernel_memory_break || data >= self.initial_kernel_memory_break {
+            return;
+        }
+
+        let end = self.initial_kernel_memory_break as *mut GrantBlock;
+        let mut cur = self.kernel_memory_break as *mut GrantBlock;
+        while cur < end {
+            if (*cur).data_ptr() == data {
+                (*cur).free = true;
+                break;
+            }
+            cur = (*cur).next();
+        }
+
+        // Merge runs of free blocks.
+        let mut cur = self.kernel_memory_break as *mut GrantBlock;
+        while cur < end {
+            let next = (*cur).next();
+            if (*cur).free && next < end && (*next).free {
+                (*cur).len += (*next).len;
+            } else {
+                cur = next;
+            }
+        }
+
+        let lowest = self.kernel_memory_break as *mut GrantBlock;
+        if lowest < end && (*lowest).free {
+            self.kernel_memory_break = (*lowest).next() as *const u8;
+        }
+    }
 
     pub unsafe fn container_for<T>(&mut self, container_num: usize) -> *mut *mut T {
         let container_num = container_num as isize;
@@ -213,7 +810,7 @@ impl<'a> Process<'a> {
         if (*ctr_ptr).is_null() {
             self.alloc(mem::size_of::<T>()).map(|root_arr| {
                 let root_ptr = root_arr.as_mut_ptr() as *mut T;
-                *root_ptr = Default::default();
+                ptr::write(root_ptr, Default::default());
                 write_volatile(ctr_ptr, root_ptr);
                 root_ptr
             })
@@ -255,12 +852,21 @@ impl<'a> Process<'a> {
         read_volatile(&SYSCALL_FIRED) != 0
     }
 
+    /// Whether the process faulted the last time it ran.
+    pub unsafe fn app_fault(&self) -> bool {
+        read_volatile(&APP_FAULT) != 0
+    }
+
     /// Context switch to the process.
     pub unsafe fn switch_to(&mut self) {
-        if self.cur_stack < self.memory.as_ptr() {
-            breakpoint();
-        }
         write_volatile(&mut SYSCALL_FIRED, 0);
+        if self.cur_stack < self.stack_bottom {
+            // The process overflowed its stack. Treat it like a CPU fault
+            // rather than switching to a corrupted stack.
+            write_volatile(&mut APP_FAULT, 1);
+            return;
+        }
+        write_volatile(&mut APP_FAULT, 0);
         let psp = switch_to_user(self.cur_stack, self.memory.as_ptr());
         self.cur_stack = psp;
     }
@@ -304,81 +910,165 @@ impl<'a> Process<'a> {
         let pspr = self.cur_stack as *const usize;
         unsafe { read_volatile(pspr.offset(3)) }
     }
+
+    /// Writes the process's state, memory layout, saved registers and queued
+    /// callbacks, for panic output.
+    pub unsafe fn dump<W: Write>(&self, writer: &mut W) {
+        let _ = write!(writer,
+                       "\tState: {:?}{}, restarted {} times\r\n",
+                       self.state,
+                       if self.stopped { " (stopped)" } else { "" },
+                       self.restart_count);
+        let _ = write!(writer,
+                       "\tFlash  0x{:08x}-0x{:08x}\r\n\
+                        \tMemory 0x{:08x}-0x{:08x}\r\n\
+                        \t  app break 0x{:08x}, kernel break 0x{:08x}, \
+                        stack bottom 0x{:08x}\r\n",
+                       self.text.as_ptr() as usize,
+                       self.text.as_ptr() as usize + self.text.len(),
+                       self.mem_start() as usize,
+                       self.mem_end() as usize,
+                       self.app_memory_break as usize,
+                       self.kernel_memory_break as usize,
+                       self.stack_bottom as usize);
+
+        // A process that is running or faulted was last switched out by an
+        // exception, which left r0-r3, r12, lr, pc and xPSR at the top of its
+        // stack and r4-r11 just below. A yielded process has had its frame
+        // popped, so only the address it yielded at is known.
+        let sp = self.cur_stack as *const usize;
+        let frame_in_memory = sp.offset(-8) as *const u8 >= self.mem_start() &&
+                              sp.offset(8) as *const u8 <= self.mem_end();
+        match self.state {
+            State::Running | State::Faulted if frame_in_memory => {
+                let reg = |i| read_volatile(sp.offset(i));
+                let _ = write!(writer,
+                               "\tr0  0x{:08x}  r1  0x{:08x}  r2  0x{:08x}  r3  0x{:08x}\r\n\
+                                \tr4  0x{:08x}  r5  0x{:08x}  r6  0x{:08x}  r7  0x{:08x}\r\n\
+                                \tr8  0x{:08x}  r9  0x{:08x}  r10 0x{:08x}  r11 0x{:08x}\r\n\
+                                \tr12 0x{:08x}  sp  0x{:08x}  lr  0x{:08x}  pc  0x{:08x}\r\n\
+                                \txPSR 0x{:08x}\r\n",
+                               reg(0),
+                               reg(1),
+                               reg(2),
+                               reg(3),
+                               reg(-8),
+                               reg(-7),
+                               reg(-6),
+                               reg(-5),
+                               reg(-4),
+                               reg(-3),
+                               reg(-2),
+                               reg(-1),
+                               reg(4),
+                               sp as usize,
+                               reg(5),
+                               reg(6),
+                               reg(7));
+            }
+            State::Yielded => {
+                let _ = write!(writer,
+                               "\tsp  0x{:08x}  yielded at pc 0x{:08x}\r\n",
+                               sp as usize,
+                               self.yield_pc);
+            }
+            _ => {
+                let _ = write!(writer, "\tsp  0x{:08x}\r\n", sp as usize);
+            }
+        }
+
+        let _ = write!(writer,
+                       "\t{} of {} callbacks queued\r\n",
+                       self.callbacks.len(),
+                       self.callback_depth);
+        for i in 0..self.callbacks.len() {
+            self.callbacks.get(i).map(|cb| {
+                let _ = write!(writer,
+                               "\t  pc 0x{:08x} (0x{:x}, 0x{:x}, 0x{:x}, 0x{:x})\r\n",
+                               cb.pc,
+                               cb.r0,
+                               cb.r1,
+                               cb.r2,
+                               cb.r3);
+            });
+        }
+    }
 }
 
-struct LoadResult {
-    text_start: *const u8,
-    text_len: usize,
-    init_fn: usize,
-    app_mem_start: *const u8,
+/// Whether the `size` bytes at `buf_start` lie between `start` and `end`,
+/// without overflowing at the top of the address space.
+fn range_within(start: usize, end: usize, buf_start: usize, size: usize) -> bool {
+    buf_start >= start && buf_start.checked_add(size).map_or(false, |buf_end| buf_end <= end)
 }
 
-unsafe fn load(start_addr: *const u8, mem_base: *mut u8) -> LoadResult {
-    let mut result = LoadResult {
-        text_start: start_addr as *const u8,
-        text_len: 0,
-        init_fn: 0,
-        app_mem_start: ptr::null(),
-    };
+/// Alignment of blocks in the grant region.
+const GRANT_ALIGN: usize = 8;
 
-    let load_info = &*(start_addr as *const LoadInfo);
+/// Header in front of each block allocated from the grant region.
+#[repr(C)]
+struct GrantBlock {
+    /// Length of the block, including this header.
+    len: usize,
+    free: bool,
+}
 
-    let rel_data_addr = start_addr.offset(mem::size_of::<LoadInfo>() as isize);
-    let rel_data: &[usize] = slice::from_raw_parts(rel_data_addr as *const usize,
-                                                   load_info.rel_data_size / 4);
+impl GrantBlock {
+    /// Space taken by the header, keeping the data after it aligned.
+    fn header_len() -> usize {
+        (mem::size_of::<GrantBlock>() + GRANT_ALIGN - 1) & !(GRANT_ALIGN - 1)
+    }
 
-    // Update text location in self
-    let text_start = rel_data_addr.offset(load_info.rel_data_size as isize);
-    result.text_start = text_start;
-    result.text_len = load_info.init_data_loc;
+    /// Length of a block holding `size` bytes, or `None` on overflow.
+    fn len_for(size: usize) -> Option<usize> {
+        size.checked_add(GrantBlock::header_len() + GRANT_ALIGN - 1)
+            .map(|len| len & !(GRANT_ALIGN - 1))
+    }
 
-    // Zero out BSS
-    ::core::intrinsics::write_bytes(mem_base.offset(load_info.bss_start_offset as isize),
-                                    0,
-                                    load_info.bss_end_offset - load_info.bss_start_offset);
+    unsafe fn data_ptr(&self) -> *const u8 {
+        (self as *const GrantBlock as *const u8).offset(GrantBlock::header_len() as isize)
+    }
 
-    // Copy data into Data section
-    let init_data: &[u8] =
-        slice::from_raw_parts(text_start.offset(load_info.init_data_loc as isize),
-                              load_info.init_data_size);
+    unsafe fn data(&mut self, size: usize) -> &mut [u8] {
+        slice::from_raw_parts_mut(self.data_ptr() as *mut u8, size)
+    }
 
-    let target_data: &mut [u8] = slice::from_raw_parts_mut(mem_base, load_info.init_data_size);
+    unsafe fn next(&self) -> *mut GrantBlock {
+        (self as *const GrantBlock as *const u8).offset(self.len as isize) as *mut GrantBlock
+    }
+}
 
-    target_data.clone_from_slice(init_data);
+#[cfg(test)]
+mod tests {
+    use super::range_within;
 
-    let fixup = |addr: *mut usize| {
-        let entry = *addr;
-        if (entry & 0x80000000) == 0 {
-            // Regular data (memory relative)
-            *addr = entry + (mem_base as usize);
-        } else {
-            // rodata or function pointer (code relative)
-            *addr = (entry ^ 0x80000000) + (text_start as usize);
-        }
-    };
+    // An app whose memory starts at 0x1000 with its break at 0x1800, below
+    // the grant region at 0x1c00..0x2000.
+    const MEM_START: usize = 0x1000;
+    const APP_BREAK: usize = 0x1800;
 
-    // Fixup Global Offset Table
-    let mut got_cur = mem_base.offset(load_info.got_start_offset as isize) as *mut usize;
-    let got_end = mem_base.offset(load_info.got_end_offset as isize) as *mut usize;
-    while got_cur != got_end {
-        fixup(got_cur);
-        got_cur = got_cur.offset(1);
+    #[test]
+    fn buffer_below_break() {
+        assert!(range_within(MEM_START, APP_BREAK, 0x1000, 0x800));
+        assert!(range_within(MEM_START, APP_BREAK, 0x1400, 0x10));
     }
 
-    // Fixup relocation data
-    for (i, addr) in rel_data.iter().enumerate() {
-        if i % 2 == 0 {
-            // Only the first of every 2 entries is an address
-            fixup(mem_base.offset(*addr as isize) as *mut usize);
-        }
+    #[test]
+    fn buffer_in_grant_memory() {
+        assert!(!range_within(MEM_START, APP_BREAK, 0x1c00, 0x10));
+        // Starting below the break does not make the rest of it the app's.
+        assert!(!range_within(MEM_START, APP_BREAK, 0x17f0, 0x420));
+        assert!(!range_within(MEM_START, APP_BREAK, 0x1800, 1));
     }
 
-    // Entry point is offset from app code
-    result.init_fn = text_start as usize + load_info.entry_loc;
-
-    let mut aligned_mem_start = load_info.bss_end_offset as isize;
-    aligned_mem_start += (8 - (aligned_mem_start % 8)) % 8;
-    result.app_mem_start = mem_base.offset(aligned_mem_start);
+    #[test]
+    fn buffer_below_memory() {
+        assert!(!range_within(MEM_START, APP_BREAK, 0xff0, 0x20));
+    }
 
-    result
+    #[test]
+    fn buffer_wrapping_address_space() {
+        // A length that wraps the end round to below the break.
+        assert!(!range_within(MEM_START, APP_BREAK, 0x1400, usize::max_value() - 0x100));
+        assert!(!range_within(MEM_START, usize::max_value(), usize::max_value(), 2));
+    }
 }
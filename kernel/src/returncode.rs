@@ -0,0 +1,87 @@
+//! Standard return codes for system calls.
+//!
+//! Drivers return a `ReturnCode` from `subscribe`, `command` and `allow`.
+//! The kernel passes it to the app in r0 as a single integer: zero or a
+//! positive value on success and a negative error code on failure.
+
+use core::cmp;
+
+#[derive(Clone,Copy,Debug,PartialEq,Eq)]
+pub enum ReturnCode {
+    /// Success, returning a value to the app. Values above
+    /// `isize::max_value()` reach the app as `isize::max_value()`, so they
+    /// are not mistaken for an error code.
+    SuccessWithValue { value: usize },
+    /// Operation completed successfully
+    SUCCESS,
+    /// Generic failure condition
+    FAIL,
+    /// Underlying system is busy; retry
+    EBUSY,
+    /// The state requested is already set
+    EALREADY,
+    /// The component is powered down
+    EOFF,
+    /// Reservation required before use
+    ERESERVE,
+    /// An invalid parameter was passed
+    EINVAL,
+    /// Parameter passed was too large
+    ESIZE,
+    /// Operation canceled by a call
+    ECANCEL,
+    /// Memory required not available
+    ENOMEM,
+    /// Operation or command is unsupported
+    ENOSUPPORT,
+    /// Device does not exist
+    ENODEVICE,
+    /// Device is not physically installed
+    EUNINSTALLED,
+    /// Packet transmission not acknowledged
+    ENOACK,
+    /// The app's image does not permit it to use the driver or command
+    EPERM,
+}
+
+impl From<ReturnCode> for isize {
+    fn from(original: ReturnCode) -> isize {
+        match original {
+            ReturnCode::SuccessWithValue { value } => {
+                cmp::min(value, isize::max_value() as usize) as isize
+            }
+            ReturnCode::SUCCESS => 0,
+            ReturnCode::FAIL => -1,
+            ReturnCode::EBUSY => -2,
+            ReturnCode::EALREADY => -3,
+            ReturnCode::EOFF => -4,
+            ReturnCode::ERESERVE => -5,
+            ReturnCode::EINVAL => -6,
+            ReturnCode::ESIZE => -7,
+            ReturnCode::ECANCEL => -8,
+            ReturnCode::ENOMEM => -9,
+            ReturnCode::ENOSUPPORT => -10,
+            ReturnCode::ENODEVICE => -11,
+            ReturnCode::EUNINSTALLED => -12,
+            ReturnCode::ENOACK => -13,
+            ReturnCode::EPERM => -14,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ReturnCode;
+
+    fn to_isize(value: usize) -> isize {
+        ReturnCode::SuccessWithValue { value: value }.into()
+    }
+
+    #[test]
+    fn values_stay_positive() {
+        assert_eq!(to_isize(0), 0);
+        assert_eq!(to_isize(isize::max_value() as usize), isize::max_value());
+        assert_eq!(to_isize(isize::max_value() as usize + 1), isize::max_value());
+        assert_eq!(to_isize(usize::max_value()), isize::max_value());
+    }
+}
@@ -1,67 +1,196 @@
 use common::Queue;
+use core::ptr::write_volatile;
+use mpu::{Permissions, Region};
 use platform::{Chip, Platform, MPU, SysTick};
 use process;
 use process::Process;
+use returncode::ReturnCode;
 use syscall;
 
+/// A process is not switched to if less than this much of its timeslice is
+/// left, in microseconds.
+const MIN_QUANTA_THRESHOLD_US: u32 = 500;
+
+/// Without a timeslice, the SysTick counts down from this many microseconds,
+/// without interrupting, to measure how long the process runs. It is close
+/// to the longest interval the SysTick supports.
+const MEASURE_INTERVAL_US: u32 = 400000;
+
+/// Programs MPU region `region_num` with `region`, or disables it if there is
+/// no region.
+fn set_region<M: MPU>(mpu: &M, region_num: u32, region: Option<Region>) {
+    match region {
+        Some(region) => mpu.set_region(region_num, region),
+        None => mpu.disable_region(region_num),
+    }
+}
+
+/// Whether a driver accepted a system call.
+fn succeeded(res: ReturnCode) -> bool {
+    match res {
+        ReturnCode::SUCCESS |
+        ReturnCode::SuccessWithValue { .. } => true,
+        _ => false,
+    }
+}
+
+/// Runs `process` until it yields, an interrupt is pending or `timeslice`
+/// microseconds pass. With no timeslice the process is never preempted by the
+/// timer.
 pub unsafe fn do_process<P: Platform, C: Chip>(platform: &mut P,
                                                chip: &mut C,
                                                process: &mut Process,
-                                               appid: ::AppId) {
+                                               appid: ::AppId,
+                                               timeslice: Option<u32>) {
+    process::CURRENT_PROCESS = Some(appid.idx());
     let systick = chip.systick();
     systick.reset();
-    systick.set_timer(10000);
-    systick.enable(true);
+    match timeslice {
+        Some(us) => {
+            systick.set_timer(us);
+            systick.enable(true);
+        }
+        None => {
+            systick.set_timer(MEASURE_INTERVAL_US);
+            systick.enable(false);
+        }
+    }
+
+    let mut expired = false;
 
     loop {
-        if chip.has_pending_interrupts() || systick.overflowed() || systick.value() <= 500 {
+        if chip.has_pending_interrupts() || expired {
+            break;
+        }
+        if timeslice.is_some() &&
+           (systick.overflowed() || systick.value() <= MIN_QUANTA_THRESHOLD_US) {
             break;
         }
 
         match process.state {
             process::State::Running => {
-                let (data_start, data_len, text_start, text_len) = process.memory_regions();
-                // Data segment read/write/execute
-                chip.mpu().set_mpu(0, data_start as u32, data_len as u32, true, 0b011);
+                // Data segment read/write/execute, up to the app break
+                set_region(chip.mpu(), 0, process.data_region());
                 // Text segment read/execute (no write)
-                chip.mpu().set_mpu(1, text_start as u32, text_len as u32, true, 0b111);
-                systick.enable(true);
+                set_region(chip.mpu(),
+                           1,
+                           Region::covering(process.flash_start() as usize,
+                                            process.flash_len(),
+                                            Permissions::ReadExecuteOnly));
+                // Buffers other processes shared over IPC read/write (no execute)
+                for i in 0..process::NUM_SHARED_REGIONS {
+                    let region = process.shared_region(i).and_then(|(start, len)| {
+                        Region::covering(start, len, Permissions::ReadWriteOnly)
+                    });
+                    set_region(chip.mpu(), 2 + i as u32, region);
+                }
+                // Stack guard no access. It overrides the data region, which has
+                // a lower number.
+                set_region(chip.mpu(), 6, process.stack_guard_region());
+                let slice_left = systick.value();
+                if timeslice.is_some() {
+                    systick.enable(true);
+                }
                 process.switch_to();
                 systick.enable(false);
+
+                // Reading the overflow flag clears it, so remember it for the
+                // check at the top of the loop.
+                let overflowed = systick.overflowed();
+                if overflowed {
+                    process.statistics.cpu_time_us += slice_left as u64;
+                } else {
+                    let used = slice_left.saturating_sub(systick.value());
+                    process.statistics.cpu_time_us += used as u64;
+                }
+                if timeslice.is_some() {
+                    expired = overflowed;
+                    if expired {
+                        process.statistics.timeslice_expirations += 1;
+                    }
+                } else if overflowed {
+                    // The process ran longer than the timer measures, and is
+                    // undercounted. Start measuring again for the next run.
+                    systick.set_timer(MEASURE_INTERVAL_US);
+                }
+
+                if process.app_fault() {
+                    process.fault_state();
+                    break;
+                }
             }
             process::State::Yielded => {
                 match process.callbacks.dequeue() {
                     None => break,
                     Some(cb) => {
+                        process.statistics.callbacks_delivered += 1;
                         process.state = process::State::Running;
                         process.push_callback(cb);
                         continue;
                     }
                 }
             }
+            process::State::Faulted | process::State::Terminated => break,
         }
 
         if !process.syscall_fired() {
             break;
         }
 
-        match process.svc_number() {
+        let svc_number = process.svc_number();
+        svc_number.map(|svc| {
+            process.statistics.syscalls.get_mut(svc as usize).map(|count| *count += 1);
+        });
+
+        // Refuse calls to drivers and commands the app's image does not list
+        // before they reach the driver.
+        let permitted = match svc_number {
+            Some(syscall::SUBSCRIBE) |
+            Some(syscall::ALLOW) => process.permissions().allows_driver(process.r0()),
+            Some(syscall::COMMAND) => {
+                process.permissions().allows_command(process.r0(), process.r1())
+            }
+            _ => true,
+        };
+        if !permitted {
+            process.set_r0(ReturnCode::EPERM.into());
+            continue;
+        }
+
+        match svc_number {
             Some(syscall::MEMOP) => {
                 let brk_type = process.r0();
                 let r1 = process.r1();
 
+                // App memory and flash lie below `isize::max_value()` on
+                // every supported chip, so addresses are never clamped.
+                let value = |value: usize| {
+                    debug_assert!(value <= isize::max_value() as usize);
+                    ReturnCode::SuccessWithValue { value: value }
+                };
                 let res = match brk_type {
                     0 /* BRK */ => {
                         process.brk(r1 as *const u8)
-                            .map(|_| 0).unwrap_or(-1)
+                            .map(|_| ReturnCode::SUCCESS).unwrap_or_else(|err| err.into())
                     },
                     1 /* SBRK */ => {
                         process.sbrk(r1 as isize)
-                            .map(|addr| addr as isize).unwrap_or(-1)
+                            .map(|addr| value(addr as usize)).unwrap_or_else(|err| err.into())
                     },
-                    _ => -2
+                    2 /* Memory start */ => value(process.mem_start() as usize),
+                    3 /* Memory end */ => value(process.mem_end() as usize),
+                    4 /* App break */ => value(process.app_memory_break() as usize),
+                    5 /* Kernel break */ => value(process.kernel_memory_break() as usize),
+                    6 /* Flash start */ => value(process.flash_start() as usize),
+                    7 /* Flash length */ => value(process.flash_len()),
+                    8 /* Stack bottom */ => value(process.stack_bottom() as usize),
+                    _ => ReturnCode::ENOSUPPORT
                 };
-                process.set_r0(res);
+                process.set_r0(res.into());
+            }
+            Some(syscall::EXIT) => {
+                process.terminate();
+                break;
             }
             Some(syscall::YIELD) => {
                 process.state = process::State::Yielded;
@@ -70,6 +199,24 @@ pub unsafe fn do_process<P: Platform, C: Chip>(platform: &mut P,
                 // There might be already enqueued callbacks
                 continue;
             }
+            Some(syscall::YIELD_NO_WAIT) => {
+                let flag = process.r0() as *mut u8;
+                if process.in_exposed_bounds(flag, 1) {
+                    let callback = process.callbacks.dequeue();
+                    write_volatile(flag, callback.is_some() as u8);
+                    // Without a pending callback the app carries on from the
+                    // system call like any other.
+                    callback.map(|cb| {
+                        process.statistics.callbacks_delivered += 1;
+                        process.pop_syscall_stack();
+                        process.push_callback(cb);
+                    });
+                } else {
+                    // The app could not be told a callback ran, so leave them
+                    // all queued.
+                    process.set_r0(ReturnCode::EINVAL.into());
+                }
+            }
             Some(syscall::SUBSCRIBE) => {
                 let driver_num = process.r0();
                 let subdriver_num = process.r1();
@@ -77,43 +224,66 @@ pub unsafe fn do_process<P: Platform, C: Chip>(platform: &mut P,
                 let appdata = process.r3();
 
                 let res = platform.with_driver(driver_num, |driver| {
-                    let callback = ::Callback::new(appid, appdata, callback_ptr);
+                    // A null callback unsubscribes.
+                    let callback = if callback_ptr.is_null() {
+                        None
+                    } else {
+                        Some(::Callback::new(appid, appdata, callback_ptr))
+                    };
                     match driver {
-                        Some(d) => d.subscribe(subdriver_num, callback),
-                        None => -1,
+                        Some(d) => d.subscribe(subdriver_num, callback, appid),
+                        None => ReturnCode::ENODEVICE,
                     }
                 });
-                process.set_r0(res);
+                process.set_r0(res.into());
             }
             Some(syscall::COMMAND) => {
                 let res = platform.with_driver(process.r0(), |driver| {
                     match driver {
-                        Some(d) => d.command(process.r1(), process.r2(), appid),
-                        None => -1,
+                        Some(d) => d.command(process.r1(), process.r2(), process.r3(), appid),
+                        None => ReturnCode::ENODEVICE,
                     }
                 });
-                process.set_r0(res);
+                process.set_r0(res.into());
             }
             Some(syscall::ALLOW) => {
-                let res = platform.with_driver(process.r0(), |driver| {
+                let driver_num = process.r0();
+                let allow_num = process.r1();
+                let res = platform.with_driver(driver_num, |driver| {
                     match driver {
                         Some(d) => {
                             let start_addr = process.r2() as *mut u8;
                             let size = process.r3();
-                            if process.in_exposed_bounds(start_addr, size) {
-                                let slice = ::AppSlice::new(start_addr as *mut u8, size, appid);
-                                d.allow(appid, process.r1(), slice)
+                            if start_addr.is_null() || size == 0 {
+                                // A null or empty buffer takes back the
+                                // previous one, unless the driver is still
+                                // using it.
+                                let res = d.allow(appid, allow_num, None);
+                                if succeeded(res) {
+                                    process.unexpose(driver_num, allow_num);
+                                }
+                                res
+                            } else if !process.in_exposed_bounds(start_addr, size) {
+                                ReturnCode::EINVAL
+                            } else if !process.can_expose(driver_num, allow_num) {
+                                ReturnCode::ENOMEM
                             } else {
-                                -1
+                                let slice = ::AppSlice::new(start_addr, size, appid);
+                                let res = d.allow(appid, allow_num, Some(slice));
+                                if succeeded(res) {
+                                    process.expose(driver_num, allow_num, start_addr, size);
+                                }
+                                res
                             }
                         }
-                        None => -1,
+                        None => ReturnCode::ENODEVICE,
                     }
                 });
-                process.set_r0(res);
+                process.set_r0(res.into());
             }
             _ => {}
         }
     }
     systick.reset();
+    process::CURRENT_PROCESS = None;
 }
@@ -0,0 +1,146 @@
+//! Policies for choosing which process runs next.
+//!
+//! Boards pass a `Scheduler` to `kernel::main`, which asks it for a process
+//! each time through the main loop. The chosen process then runs until it
+//! yields, an interrupt becomes pending or its timeslice expires, at which
+//! point the scheduler is asked again.
+
+use process::{Process, State};
+
+pub trait Scheduler {
+    /// Returns the index in `processes` of the process to run next, or `None`
+    /// if no process is ready to run.
+    fn next(&mut self, processes: &[Option<Process<'static>>]) -> Option<usize>;
+
+    /// The timeslice, in microseconds, for the process returned by the last
+    /// call to `next`. `None` lets the process run until it yields.
+    fn timeslice(&self) -> Option<u32>;
+}
+
+fn is_ready(processes: &[Option<Process<'static>>], idx: usize) -> bool {
+    processes[idx].as_ref().map_or(false, |p| p.ready())
+}
+
+/// Runs ready processes in turn, each for at most one timeslice.
+pub struct RoundRobin {
+    next: usize,
+    timeslice_us: u32,
+}
+
+impl RoundRobin {
+    pub const fn new(timeslice_us: u32) -> RoundRobin {
+        RoundRobin {
+            next: 0,
+            timeslice_us: timeslice_us,
+        }
+    }
+}
+
+impl Scheduler for RoundRobin {
+    fn next(&mut self, processes: &[Option<Process<'static>>]) -> Option<usize> {
+        let len = processes.len();
+        for offset in 0..len {
+            let idx = (self.next + offset) % len;
+            if is_ready(processes, idx) {
+                self.next = (idx + 1) % len;
+                return Some(idx);
+            }
+        }
+        None
+    }
+
+    fn timeslice(&self) -> Option<u32> {
+        Some(self.timeslice_us)
+    }
+}
+
+/// Always runs the highest priority ready process.
+///
+/// `priorities[i]` is the priority of the process in slot `i`, with lower
+/// numbers running first. Processes without an entry have the lowest
+/// priority. Ready processes with equal priority take turns.
+///
+/// A lower priority process keeps running only until its timeslice expires
+/// or an interrupt is pending, so a higher priority process that receives a
+/// callback preempts it within one timeslice.
+pub struct FixedPriority<'a> {
+    priorities: &'a [usize],
+    last: usize,
+    timeslice_us: u32,
+}
+
+impl<'a> FixedPriority<'a> {
+    pub const fn new(priorities: &'a [usize], timeslice_us: u32) -> FixedPriority<'a> {
+        FixedPriority {
+            priorities: priorities,
+            last: 0,
+            timeslice_us: timeslice_us,
+        }
+    }
+
+    fn priority(&self, idx: usize) -> usize {
+        self.priorities.get(idx).map_or(usize::max_value(), |p| *p)
+    }
+}
+
+impl<'a> Scheduler for FixedPriority<'a> {
+    fn next(&mut self, processes: &[Option<Process<'static>>]) -> Option<usize> {
+        let len = processes.len();
+        let mut best: Option<usize> = None;
+        // Start after the last process run so that processes of equal
+        // priority alternate.
+        for offset in 1..(len + 1) {
+            let idx = (self.last + offset) % len;
+            if is_ready(processes, idx) {
+                let better = best.map_or(true, |b| self.priority(idx) < self.priority(b));
+                if better {
+                    best = Some(idx);
+                }
+            }
+        }
+        best.map(|idx| {
+            self.last = idx;
+            idx
+        })
+    }
+
+    fn timeslice(&self) -> Option<u32> {
+        Some(self.timeslice_us)
+    }
+}
+
+/// Runs each process until it yields.
+///
+/// Processes are never preempted by the timer. Interrupts are still serviced
+/// while a process runs, after which the same process is resumed.
+pub struct Cooperative {
+    round_robin: RoundRobin,
+    current: Option<usize>,
+}
+
+impl Cooperative {
+    pub const fn new() -> Cooperative {
+        Cooperative {
+            round_robin: RoundRobin::new(0),
+            current: None,
+        }
+    }
+}
+
+impl Scheduler for Cooperative {
+    fn next(&mut self, processes: &[Option<Process<'static>>]) -> Option<usize> {
+        // Keep running the current process until it is no longer running,
+        // i.e. it yielded, exited, faulted or was stopped.
+        let still_running = self.current.map_or(false, |idx| {
+            processes[idx].as_ref().map_or(false, |p| p.state == State::Running && p.ready())
+        });
+        if !still_running {
+            self.current = self.round_robin.next(processes);
+        }
+        self.current
+    }
+
+    fn timeslice(&self) -> Option<u32> {
+        None
+    }
+}
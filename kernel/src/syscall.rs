@@ -3,3 +3,14 @@ pub const SUBSCRIBE: u8 = 1;
 pub const COMMAND: u8 = 2;
 pub const ALLOW: u8 = 3;
 pub const MEMOP: u8 = 4;
+pub const EXIT: u8 = 5;
+
+/// Runs one pending callback if there is one, and otherwise returns straight
+/// away. A delivered callback returns directly to the app, so rather than
+/// returning a value the kernel writes whether a callback ran (1 or 0) to the
+/// byte r0 points to. If that byte is not in the app's memory, no callback
+/// runs and the call returns `EINVAL`.
+pub const YIELD_NO_WAIT: u8 = 6;
+
+/// Number of system call types, used to size per-process counters.
+pub const NUM_SYSCALLS: usize = 7;
@@ -8,30 +8,24 @@ extern unsigned int* _bss;
 extern unsigned int* _ebss;
 extern int main();
 
-#ifndef STACK_SIZE
-#define STACK_SIZE 2048
-#endif
-
 __attribute__ ((section(".start"), used, naked))
-void _start(void* mem_start,
+void _start(__attribute__((unused))void* mem_start,
     __attribute__((unused))void* app_memory_break,
     __attribute__((unused))void* kernel_memory_break) {
 
-  /* Setup the stack and heap.
-   * We setup the stack at the bottom of memory (directory after the GOT, data
-   * and BSS segments. The stack size is, therefore, fixed at load time, so
-   * adjust it appropriately using `-DSTACK_SIZE 1024` during compilation.
+  /* The kernel sets up the stack and heap before starting the app.
+   * The stack sits at the bottom of memory, after the GOT, data and BSS
+   * segments and a small guard region the app cannot access, and is sized
+   * from the minimum stack size in the app's TBF header (set with
+   * `STACK_SIZE` in the app Makefile). The stack pointer starts at its top.
+   * Overflowing the stack into the guard region faults the app.
    *
-   * The heap will begin directly above the stack, and grow upwards towards
-   * kernel borrowed heap (which grows downwards from the top of memory).
+   * The heap begins at `app_memory_break`, directly above the stack, and
+   * grows upwards towards kernel borrowed heap (which grows downwards from
+   * the top of memory). The app cannot access memory above its break,
+   * rounded up to one eighth of its memory.
    */
-  void* stack_bottom = mem_start + STACK_SIZE;
-  memop(0, (int)stack_bottom);
-
-  __asm volatile ("mov sp, %0\n\t" : : "r" (stack_bottom));
-
   main();
 
   while(1) { yield(); }
 }
-
@@ -1,42 +1,44 @@
 #include "gpio.h"
 
 int gpio_enable_output(GPIO_Pin_t pin) {
-  return command(GPIO_DRIVER_NUM, 0, pin);
+  return command(GPIO_DRIVER_NUM, 0, pin, 0);
 }
 
 int gpio_set(GPIO_Pin_t pin) {
-  return command(GPIO_DRIVER_NUM, 1, pin);
+  return command(GPIO_DRIVER_NUM, 1, pin, 0);
 }
 
 int gpio_clear(GPIO_Pin_t pin) {
-  return command(GPIO_DRIVER_NUM, 2, pin);
+  return command(GPIO_DRIVER_NUM, 2, pin, 0);
 }
 
 int gpio_toggle(GPIO_Pin_t pin) {
-  return command(GPIO_DRIVER_NUM, 3, pin);
+  return command(GPIO_DRIVER_NUM, 3, pin, 0);
 }
 
 int gpio_enable_input(GPIO_Pin_t pin, GPIO_InputMode_t pin_config) {
-  uint32_t data = ((pin_config & 0xFF) << 8) | (pin & 0xFF);
-  return command(GPIO_DRIVER_NUM, 4, data);
+  return command(GPIO_DRIVER_NUM, 4, pin, pin_config);
 }
 
 int gpio_read(GPIO_Pin_t pin) {
-  return command(GPIO_DRIVER_NUM, 5, pin);
+  return command(GPIO_DRIVER_NUM, 5, pin, 0);
 }
 
 int gpio_enable_interrupt(GPIO_Pin_t pin, GPIO_InputMode_t pin_config,
     GPIO_InterruptMode_t irq_config) {
-  uint32_t data = ((irq_config & 0xFF) << 16) | ((pin_config & 0xFF) << 8) | (pin & 0xFF);
-  return command(GPIO_DRIVER_NUM, 6, data);
+  int err = gpio_enable_input(pin, pin_config);
+  if (err < 0) {
+    return err;
+  }
+  return command(GPIO_DRIVER_NUM, 6, pin, irq_config);
 }
 
 int gpio_disable_interrupt(GPIO_Pin_t pin) {
-  return command(GPIO_DRIVER_NUM, 7, pin);
+  return command(GPIO_DRIVER_NUM, 7, pin, 0);
 }
 
 int gpio_disable(GPIO_Pin_t pin) {
-  return command(GPIO_DRIVER_NUM, 8, pin);
+  return command(GPIO_DRIVER_NUM, 8, pin, 0);
 }
 
 int gpio_interrupt_callback(subscribe_cb callback, void* callback_args) {
@@ -34,6 +34,9 @@ int gpio_enable_interrupt(GPIO_Pin_t pin, GPIO_InputMode_t pin_config,
     GPIO_InterruptMode_t irq_config);
 int gpio_disable_interrupt(GPIO_Pin_t pin);
 int gpio_disable(GPIO_Pin_t pin);
+// The callback receives the pin number, the pin value and the number of
+// interrupts dropped since the previous callback because the app's callback
+// queue was full.
 int gpio_interrupt_callback(subscribe_cb callback, void* callback_args);
 
 #ifdef __cplusplus
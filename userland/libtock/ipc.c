@@ -0,0 +1,30 @@
+#include "ipc.h"
+
+int ipc_register_service(const char* name, size_t len,
+                         subscribe_cb callback, void* ud) {
+  int err = allow(IPC_DRIVER_NUM, 0, (void*)name, len);
+  if (err < 0) {
+    return err;
+  }
+  return subscribe(IPC_DRIVER_NUM, 0, callback, ud);
+}
+
+int ipc_discover(const char* name, size_t len) {
+  return allow(IPC_DRIVER_NUM, 1, (void*)name, len);
+}
+
+int ipc_share(int service, void* buf, size_t len) {
+  return allow(IPC_DRIVER_NUM, 2 + service, buf, len);
+}
+
+int ipc_notify_service(int service) {
+  return command(IPC_DRIVER_NUM, 0, service, 0);
+}
+
+int ipc_register_client_callback(subscribe_cb callback, void* ud) {
+  return subscribe(IPC_DRIVER_NUM, 1, callback, ud);
+}
+
+int ipc_notify_client(int client) {
+  return command(IPC_DRIVER_NUM, 1, client, 0);
+}
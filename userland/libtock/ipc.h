@@ -0,0 +1,41 @@
+#ifndef _IPC_H
+#define _IPC_H
+
+#include <tock.h>
+
+#define IPC_DRIVER_NUM 0xff
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+// Offers a service under `name`, which must stay unchanged while the service
+// is offered. `callback` is called with the id of the client, and the length
+// and address of the buffer the client shared with this service.
+int ipc_register_service(const char* name, size_t len,
+                         subscribe_cb callback, void* ud);
+
+// Returns the id of the service registered under `name`, or a negative value
+// if there is none.
+int ipc_discover(const char* name, size_t len);
+
+// Shares `buf` with a service. The buffer must be a power of two long, at
+// least 32 bytes and aligned to its length.
+int ipc_share(int service, void* buf, size_t len);
+
+// Notifies a service, which gains access to the buffer shared with it.
+int ipc_notify_service(int service);
+
+// Registers the callback called when a service notifies this process, with
+// the id of the service and the length and address of the buffer shared
+// with it.
+int ipc_register_client_callback(subscribe_cb callback, void* ud);
+
+// Notifies a client of this process's service.
+int ipc_notify_client(int client);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif // _IPC_H
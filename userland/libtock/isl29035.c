@@ -38,6 +38,6 @@ int isl29035_subscribe(subscribe_cb callback, void* userdata) {
 }
 
 int isl29035_start_intensity_reading() {
-  return command(6, 0, 0);
+  return command(6, 0, 0, 0);
 }
 
@@ -15,6 +15,6 @@ void nrf51822_serialization_write (char* tx, int tx_len) {
   allow(5, 1, tx, tx_len);
 
   // Do the write!!!!!
-  command(5, 0, 0);
+  command(5, 0, 0, 0);
 }
 
@@ -0,0 +1,29 @@
+#include "process_info.h"
+
+int process_info_count(void) {
+  return command(PROCESS_INFO_DRIVER_NUM, 0, 0, 0);
+}
+
+int process_info_syscalls(uint32_t process, uint32_t syscall) {
+  return command(PROCESS_INFO_DRIVER_NUM, 1, process, syscall);
+}
+
+int process_info_callbacks_delivered(uint32_t process) {
+  return command(PROCESS_INFO_DRIVER_NUM, 2, process, 0);
+}
+
+int process_info_callbacks_dropped(uint32_t process) {
+  return command(PROCESS_INFO_DRIVER_NUM, 3, process, 0);
+}
+
+int process_info_timeslice_expirations(uint32_t process) {
+  return command(PROCESS_INFO_DRIVER_NUM, 4, process, 0);
+}
+
+int process_info_cpu_time_ms(uint32_t process) {
+  return command(PROCESS_INFO_DRIVER_NUM, 5, process, 0);
+}
+
+int process_info_self(void) {
+  return command(PROCESS_INFO_DRIVER_NUM, 6, 0, 0);
+}
@@ -0,0 +1,28 @@
+#ifndef _PROCESS_INFO_H
+#define _PROCESS_INFO_H
+
+#include <tock.h>
+
+#define PROCESS_INFO_DRIVER_NUM 7
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+// All functions taking a process index return a negative value if there is
+// no process in that slot.
+int process_info_count(void);
+// Number of system calls of type `syscall` (the svc number) made by
+// `process`. The kernel takes the two as separate command arguments.
+int process_info_syscalls(uint32_t process, uint32_t syscall);
+int process_info_callbacks_delivered(uint32_t process);
+int process_info_callbacks_dropped(uint32_t process);
+int process_info_timeslice_expirations(uint32_t process);
+int process_info_cpu_time_ms(uint32_t process);
+int process_info_self(void);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif // _PROCESS_INFO_H
@@ -1,19 +1,20 @@
 #include "spi.h"
 
 int spi_init() {return 0;}
-int spi_set_chip_select(unsigned char cs) {return command(4, 2, cs);}
-int spi_get_chip_select()                 {return command(4, 3, 0);}
-int spi_set_rate(int rate)                {return command(4, 4, rate);}
-int spi_get_rate()                        {return command(4, 5, 0);}
-int spi_set_phase(bool phase)             {return command(4, 6, (unsigned char)phase);}
-int spi_get_phase()                       {return command(4, 7, 0);}
-int spi_set_polarity(bool pol)            {return command(4, 8, (unsigned char)pol);}
-int spi_get_polarity()                    {return command(4, 9, 0);}
-int spi_hold_low()                        {return command(4, 10, 0);}
-int spi_release_low()                     {return command(4, 11, 0);}
+int spi_set_chip_select(unsigned char cs) {return command(4, 2, cs, 0);}
+int spi_get_chip_select()                 {return command(4, 3, 0, 0);}
+int spi_set_rate(int rate)                {return command(4, 4, rate, 0);}
+int spi_get_rate()                        {return command(4, 5, 0, 0);}
+int spi_set_phase(bool phase)             {return command(4, 6, (unsigned char)phase, 0);}
+int spi_get_phase()                       {return command(4, 7, 0, 0);}
+int spi_set_polarity(bool pol)            {return command(4, 8, (unsigned char)pol, 0);}
+int spi_get_polarity()                    {return command(4, 9, 0, 0);}
+int spi_hold_low()                        {return command(4, 10, 0, 0);}
+int spi_release_low()                     {return command(4, 11, 0, 0);}
+int spi_configure(int rate, int mode)     {return command(4, 12, rate, mode);}
 
 int spi_write_byte(unsigned char byte) {
-  return command(4, 0, byte);
+  return command(4, 0, byte, 0);
 }
 
 int spi_read_buf(const char* str, size_t len) {
@@ -39,7 +40,7 @@ int spi_write(const char* str,
   if (err < 0 ) {
     return err;
   }
-  return command(4, 1, len);
+  return command(4, 1, len, 0);
 }
 
 int spi_read_write(const char* write,
@@ -25,6 +25,9 @@ int spi_set_polarity(bool pol);
 int spi_get_polarity();
 int spi_hold_low();
 int spi_release_low();
+/* Sets the rate and the SPI mode (0-3) of the current
+ * peripheral in one call. Returns the rate actually set. */
+int spi_configure(int rate, int mode);
 int spi_write_byte(unsigned char byte);
 int spi_write(const char* str, size_t len, subscribe_cb cb, bool* cond);
 int spi_read_write(const char* write, char* read, size_t len, subscribe_cb cb, bool* cond);
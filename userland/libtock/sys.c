@@ -50,6 +50,7 @@ int _read(int fd, void *buf, uint32_t count)
 }
 void _exit(int __status)
 {
+  tock_exit((uint32_t)__status);
   while(666) {}
 }
 void abort()
@@ -19,13 +19,13 @@ int timer_subscribe(subscribe_cb cb, void *userdata) {
 }
 
 int timer_oneshot(uint32_t interval_ms) {
-  return command(3, 0, (int)interval_ms);
+  return command(3, 0, (int)interval_ms, 0);
 }
 
 int timer_start_repeating(uint32_t interval_ms) {
-  return command(3, 1, (int)interval_ms);
+  return command(3, 1, (int)interval_ms, 0);
 }
 
 int timer_stop() {
-  return command(3, 2, 0);
+  return command(3, 2, 0, 0);
 }
@@ -49,7 +49,7 @@ int tmp006_read_async(subscribe_cb callback, void* callback_args) {
 // enable TMP006, configure periodic sampling with interrupts, callback with value on interrupt
 int tmp006_start_sampling(uint8_t period, subscribe_cb callback, void* callback_args) {
     // set period for periodic temp readings
-    uint32_t err_code = command(2, 0, period);
+    uint32_t err_code = command(2, 0, period, 0);
     if (err_code != ERR_NONE) {
         return err_code;
     }
@@ -63,6 +63,6 @@ int tmp006_start_sampling(uint8_t period, subscribe_cb callback, void* callback_
 int tmp006_stop_sampling(void) {
     // unsubscribe from periodic temp value callbacks
     //  also disables the temperature sensor
-    return command(2, 1, 0);
+    return command(2, 1, 0, 0);
 }
 
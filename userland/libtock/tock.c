@@ -15,6 +15,18 @@ void __attribute__((naked)) yield() {
   asm volatile("push {lr}\nsvc 0\npop {pc}" ::: "memory", "r0");
 }
 
+// A delivered callback returns straight to the instruction after the svc, so
+// the kernel reports whether it ran through `delivered` rather than r0.
+static void __attribute__((naked)) yield_no_wait_svc(volatile uint8_t* delivered) {
+  asm volatile("push {lr}\nsvc 6\npop {pc}" ::: "memory", "r0");
+}
+
+bool yield_no_wait(void) {
+  volatile uint8_t delivered = 0;
+  yield_no_wait_svc(&delivered);
+  return delivered != 0;
+}
+
 int __attribute__((naked)) subscribe(uint32_t driver, uint32_t subscribe,
               subscribe_cb cb, void* userdata) {
   asm volatile("svc 1\nbx lr" ::: "memory", "r0");
@@ -22,7 +34,7 @@ int __attribute__((naked)) subscribe(uint32_t driver, uint32_t subscribe,
 
 
 int __attribute__((naked))
-command(uint32_t driver, uint32_t command, int data) {
+command(uint32_t driver, uint32_t command, int data, int arg2) {
   asm volatile("svc 2\nbx lr" ::: "memory", "r0");
 }
 
@@ -35,3 +47,35 @@ int __attribute__((naked)) memop(uint32_t op_type, int arg1) {
   asm volatile("svc 4\nbx lr" ::: "memory", "r0");
 }
 
+void* tock_app_memory_begins_at(void) {
+  return (void*)memop(2, 0);
+}
+
+void* tock_app_memory_ends_at(void) {
+  return (void*)memop(3, 0);
+}
+
+void* tock_app_break(void) {
+  return (void*)memop(4, 0);
+}
+
+void* tock_kernel_break(void) {
+  return (void*)memop(5, 0);
+}
+
+void* tock_app_flash_begins_at(void) {
+  return (void*)memop(6, 0);
+}
+
+size_t tock_app_flash_len(void) {
+  return (size_t)memop(7, 0);
+}
+
+void* tock_app_stack_bottom(void) {
+  return (void*)memop(8, 0);
+}
+
+void __attribute__((naked)) tock_exit(uint32_t exit_code) {
+  asm volatile("svc 5" ::: "memory");
+}
+
@@ -9,11 +9,35 @@
 extern "C" {
 #endif
 
+// Return codes for system calls. Non-negative values mean success; commands
+// that return a value return it in place of TOCK_SUCCESS. Prefixed to avoid
+// clashing with the errno values in <errno.h>.
+#define TOCK_SUCCESS 0
+#define TOCK_FAIL -1
+#define TOCK_EBUSY -2
+#define TOCK_EALREADY -3
+#define TOCK_EOFF -4
+#define TOCK_ERESERVE -5
+#define TOCK_EINVAL -6
+#define TOCK_ESIZE -7
+#define TOCK_ECANCEL -8
+#define TOCK_ENOMEM -9
+#define TOCK_ENOSUPPORT -10
+#define TOCK_ENODEVICE -11
+#define TOCK_EUNINSTALLED -12
+#define TOCK_ENOACK -13
+#define TOCK_EPERM -14
+
 typedef void (subscribe_cb)(int, int, int,void*);
 
 void yield();
 void yield_for(bool*);
-int command(uint32_t driver, uint32_t command, int data);
+
+// Runs one pending callback if there is one, without waiting for one.
+// Returns whether a callback ran.
+bool yield_no_wait(void);
+
+int command(uint32_t driver, uint32_t command, int data, int arg2);
 int subscribe(uint32_t driver, uint32_t subscribe,
               subscribe_cb cb, void* userdata);
 int allow(uint32_t driver, uint32_t allow, void* ptr, size_t size);
@@ -21,8 +45,29 @@ int allow(uint32_t driver, uint32_t allow, void* ptr, size_t size);
 // op_type can be:
 // 0: brk, arg1 is pointer to new memory break
 // 1: sbrk, arg1 is increment to increase/decrease memory break
+//    The break cannot be lowered below a buffer the app has passed to allow.
+// 2: returns the start of the app's memory
+// 3: returns the end of the app's memory
+// 4: returns the app memory break
+// 5: returns the kernel memory break, below which the app heap cannot grow
+// 6: returns the start of the app's flash
+// 7: returns the length of the app's flash
+// 8: returns the lowest address of the stack, which grows down towards it
 int memop(uint32_t op_type, int arg1);
 
+// Helpers for the memop layout queries.
+void* tock_app_memory_begins_at(void);
+void* tock_app_memory_ends_at(void);
+void* tock_app_break(void);
+void* tock_kernel_break(void);
+void* tock_app_flash_begins_at(void);
+size_t tock_app_flash_len(void);
+void* tock_app_stack_bottom(void);
+
+// Terminates the app. Pending callbacks are dropped and drivers release any
+// state they hold for the app. Does not return.
+void tock_exit(uint32_t exit_code);
+
 
 #ifdef __cplusplus
 }
@@ -24,6 +24,33 @@ struct LoadInfo {
     got_end_offset: u32, // Offset in memory to end of GOT
     bss_start_offset: u32, // Offset in memory to start of BSS
     bss_end_offset: u32, // Offset in memory to end of BSS
+    min_stack_len: u32, // Minimum stack size
+    min_app_heap_len: u32, // Minimum size of the heap the app grows with brk/sbrk
+    min_kernel_heap_len: u32, // Minimum size of the heap the kernel allocates grants from
+    permissions_len: u32, // Number of entries in the permission list
+}
+
+/// An entry in the permission list, which follows the header: a driver the
+/// app may use and a mask of the commands it may send it.
+#[repr(C)]
+#[derive(Clone, Copy, Debug)]
+struct Permission {
+    driver: u32,
+    commands: u32,
+}
+
+/// A command mask that allows every command.
+const ALL_COMMANDS: u32 = 0xffffffff;
+
+const DEFAULT_STACK_LEN: u32 = 2048;
+const DEFAULT_APP_HEAP_LEN: u32 = 1024;
+const DEFAULT_KERNEL_HEAP_LEN: u32 = 1024;
+
+/// Memory the app asks the kernel to reserve for it.
+struct MemoryRequirements {
+    stack_len: u32,
+    app_heap_len: u32,
+    kernel_heap_len: u32,
 }
 
 fn main() {
@@ -31,12 +58,27 @@ fn main() {
     let program = args[0].clone();
     let mut opts = Options::new();
     opts.optopt("o", "", "set output file name", "OUTFILE");
+    opts.optopt("", "stack", "minimum stack size in bytes", "STACK_SIZE");
+    opts.optopt("", "app-heap", "minimum app heap size in bytes", "HEAP_SIZE");
+    opts.optopt("", "kernel-heap", "minimum kernel heap size in bytes", "HEAP_SIZE");
+    opts.optmulti("",
+                  "permit",
+                  "allow the app to use a driver, and only the listed commands if any are \
+                   given. An app with no --permit may not use any driver.",
+                  "DRIVER[:COMMAND,...]");
 
     let matches = match opts.parse(&args[1..]) {
         Ok(m) => m,
         Err(f) => panic!(f.to_string()),
     };
     let output = matches.opt_str("o");
+    let requirements = MemoryRequirements {
+        stack_len: parse_size(&matches, "stack", DEFAULT_STACK_LEN),
+        app_heap_len: parse_size(&matches, "app-heap", DEFAULT_APP_HEAP_LEN),
+        kernel_heap_len: parse_size(&matches, "kernel-heap", DEFAULT_KERNEL_HEAP_LEN),
+    };
+    let permissions: Vec<Permission> =
+        matches.opt_strs("permit").iter().map(|permit| parse_permission(permit)).collect();
     let input = if !matches.free.is_empty() {
         matches.free[0].clone()
     } else {
@@ -54,11 +96,11 @@ fn main() {
     match output {
             None => {
                 let mut out = io::stdout();
-                do_work(&file, &mut out)
+                do_work(&file, &requirements, &permissions, &mut out)
             }
             Some(name) => {
                 match File::create(Path::new(&name)) {
-                    Ok(mut f) => do_work(&file, &mut f),
+                    Ok(mut f) => do_work(&file, &requirements, &permissions, &mut f),
                     Err(e) => panic!("Error: {:?}", e),
                 }
             }
@@ -67,10 +109,59 @@ fn main() {
 }
 
 fn print_usage(program: &str, opts: Options) {
-    let brief = format!("Usage: {} [-o OUTFILE] FILE", program);
+    let brief = format!("Usage: {} [-o OUTFILE] [--stack SIZE] [--app-heap SIZE] \
+                         [--kernel-heap SIZE] [--permit DRIVER[:COMMAND,...]]... FILE",
+                        program);
     print!("{}", opts.usage(&brief));
 }
 
+fn parse_size(matches: &getopts::Matches, name: &str, default: u32) -> u32 {
+    match matches.opt_str(name) {
+        Some(size) => {
+            match size.parse() {
+                Ok(size) => size,
+                Err(e) => panic!("Error: invalid --{} {}: {:?}", name, size, e),
+            }
+        }
+        None => default,
+    }
+}
+
+/// Parses a `--permit` argument: a driver number, optionally followed by a
+/// colon and a comma-separated list of the command numbers allowed.
+fn parse_permission(permit: &str) -> Permission {
+    let parse = |number: &str| -> u32 {
+        match number.trim().parse() {
+            Ok(number) => number,
+            Err(e) => panic!("Error: invalid --permit {}: {:?}", permit, e),
+        }
+    };
+    let mut parts = permit.splitn(2, ':');
+    let driver = parse(parts.next().unwrap_or(""));
+    let commands = match parts.next() {
+        Some(commands) if commands.trim().is_empty() => {
+            panic!("Error: invalid --permit {}: list at least one command after the colon, or \
+                    drop the colon to allow every command",
+                   permit)
+        }
+        Some(commands) => {
+            commands.split(',').fold(0, |mask, command| {
+                let command = parse(command);
+                if command >= 32 {
+                    panic!("Error: invalid --permit {}: only commands 0 to 31 can be listed",
+                           permit);
+                }
+                mask | (1 << command)
+            })
+        }
+        None => ALL_COMMANDS,
+    };
+    Permission {
+        driver: driver,
+        commands: commands,
+    }
+}
+
 fn get_section<'a>(input: &'a elf::File, name: &str) -> elf::Section {
     match input.get_section(name) {
         Some(section) => {
@@ -103,7 +194,11 @@ unsafe fn as_byte_slice<'a, T: Copy>(input: &'a T) -> &'a [u8] {
     slice::from_raw_parts(input as *const T as *const u8, mem::size_of::<T>())
 }
 
-fn do_work(input: &elf::File, output: &mut Write) -> io::Result<()> {
+fn do_work(input: &elf::File,
+           requirements: &MemoryRequirements,
+           permissions: &[Permission],
+           output: &mut Write)
+           -> io::Result<()> {
     let (rel_data_size, rel_data) = match input.sections
         .iter()
         .find(|section| section.shdr.name == ".rel.data".as_ref()) {
@@ -116,8 +211,9 @@ fn do_work(input: &elf::File, output: &mut Write) -> io::Result<()> {
     let data = get_section(input, ".data");
     let bss = get_section(input, ".bss");
 
-    let mut total_len = (mem::size_of::<LoadInfo>() + rel_data.len() + text.data.len() +
-                         got.data.len() + data.data.len()) as u32;
+    let permissions_size = permissions.len() * mem::size_of::<Permission>();
+    let mut total_len = (mem::size_of::<LoadInfo>() + permissions_size + rel_data.len() +
+                         text.data.len() + got.data.len() + data.data.len()) as u32;
 
     let pad = if total_len.count_ones() > 1 {
         let power2len = 1 << (32 - total_len.leading_zeros());
@@ -137,9 +233,16 @@ fn do_work(input: &elf::File, output: &mut Write) -> io::Result<()> {
         got_end_offset: got.shdr.size as u32,
         bss_start_offset: bss.shdr.addr as u32,
         bss_end_offset: (bss.shdr.addr + bss.shdr.size) as u32,
+        min_stack_len: requirements.stack_len,
+        min_app_heap_len: requirements.app_heap_len,
+        min_kernel_heap_len: requirements.kernel_heap_len,
+        permissions_len: permissions.len() as u32,
     };
 
     try!(output.write_all(unsafe { as_byte_slice(&load_info) }));
+    for permission in permissions {
+        try!(output.write_all(unsafe { as_byte_slice(permission) }));
+    }
     try!(output.write_all(rel_data.as_ref()));
     try!(output.write_all(text.data.as_ref()));
     try!(output.write_all(got.data.as_ref()));